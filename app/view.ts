@@ -27,6 +27,24 @@ export default class LogicxView extends obs.TextFileView implements LogicxFile {
 
     setViewData(data: string, clear: boolean): void {
         this.logicx.setData(data, clear);
+        void this.loadMemories();
+    }
+
+    // Memory components keep the path of the file their contents came from, so pick up any changes to it
+    async loadMemories(): Promise<void> {
+        for (const path of this.logicx.memorySources()) {
+            const file = this.app.vault.getFileByPath(obs.normalizePath(path));
+
+            if (!file)
+                continue;
+
+            // A malformed image only leaves its own memory empty
+            try {
+                this.logicx.loadMemory(path, new Uint8Array(await this.app.vault.readBinary(file)));
+            } catch (err) {
+                new obs.Notice(`Couldn't load ${path}: ${err}`);
+            }
+        }
     }
 
     clear(): void {
@@ -0,0 +1,289 @@
+use crate::{
+    circuit::Circuit,
+    circuit::NetId,
+    circuit::NodeId,
+    logic::Value,
+    project::ComponentDriver,
+    project::ComponentId,
+    project::InstanceId,
+    project::Project
+};
+use std::collections::{
+    HashMap,
+    HashSet
+};
+
+/// Loops with more free variables than this are not classified, as every combination of them is tried.
+pub const CLASSIFY_LIMIT: usize = 16;
+
+/// A strongly connected group of instances, each of which feeds back into the others.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Loop {
+    pub instances: Vec<InstanceId>,
+    pub kind: LoopKind,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum LoopKind {
+    /// The loop settles into a stable state for every combination of the values feeding it, like an SR latch.
+    Latch,
+    /// There is at least one combination of the values feeding the loop under which it never settles.
+    Oscillator,
+    /// The loop was too large to classify. See [`CLASSIFY_LIMIT`].
+    Unknown,
+}
+
+/// Finds every combinational loop in the circuit and classifies it.
+///
+/// Loops inside a subcomponent are reported by the top-level instance of that subcomponent.
+pub fn loops(circuit: &Circuit) -> Vec<Loop> {
+    components(circuit)
+        .into_iter()
+        .map(|nodes| {
+            let mut instances = nodes.iter()
+                .map(|node| circuit.nodes[*node].root())
+                .collect::<Vec<_>>();
+            instances.sort_unstable();
+            instances.dedup();
+
+            Loop {
+                kind: classify(circuit, &nodes),
+                instances,
+            }
+        })
+        .collect()
+}
+
+/// Finds a component that contains itself, directly or through other subcomponents.
+/// Returns the chain of components that leads back to the first.
+pub fn recursion(project: &Project) -> Option<Vec<ComponentId>> {
+    fn visit(project: &Project, component: ComponentId, stack: &mut Vec<ComponentId>, done: &mut HashSet<ComponentId>) -> Option<Vec<ComponentId>> {
+        if let Some(start) = stack.iter().position(|id| *id == component) {
+            return Some(stack[start..].to_vec());
+        }
+
+        if !done.insert(component) {
+            return None;
+        }
+
+        let Some(ComponentDriver::Subcomponent { body, .. }) = project.components.get(&component).map(|component| &component.driver) else {
+            return None;
+        };
+
+        stack.push(component);
+
+        let mut children = body.values()
+            .map(|placement| placement.component)
+            .collect::<Vec<_>>();
+        children.sort_unstable();
+        children.dedup();
+
+        let cycle = children.into_iter()
+            .find_map(|child| visit(project, child, stack, done));
+
+        stack.pop();
+
+        cycle
+    }
+
+    let mut components = project.components.keys().copied().collect::<Vec<_>>();
+    components.sort_unstable();
+
+    let mut done = HashSet::new();
+
+    components.into_iter()
+        .find_map(|component| visit(project, component, &mut vec![], &mut done))
+}
+
+// Tarjan's algorithm, unrolled onto an explicit stack so that long chains of gates can't overflow the call stack.
+// Only components that actually contain a cycle are returned.
+pub(crate) fn components(circuit: &Circuit) -> Vec<Vec<NodeId>> {
+    let successors = |node: NodeId| circuit.nodes[node].outputs.iter()
+        .flat_map(|net| circuit.nets[*net].sinks.iter().copied())
+        .filter(|sink| !circuit.drivers[circuit.nodes[*sink].driver].is_edge_triggered())
+        .collect::<Vec<_>>();
+
+    let mut index = vec![None; circuit.nodes.len()];
+    let mut lowlink = vec![0; circuit.nodes.len()];
+    let mut on_stack = vec![false; circuit.nodes.len()];
+    let mut stack = vec![];
+    let mut counter = 0;
+
+    let mut result = vec![];
+
+    for root in 0..circuit.nodes.len() {
+        if index[root].is_some() {
+            continue;
+        }
+
+        let mut work = vec![(root, successors(root), 0)];
+        index[root] = Some(counter);
+        lowlink[root] = counter;
+        counter += 1;
+        stack.push(root);
+        on_stack[root] = true;
+
+        while let Some((node, next, position)) = work.last_mut() {
+            let node = *node;
+
+            if let Some(successor) = next.get(*position).copied() {
+                *position += 1;
+
+                match index[successor] {
+                    None => {
+                        index[successor] = Some(counter);
+                        lowlink[successor] = counter;
+                        counter += 1;
+                        stack.push(successor);
+                        on_stack[successor] = true;
+                        work.push((successor, successors(successor), 0));
+                    },
+                    Some(successor_index) if on_stack[successor] => lowlink[node] = lowlink[node].min(successor_index),
+                    Some(_) => {},
+                }
+
+                continue;
+            }
+
+            work.pop();
+
+            if let Some((parent, _, _)) = work.last() {
+                lowlink[*parent] = lowlink[*parent].min(lowlink[node]);
+            }
+
+            if Some(lowlink[node]) == index[node] {
+                let mut component = vec![];
+
+                while let Some(member) = stack.pop() {
+                    on_stack[member] = false;
+                    component.push(member);
+
+                    if member == node {
+                        break;
+                    }
+                }
+
+                if component.len() > 1 || successors(node).contains(&node) {
+                    component.sort_unstable();
+                    result.push(component);
+                }
+            }
+        }
+    }
+
+    result
+}
+
+// Looks for a fixed point of the loop under every combination of its external inputs.
+// A loop is latch-like if one always exists, and an oscillator if for some inputs none does.
+fn classify(circuit: &Circuit, nodes: &[NodeId]) -> LoopKind {
+    let internal = nodes.iter()
+        .flat_map(|node| circuit.nodes[*node].outputs.iter().copied())
+        .filter(|net| circuit.nets[*net].sinks.iter().any(|sink| nodes.contains(sink)))
+        .collect::<Vec<NetId>>();
+
+    let mut external = nodes.iter()
+        .flat_map(|node| circuit.nodes[*node].inputs.iter().flatten().copied())
+        .filter(|net| !nodes.contains(&circuit.nets[*net].driver))
+        .collect::<Vec<NetId>>();
+    external.sort_unstable();
+    external.dedup();
+
+    let bits = |nets: &[NetId]| nets.iter()
+        .map(|net| circuit.nets[*net].width as usize)
+        .sum::<usize>();
+
+    let (internal_bits, external_bits) = (bits(&internal), bits(&external));
+
+    if internal_bits + external_bits > CLASSIFY_LIMIT {
+        return LoopKind::Unknown;
+    }
+
+    // Slices a packed value up into one value per net
+    let unpack = |nets: &[NetId], packed: Value| nets.iter()
+        .scan(0, |offset, net| {
+            let width = circuit.nets[*net].width;
+            let value = packed.slice(*offset, width);
+            *offset += width;
+            Some((*net, value))
+        })
+        .collect::<Vec<_>>();
+
+    let stable = |values: &HashMap<NetId, Value>| nodes.iter().all(|index| {
+        let node = &circuit.nodes[*index];
+
+        let inputs = circuit.gather(*index, |net| values.get(&net).copied().unwrap_or(Value::x(circuit.nets[net].width)));
+        let widths = node.outputs.iter()
+            .map(|net| circuit.nets[*net].width)
+            .collect::<Vec<_>>();
+
+        let Some(outputs) = circuit.drivers[node.driver].evaluate_terminals(&inputs, &widths, circuit.time(), &mut circuit.state[*index].clone()) else {
+            return true;
+        };
+
+        node.outputs.iter()
+            .zip(outputs)
+            .filter(|(net, _)| values.contains_key(net))
+            .all(|(net, value)| values[net] == value)
+    });
+
+    let oscillates = (0..1u64 << external_bits).any(|outside| {
+        !(0..1u64 << internal_bits).any(|inside| {
+            let values = unpack(&external, Value::new(external_bits as u8, outside))
+                .into_iter()
+                .chain(unpack(&internal, Value::new(internal_bits as u8, inside)))
+                .collect::<HashMap<_, _>>();
+
+            stable(&values)
+        })
+    });
+
+    match oscillates {
+        true => LoopKind::Oscillator,
+        false => LoopKind::Latch,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::tests::{
+        connect,
+        place,
+        project
+    };
+
+    #[test]
+    fn latch() {
+        // Two cross-coupled NOR gates, each an OR followed by a NOT
+        let mut project = project();
+        place(&mut project, 0, Project::INPUT, "s");
+        place(&mut project, 1, Project::INPUT, "r");
+        place(&mut project, 2, Project::OR, "set");
+        place(&mut project, 3, Project::NOT, "q");
+        place(&mut project, 4, Project::OR, "reset");
+        place(&mut project, 5, Project::NOT, "q!");
+        connect(&mut project, (0, 0), (2, 0));
+        connect(&mut project, (5, 0), (2, 1));
+        connect(&mut project, (2, 0), (3, 0));
+        connect(&mut project, (3, 0), (4, 0));
+        connect(&mut project, (1, 0), (4, 1));
+        connect(&mut project, (4, 0), (5, 0));
+
+        let circuit = Circuit::compile(&project).unwrap();
+        assert_eq!(loops(&circuit), vec![Loop { instances: vec![2, 3, 4, 5], kind: LoopKind::Latch }]);
+    }
+
+    #[test]
+    fn oscillator() {
+        let mut project = project();
+        for instance in 0..3 {
+            place(&mut project, instance, Project::NOT, "not");
+            connect(&mut project, (instance, 0), ((instance + 1) % 3, 0));
+        }
+
+        let circuit = Circuit::compile(&project).unwrap();
+        assert_eq!(loops(&circuit), vec![Loop { instances: vec![0, 1, 2], kind: LoopKind::Oscillator }]);
+        assert!(!circuit.is_combinational());
+    }
+}
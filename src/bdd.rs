@@ -0,0 +1,339 @@
+use crate::{
+    expression::Expr,
+    logic::Logic
+};
+use serde::{
+    Deserialize,
+    Serialize
+};
+use std::collections::{
+    HashMap,
+    HashSet
+};
+
+/// A Boolean function stored in a [`Bdds`]. Functions from the same store are equal exactly when their handles are.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Bdd(usize);
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+struct Node {
+    variable: u32,
+    low: Bdd,
+    high: Bdd,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+enum Op {
+    And,
+    Or,
+    Xor,
+}
+
+/// A store of reduced, ordered binary decision diagrams over numbered variables, variable 0 at the top.
+///
+/// Every node is unique, so two functions are equivalent exactly when they are the same [`Bdd`],
+/// and results of operations are cached so that building up a large function stays polynomial in its size.
+pub struct Bdds {
+    nodes: Vec<Node>,
+    unique: HashMap<Node, Bdd>,
+    cache: HashMap<(Op, Bdd, Bdd), Bdd>,
+}
+
+impl Default for Bdds {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Bdds {
+    pub const FALSE: Bdd = Bdd(0);
+    pub const TRUE: Bdd = Bdd(1);
+
+    pub fn new() -> Self {
+        // The two terminals sit below every variable
+        let terminal = Node { variable: u32::MAX, low: Self::FALSE, high: Self::FALSE };
+
+        Self {
+            nodes: vec![terminal, terminal],
+            unique: HashMap::new(),
+            cache: HashMap::new(),
+        }
+    }
+
+    pub fn constant(&self, value: bool) -> Bdd {
+        match value {
+            true => Self::TRUE,
+            false => Self::FALSE,
+        }
+    }
+
+    /// The function that is true when variable `variable` is.
+    pub fn variable(&mut self, variable: u32) -> Bdd {
+        self.node(variable, Self::FALSE, Self::TRUE)
+    }
+
+    fn node(&mut self, variable: u32, low: Bdd, high: Bdd) -> Bdd {
+        if low == high {
+            return low;
+        }
+
+        let node = Node { variable, low, high };
+
+        if let Some(bdd) = self.unique.get(&node) {
+            return *bdd;
+        }
+
+        self.nodes.push(node);
+        self.unique.insert(node, Bdd(self.nodes.len() - 1));
+        Bdd(self.nodes.len() - 1)
+    }
+
+    pub fn not(&mut self, f: Bdd) -> Bdd {
+        self.xor(f, Self::TRUE)
+    }
+
+    pub fn and(&mut self, f: Bdd, g: Bdd) -> Bdd {
+        self.apply(Op::And, f, g)
+    }
+
+    pub fn or(&mut self, f: Bdd, g: Bdd) -> Bdd {
+        self.apply(Op::Or, f, g)
+    }
+
+    pub fn xor(&mut self, f: Bdd, g: Bdd) -> Bdd {
+        self.apply(Op::Xor, f, g)
+    }
+
+    /// Builds an expression, using `variable` for the function each of its variables stands for.
+    pub fn expression(&mut self, expr: &Expr, variable: &impl Fn(&str) -> Bdd) -> Bdd {
+        match expr {
+            Expr::Constant(constant) => self.constant(*constant),
+            Expr::Variable(name) => variable(name),
+            Expr::Not(inner) => {
+                let inner = self.expression(inner, variable);
+                self.not(inner)
+            },
+            Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) | Expr::Xor(lhs, rhs) => {
+                let (lhs, rhs) = (self.expression(lhs, variable), self.expression(rhs, variable));
+
+                match expr {
+                    Expr::And(..) => self.and(lhs, rhs),
+                    Expr::Or(..) => self.or(lhs, rhs),
+                    _ => self.xor(lhs, rhs),
+                }
+            },
+        }
+    }
+
+    fn apply(&mut self, op: Op, f: Bdd, g: Bdd) -> Bdd {
+        let terminal = match (op, f, g) {
+            (Op::And, Self::FALSE, _) | (Op::And, _, Self::FALSE) => Some(Self::FALSE),
+            (Op::And, Self::TRUE, other) | (Op::And, other, Self::TRUE) => Some(other),
+            (Op::Or, Self::TRUE, _) | (Op::Or, _, Self::TRUE) => Some(Self::TRUE),
+            (Op::Or, Self::FALSE, other) | (Op::Or, other, Self::FALSE) => Some(other),
+            (Op::Xor, Self::FALSE, other) | (Op::Xor, other, Self::FALSE) => Some(other),
+            (Op::Xor, f, g) if f == g => Some(Self::FALSE),
+            (_, f, g) if f == g => Some(f),
+            _ => None,
+        };
+
+        if let Some(result) = terminal {
+            return result;
+        }
+
+        // Every operation is commutative, so the cache only needs one order
+        let key = (op, f.min(g), f.max(g));
+
+        if let Some(result) = self.cache.get(&key) {
+            return *result;
+        }
+
+        let (a, b) = (self.nodes[f.0], self.nodes[g.0]);
+        let variable = a.variable.min(b.variable);
+
+        let cofactors = |node: Node, bdd: Bdd| match node.variable == variable {
+            true => (node.low, node.high),
+            false => (bdd, bdd),
+        };
+        let ((f0, f1), (g0, g1)) = (cofactors(a, f), cofactors(b, g));
+
+        let low = self.apply(op, f0, g0);
+        let high = self.apply(op, f1, g1);
+        let result = self.node(variable, low, high);
+
+        self.cache.insert(key, result);
+        result
+    }
+
+    /// Evaluates a function, reading the value of each variable from `assignment`.
+    pub fn evaluate(&self, f: Bdd, assignment: impl Fn(u32) -> bool) -> bool {
+        let mut bdd = f;
+
+        while bdd != Self::FALSE && bdd != Self::TRUE {
+            let node = self.nodes[bdd.0];
+            bdd = match assignment(node.variable) {
+                true => node.high,
+                false => node.low,
+            };
+        }
+
+        bdd == Self::TRUE
+    }
+
+    /// Some assignment that makes the function true, as the set of variables that are true in it.
+    /// Variables the function doesn't depend on are left false.
+    pub fn satisfy(&self, f: Bdd) -> Option<HashSet<u32>> {
+        if f == Self::FALSE {
+            return None;
+        }
+
+        let mut assignment = HashSet::new();
+        let mut bdd = f;
+
+        // Every path in a reduced diagram that doesn't end at FALSE can reach TRUE
+        while bdd != Self::TRUE {
+            let node = self.nodes[bdd.0];
+
+            bdd = match node.low {
+                Self::FALSE => {
+                    assignment.insert(node.variable);
+                    node.high
+                },
+                low => low,
+            };
+        }
+
+        Some(assignment)
+    }
+
+    /// Builds each of the `width` output bits of a truth table as a function of `inputs`, the way
+    /// [`ComponentDriver::TruthTable`](crate::project::ComponentDriver::TruthTable) packs them. Missing rows output 0.
+    pub fn truth_table(&mut self, truth: &HashMap<u64, u64>, inputs: &[Bdd], width: usize) -> Vec<Bdd> {
+        let mut outputs = vec![Self::FALSE; width];
+
+        for (key, value) in truth.iter() {
+            // Rows wider than the inputs can never be looked up
+            if inputs.len() < 64 && key >> inputs.len() != 0 {
+                continue;
+            }
+
+            // The function that is true on exactly this row
+            let mut row = Self::TRUE;
+            for (bit, input) in inputs.iter().enumerate() {
+                let literal = match key >> bit & 1 {
+                    1 => *input,
+                    _ => self.not(*input),
+                };
+                row = self.and(row, literal);
+            }
+
+            for (bit, output) in outputs.iter_mut().enumerate() {
+                if value >> bit & 1 == 1 {
+                    *output = self.or(*output, row);
+                }
+            }
+        }
+
+        outputs
+    }
+
+    /// Lists the nodes of some functions so they can be stored, sharing every node they have in common.
+    pub fn export(&self, roots: &[Bdd]) -> BddTable {
+        fn visit(bdds: &Bdds, bdd: Bdd, index: &mut HashMap<Bdd, usize>, nodes: &mut Vec<(u32, usize, usize)>) -> usize {
+            if let Some(index) = index.get(&bdd) {
+                return *index;
+            }
+
+            let node = bdds.nodes[bdd.0];
+            let low = visit(bdds, node.low, index, nodes);
+            let high = visit(bdds, node.high, index, nodes);
+
+            nodes.push((node.variable, low, high));
+            index.insert(bdd, nodes.len() + 1);
+            nodes.len() + 1
+        }
+
+        let mut index = HashMap::from([(Self::FALSE, 0), (Self::TRUE, 1)]);
+        let mut nodes = vec![];
+
+        let outputs = roots.iter()
+            .map(|root| visit(self, *root, &mut index, &mut nodes))
+            .collect();
+
+        BddTable { nodes, outputs }
+    }
+
+    /// Brings a stored table back into the store, substituting `inputs[n]` for variable `n`.
+    /// Fails if a node refers to a variable without an input.
+    pub fn load(&mut self, table: &BddTable, inputs: &[Bdd]) -> Option<Vec<Bdd>> {
+        let mut loaded = vec![Self::FALSE, Self::TRUE];
+
+        for (variable, low, high) in table.nodes.iter() {
+            let condition = *inputs.get(*variable as usize)?;
+            let (low, high) = (loaded[*low], loaded[*high]);
+
+            let when = self.and(condition, high);
+            let otherwise = self.not(condition);
+            let otherwise = self.and(otherwise, low);
+
+            loaded.push(self.or(when, otherwise));
+        }
+
+        Some(table.outputs.iter()
+            .map(|output| loaded[*output])
+            .collect())
+    }
+}
+
+/// Binary decision diagrams in a form that can be stored in a project, for truth tables too wide to list row by row.
+///
+/// Each node is `(variable, low, high)`, where a child of 0 or 1 is that constant and `n + 2` is the `n`th node,
+/// so every node comes after its children. Variable `n` is input bit `n`, and each output bit names its root.
+/// Unlike in [`Bdds`], the variables may be tested in any order, so a table can keep whichever order suits it best.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct BddTable {
+    pub(crate) nodes: Vec<(u32, usize, usize)>,
+    pub(crate) outputs: Vec<usize>,
+}
+
+impl BddTable {
+    /// Converts a truth table with `inputs` input bits and `outputs` output bits.
+    pub fn from_truth(truth: &HashMap<u64, u64>, inputs: u32, outputs: usize) -> Self {
+        let mut bdds = Bdds::new();
+        let variables = (0..inputs)
+            .map(|variable| bdds.variable(variable))
+            .collect::<Vec<_>>();
+        let roots = bdds.truth_table(truth, &variables, outputs);
+
+        bdds.export(&roots)
+    }
+
+    /// Whether every child comes before its parent and every variable is below `inputs`.
+    pub fn is_valid(&self, inputs: usize) -> bool {
+        let children_first = self.nodes.iter()
+            .enumerate()
+            .all(|(index, (variable, low, high))| *low < index + 2 && *high < index + 2 && (*variable as usize) < inputs);
+
+        children_first && self.outputs.iter().all(|output| *output < self.nodes.len() + 2)
+    }
+
+    /// Evaluates every output bit. An unknown input only makes an output unknown if the output depends on it.
+    pub fn evaluate(&self, input: impl Fn(u32) -> Logic) -> Vec<Logic> {
+        let mut values = vec![Logic::Zero, Logic::One];
+
+        for (variable, low, high) in self.nodes.iter() {
+            let (low, high) = (values[*low], values[*high]);
+
+            values.push(match input(*variable) {
+                Logic::Zero => low,
+                Logic::One => high,
+                _ if low == high => low,
+                _ => Logic::X,
+            });
+        }
+
+        self.outputs.iter()
+            .map(|output| values[*output])
+            .collect()
+    }
+}
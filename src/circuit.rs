@@ -1,3 +1,1561 @@
+use crate::{
+    analysis,
+    bdd::BddTable,
+    expression::Expr,
+    logic::Logic,
+    logic::Value,
+    memory,
+    project::Component,
+    project::ComponentDriver,
+    project::ComponentId,
+    project::Connection,
+    project::FlipFlopKind,
+    project::InstanceId,
+    project::LatchKind,
+    project::MemoryKind,
+    project::Placement,
+    project::Port,
+    project::Project,
+    project::Terminal,
+    script::Program,
+    trace::Trace,
+    Result
+};
+use std::{
+    cmp::Ordering,
+    cmp::Reverse,
+    collections::BinaryHeap,
+    collections::HashMap,
+};
+
+pub type NodeId = usize;
+pub type NetId = usize;
+
+/// The default number of passes through the event queue before a circuit is considered unstable.
+pub const ITERATION_LIMIT: usize = 10_000;
+
+/// A truth table with more unknown input bits than this outputs all X rather than trying every combination of them.
+pub const EXPAND_LIMIT: u32 = 8;
+
+/// A netlist compiled from a [`Project`].
+///
+/// Every placement becomes a node, and every output terminal of a node drives exactly one net.
+/// Nets fan out to the input terminals listed in [`Project::connections`].
+///
+/// The circuit is simulated with an event queue. A node whose inputs change is re-evaluated at the same instant,
+/// and the resulting output changes are scheduled for after the node's delay.
 pub struct Circuit {
+    pub(crate) drivers: Vec<Driver>,
+    pub(crate) nodes: Vec<Node>,
+    pub(crate) nets: Vec<Net>,
+
+    pub(crate) values: Vec<Value>,
+    pub(crate) state: Vec<NodeState>,
+
+    pub(crate) instances: HashMap<InstanceId, NodeId>,
+    // The net driven by each output terminal of a top-level instance, including subcomponents
+    pub(crate) terminals: HashMap<Connection, NetId>,
+
+    time: u64,
+    queue: BinaryHeap<Reverse<Event>>,
+    sequence: u64,
+
+    iteration_limit: usize,
+
+    // The value each net will have once all scheduled events have been applied
+    projected: Vec<Value>,
+
+    trace: Option<Trace>,
+}
+
+/// The simulation state of a [`Circuit`] at some point in time. See [`Circuit::snapshot`].
+#[derive(Clone)]
+pub struct Snapshot {
+    time: u64,
+    values: Vec<Value>,
+    state: Vec<NodeState>,
+    queue: BinaryHeap<Reverse<Event>>,
+    sequence: u64,
+    projected: Vec<Value>,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Event {
+    time: u64,
+    sequence: u64,
+    kind: EventKind,
+}
+
+// Events are ordered by when they happen, and then by when they were scheduled
+impl Ord for Event {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.time, self.sequence).cmp(&(other.time, other.sequence))
+    }
+}
+
+impl PartialOrd for Event {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Event {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Event {}
+
+#[derive(Debug, Copy, Clone)]
+enum EventKind {
+    Drive { net: NetId, value: Value },
+    Evaluate { node: NodeId },
+    // The next edge of a clock
+    Tick { node: NodeId },
+}
+
+pub(crate) struct Node {
+    pub(crate) instance: InstanceId,
+    // The subcomponent instances this node is nested in, outermost first
+    pub(crate) path: Vec<InstanceId>,
+    pub(crate) component: ComponentId,
+    pub(crate) label: Option<String>,
+
+    pub(crate) driver: usize,
+    pub(crate) delay: u64,
+
+    pub(crate) inputs: Vec<Option<NetId>>,
+    pub(crate) input_widths: Vec<u8>,
+    pub(crate) outputs: Vec<NetId>,
+}
+
+impl Node {
+    // The top-level instance this node belongs to
+    pub(crate) fn root(&self) -> InstanceId {
+        self.path.first()
+            .copied()
+            .unwrap_or(self.instance)
+    }
+}
+
+pub(crate) struct Net {
+    pub(crate) driver: NodeId,
+    pub(crate) width: u8,
+    pub(crate) sinks: Vec<NodeId>,
+}
+
+pub(crate) enum Driver {
+    TruthTable {
+        truth: HashMap<u64, u64>,
+    },
+
+    Input,
+    Output,
+
+    // Carries a value across the boundary of a subcomponent unchanged
+    Buffer,
+
+    Script {
+        program: Program,
+    },
+    Expression {
+        // The bit each input name refers to
+        inputs: HashMap<String, u8>,
+        outputs: Vec<Option<Expr>>,
+    },
+    // Unlike every other driver, reads and writes its terminals one by one, so it isn't limited to 64 bits either way
+    Bdd {
+        table: BddTable,
+    },
+
+    Clock {
+        period: u64,
+        high: u64,
+    },
+    FlipFlop {
+        kind: FlipFlopKind,
+        width: u8,
+    },
+    Latch {
+        kind: LatchKind,
+        width: u8,
+    },
+    Memory {
+        kind: MemoryKind,
+        address: u8,
+        data: u8,
+        contents: Vec<u64>,
+    },
+}
+
+// Whatever a node remembers between evaluations
+#[derive(Debug, Clone)]
+pub(crate) enum NodeState {
+    Stateless,
+    Script(Vec<u64>),
+    Sequential {
+        // The clock as of the last evaluation, to find edges with
+        clock: Logic,
+        q: Value,
+    },
+    // Every word of a RAM
+    Memory(Vec<Value>),
+}
+
+impl Driver {
+    pub(crate) fn compile(component: &Component) -> Result<Option<Self>> {
+        Ok(match &component.driver {
+            ComponentDriver::TruthTable { truth, .. } => Some(Self::TruthTable { truth: truth.clone() }),
+            ComponentDriver::Script { script } => Some(Self::Script { program: Program::compile(&script.script, &component.inputs, &component.outputs)? }),
+            ComponentDriver::Expression { equations } => {
+                let inputs = Port::bit_names(&component.inputs)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(bit, name)| (name, bit as u8))
+                    .collect::<HashMap<_, _>>();
+                let names = Port::bit_names(&component.outputs);
+                let mismatch = |port: &str| CircuitError::PortMismatch { component: component.id, port: port.to_owned() };
+
+                let mut outputs = vec![None; names.len()];
+
+                for equation in equations {
+                    let bit = names.iter()
+                        .position(|name| *name == equation.output)
+                        .filter(|bit| outputs[*bit].is_none())
+                        .ok_or_else(|| mismatch(&equation.output))?;
+
+                    if let Some(variable) = equation.expr.variables().into_iter().find(|variable| !inputs.contains_key(variable)) {
+                        return Err(mismatch(&variable).into());
+                    }
+
+                    outputs[bit] = Some(equation.expr.clone());
+                }
+
+                Some(Self::Expression { inputs, outputs })
+            },
+            ComponentDriver::Bdd { table } => {
+                let bits = |ports: &Vec<Port>| ports.iter().map(|port| port.width as usize).sum::<usize>();
+
+                if !table.is_valid(bits(&component.inputs)) || table.outputs.len() != bits(&component.outputs) {
+                    return Err(CircuitError::InvalidBdd(component.id).into());
+                }
+
+                Some(Self::Bdd { table: table.clone() })
+            },
+            ComponentDriver::Clock { period, duty } => {
+                let period = (*period).max(2);
+                let high = ((period as f64 * duty).round() as u64).clamp(1, period - 1);
+
+                Some(Self::Clock { period, high })
+            },
+            ComponentDriver::FlipFlop { kind } => Some(Self::FlipFlop {
+                kind: *kind,
+                width: component.outputs.first().map(|port| port.width).unwrap_or(1),
+            }),
+            ComponentDriver::Latch { kind } => Some(Self::Latch {
+                kind: *kind,
+                width: component.outputs.first().map(|port| port.width).unwrap_or(1),
+            }),
+            ComponentDriver::Memory { kind, address, data, contents, .. } => {
+                if *address > memory::ADDRESS_LIMIT || *data > 64 {
+                    return Err(CircuitError::TooWide(component.id).into());
+                }
+
+                let expected = match kind {
+                    MemoryKind::Rom => vec![*address, 1, *data],
+                    MemoryKind::Ram => vec![*address, *data, 1, 1, *data],
+                };
+                let ports = component.inputs.iter()
+                    .chain(component.outputs.iter())
+                    .collect::<Vec<_>>();
+
+                if let Some(index) = (0..ports.len().max(expected.len())).find(|index| ports.get(*index).map(|port| port.width) != expected.get(*index).copied()) {
+                    return Err(CircuitError::PortMismatch {
+                        component: component.id,
+                        port: ports.get(index).map(|port| port.name.clone()).unwrap_or_default(),
+                    }.into());
+                }
+
+                Some(Self::Memory { kind: *kind, address: *address, data: *data, contents: contents.clone() })
+            },
+            ComponentDriver::Input => Some(Self::Input),
+            ComponentDriver::Output => Some(Self::Output),
+            _ => None
+        })
+    }
+
+    pub(crate) fn initial_state(&self) -> NodeState {
+        match self {
+            Self::Script { program } => NodeState::Script(program.initial_state()),
+            Self::FlipFlop { width, .. } | Self::Latch { width, .. } => NodeState::Sequential {
+                clock: Logic::X,
+                q: Value::x(*width),
+            },
+            Self::Memory { kind: MemoryKind::Ram, address, data, contents } => NodeState::Memory((0..1usize << address)
+                .map(|word| contents.get(word)
+                    .map(|value| Value::new(*data, *value))
+                    .unwrap_or(Value::x(*data)))
+                .collect()),
+            _ => NodeState::Stateless,
+        }
+    }
+
+    // Edge-triggered elements only pass values along on a clock edge, so they break combinational paths
+    pub(crate) fn is_edge_triggered(&self) -> bool {
+        matches!(self, Self::FlipFlop { .. })
+    }
+
+    // When a clock next changes level after `time`
+    fn next_edge(&self, time: u64) -> Option<u64> {
+        match self {
+            Self::Clock { period, high } => match time % period {
+                phase if phase < period - high => Some(time + period - high - phase),
+                phase => Some(time + period - phase),
+            },
+            _ => None,
+        }
+    }
+
+    /// Computes the value of each output terminal, `outputs` bits wide, from the value of each input terminal.
+    pub(crate) fn evaluate_terminals(&self, inputs: &[Value], outputs: &[u8], time: u64, state: &mut NodeState) -> Option<Vec<Value>> {
+        if let Self::Bdd { table } = self {
+            let bits = inputs.iter()
+                .flat_map(|input| (0..input.width()).map(|bit| input.get(bit)))
+                .collect::<Vec<_>>();
+            let mut bits = table.evaluate(|variable| bits[variable as usize]).into_iter();
+
+            return Some(outputs.iter()
+                .map(|width| bits.by_ref()
+                    .take(*width as usize)
+                    .enumerate()
+                    .fold(Value::new(*width, 0), |mut value, (bit, logic)| {
+                        value.set(bit as u8, logic);
+                        value
+                    }))
+                .collect());
+        }
+
+        let input = inputs.iter()
+            .rev()
+            .fold(Value::new(0, 0), |packed, value| value.concat(packed));
+        let output = self.evaluate(input, outputs.iter().sum(), time, state)?;
+
+        Some(unpack(output, outputs))
+    }
+
+    /// Computes the outputs of a driver at `time`, packed into a value `width` bits wide.
+    pub(crate) fn evaluate(&self, input: Value, width: u8, time: u64, state: &mut NodeState) -> Option<Value> {
+        match (self, state) {
+            (Self::TruthTable { truth }, _) => Some(lookup(truth, input, width)),
+            (Self::Buffer, _) => Some(input),
+            (Self::Script { program }, NodeState::Script(state)) => Some(run(program, input, width, state)),
+            (Self::Expression { inputs, outputs }, _) => {
+                let input = input.resolve();
+
+                Some(outputs.iter()
+                    .rev()
+                    .fold(Value::new(0, 0), |packed, output| match output {
+                        Some(expr) => expr.evaluate_value(&|name| input.slice(inputs[name], 1)),
+                        None => Value::x(1),
+                    }.concat(packed)))
+            },
+            // Clocks start low so that the first rising edge comes after everything else has settled
+            (Self::Clock { period, high }, _) => Some(Value::new(width, (time % period >= period - high) as u64)),
+
+            (Self::FlipFlop { kind, width }, NodeState::Sequential { clock, q }) => {
+                let data = |index: u8| input.slice(index * width, *width);
+
+                let (next, now) = match kind {
+                    FlipFlopKind::D => (data(0).resolve(), input.slice(*width, 1)),
+                    // The redundant `j & !k` term sets the flip-flop even while `q` is unknown
+                    FlipFlopKind::JK => ((data(0) & !*q) | (!data(1) & *q) | (data(0) & !data(1)), input.slice(2 * width, 1)),
+                    FlipFlopKind::T => (data(0) ^ *q, input.slice(*width, 1)),
+                };
+                let now = now.resolve().get(0);
+
+                *q = match (*clock, now) {
+                    (Logic::Zero, Logic::One) => next,
+                    (Logic::One, _) | (_, Logic::Zero) => *q,
+                    // The clock might have risen, so only bits that come out the same either way are known
+                    _ => q.merge(next),
+                };
+                *clock = now;
+
+                Some(q.concat(!*q))
+            },
+
+            (Self::Latch { kind, width }, NodeState::Sequential { q, .. }) => {
+                let data = |index: u8| input.slice(index * width, *width);
+
+                *q = match kind {
+                    LatchKind::D => match input.slice(*width, 1).resolve().get(0) {
+                        Logic::One => data(0).resolve(),
+                        Logic::Zero => *q,
+                        _ => q.merge(data(0)),
+                    },
+                    LatchKind::SR => {
+                        let (set, reset) = (data(0), data(1));
+                        let next = set | (*q & !reset);
+
+                        // Setting and resetting at once leaves the latch in an unknown state
+                        next.merge(next ^ (set & reset))
+                    },
+                };
+
+                Some(q.concat(!*q))
+            },
+
+            (Self::Memory { kind, address, data, contents }, state) => {
+                let at = input.slice(0, *address).resolve().to_u64().map(|word| word as usize);
+                let read = input.slice(input.width() - 1, 1).resolve().get(0);
+
+                if let (MemoryKind::Ram, NodeState::Memory(words)) = (kind, &mut *state) {
+                    let value = input.slice(*address, *data).resolve();
+
+                    match (input.slice(address + data, 1).resolve().get(0), at) {
+                        (Logic::Zero, _) => {},
+                        (Logic::One, Some(word)) => words[word] = value,
+                        (_, Some(word)) => words[word] = words[word].merge(value),
+                        // Any word might be written to
+                        (_, None) => words.iter_mut().for_each(|word| *word = word.merge(value)),
+                    }
+                }
+
+                Some(match (read, at) {
+                    (Logic::Zero, _) => Value::z(*data),
+                    (Logic::One, Some(word)) => match state {
+                        NodeState::Memory(words) => words[word],
+                        _ => Value::new(*data, contents.get(word).copied().unwrap_or(0)),
+                    },
+                    _ => Value::x(*data),
+                })
+            },
+
+            _ => None,
+        }
+    }
+}
+
+// Slices a packed value up into values of the given widths, least significant first
+pub(crate) fn unpack(packed: Value, widths: &[u8]) -> Vec<Value> {
+    widths.iter()
+        .scan(0u8, |offset, width| {
+            let value = packed.slice(*offset, *width);
+            *offset = offset.saturating_add(*width);
+            Some(value)
+        })
+        .collect()
+}
+
+// Scripts only see whole numbers, so any unknown input makes every output unknown. So does a script that faults.
+fn run(program: &Program, input: Value, width: u8, state: &mut [u64]) -> Value {
+    if !input.is_known() {
+        return Value::x(width);
+    }
+
+    let inputs = program.inputs.iter()
+        .scan(0, |offset, width| {
+            let value = input.slice(*offset, *width).bits();
+            *offset += width;
+            Some(value)
+        })
+        .collect::<Vec<_>>();
+
+    let Some(outputs) = program.run(&inputs, state) else {
+        return Value::x(width);
+    };
+
+    outputs.into_iter()
+        .zip(program.outputs.iter())
+        .rev()
+        .fold(Value::new(0, 0), |packed, (value, width)| value
+            .map(|value| Value::new(*width, value))
+            .unwrap_or(Value::x(*width))
+            .concat(packed))
+}
+
+// Looks up every row the unknown input bits could select. Output bits on which all of those rows agree are known,
+// the rest are X. Z inputs are read as X.
+fn lookup(truth: &HashMap<u64, u64>, input: Value, width: u8) -> Value {
+    let input = input.resolve();
+    let unknown = input.unknown();
+
+    if unknown.count_ones() > EXPAND_LIMIT {
+        return Value::x(width);
+    }
+
+    let row = |key: u64| truth.get(&key).copied().unwrap_or(0);
+
+    let first = row(input.bits());
+    let mut differ = 0;
+
+    // Walks every subset of the unknown bits
+    let mut subset = unknown;
+    while subset != 0 {
+        differ |= first ^ row(input.bits() | subset);
+        subset = (subset - 1) & unknown;
+    }
+
+    Value::from_planes(width, first & !differ, differ)
+}
+
+// The terminals of a placement as seen from the scope it is placed in
+struct Terminals {
+    inputs: Vec<(NodeId, usize)>,
+    outputs: Vec<NetId>,
+}
+
+// State carried through the recursive instantiation of subcomponents
+struct Compiler<'a> {
+    project: &'a Project,
+    drivers: HashMap<ComponentId, usize>,
+    buffer: Option<usize>,
+
+    // The subcomponents currently being instantiated, outermost first
+    stack: Vec<ComponentId>,
+}
+
+impl Circuit {
+    /// Compiles a project, flattening subcomponents into the nodes they contain.
+    pub fn compile(project: &Project) -> Result<Self> {
+        Self::build(project, &project.body, &project.connections, vec![])
+    }
+
+    /// Compiles the inside of a subcomponent as if it were a project of its own,
+    /// so that its `Input` and `Output` placements can be driven and read.
+    pub fn compile_component(project: &Project, component: ComponentId) -> Result<Self> {
+        match project.components.get(&component).map(|component| &component.driver) {
+            Some(ComponentDriver::Subcomponent { body, connections, .. }) => Self::build(project, body, connections, vec![component]),
+            _ => Err(CircuitError::NotASubcomponent(component).into()),
+        }
+    }
+
+    fn build(
+        project: &Project,
+        body: &HashMap<InstanceId, Placement>,
+        connections: &HashMap<Connection, Vec<Connection>>,
+        stack: Vec<ComponentId>,
+    ) -> Result<Self> {
+        let mut circuit = Self {
+            drivers: vec![],
+            nodes: vec![],
+            nets: vec![],
+            values: vec![],
+            state: vec![],
+            instances: HashMap::new(),
+            terminals: HashMap::new(),
+            time: 0,
+            queue: BinaryHeap::new(),
+            sequence: 0,
+            iteration_limit: ITERATION_LIMIT,
+            projected: vec![],
+            trace: None,
+        };
+
+        let mut compiler = Compiler {
+            project,
+            drivers: HashMap::new(),
+            buffer: None,
+            stack,
+        };
+
+        circuit.instantiate(&mut compiler, body, connections, &[])?;
+
+        circuit.projected = circuit.values.clone();
+
+        for node in 0..circuit.nodes.len() {
+            circuit.schedule(0, EventKind::Evaluate { node });
+        }
+
+        circuit.advance(0)?;
+
+        Ok(circuit)
+    }
+
+    // Creates the nodes for every placement in a scope and wires them together.
+    // `path` lists the subcomponent instances enclosing the scope and is empty at the top level.
+    fn instantiate(
+        &mut self,
+        compiler: &mut Compiler,
+        body: &HashMap<InstanceId, Placement>,
+        connections: &HashMap<Connection, Vec<Connection>>,
+        path: &[InstanceId],
+    ) -> Result<HashMap<InstanceId, Terminals>> {
+        let mut scope = HashMap::new();
+
+        let mut body = body.values().collect::<Vec<_>>();
+        body.sort_by_key(|placement| placement.instance);
+
+        for placement in body {
+            let component = compiler.project.components.get(&placement.component)
+                .ok_or(CircuitError::UnknownComponent { instance: placement.instance, component: placement.component })?;
+
+            let widths = |ports: &Vec<Port>| ports.iter()
+                .map(|port| port.width)
+                .collect::<Vec<_>>();
+            let total = |ports: &Vec<Port>| vec![ports.iter().map(|port| port.width).sum()];
+
+            let terminals = match &component.driver {
+                ComponentDriver::Subcomponent { body, connections, .. } => {
+                    if let Some(start) = compiler.stack.iter().position(|id| *id == component.id) {
+                        return Err(CircuitError::RecursiveComponent(compiler.stack[start..].to_vec()).into());
+                    }
+
+                    compiler.stack.push(component.id);
+                    let inner = self.instantiate(compiler, body, connections, &[path, &[placement.instance]].concat())?;
+                    compiler.stack.pop();
+
+                    self.expose(compiler.project, component, body, &inner)?
+                },
+
+                // Inputs and outputs of a subcomponent pass values across its boundary and are not driven by the user
+                ComponentDriver::Input | ComponentDriver::Output if !path.is_empty() => {
+                    let driver = *compiler.buffer.get_or_insert_with(|| {
+                        self.drivers.push(Driver::Buffer);
+                        self.drivers.len() - 1
+                    });
+
+                    let (inputs, outputs) = match component.driver {
+                        ComponentDriver::Input => (total(&component.outputs), widths(&component.outputs)),
+                        _ => (widths(&component.inputs), total(&component.inputs)),
+                    };
+
+                    self.node(placement, path, driver, 0, inputs, outputs)
+                },
+
+                _ => {
+                    let driver = match compiler.drivers.get(&component.id) {
+                        Some(driver) => *driver,
+                        None => {
+                            let too_wide = |ports: &Vec<Port>| ports.iter().map(|port| port.width as u32).sum::<u32>() > 64;
+                            let packed = matches!(component.driver,
+                                ComponentDriver::TruthTable { .. } | ComponentDriver::Expression { .. } | ComponentDriver::Script { .. } | ComponentDriver::FlipFlop { .. } | ComponentDriver::Latch { .. });
+
+                            if packed && (too_wide(&component.inputs) || too_wide(&component.outputs)) {
+                                return Err(CircuitError::TooWide(component.id).into());
+                            }
+
+                            let driver = Driver::compile(component)?
+                                .ok_or(CircuitError::UnsupportedDriver { instance: placement.instance, component: component.id })?;
+
+                            self.drivers.push(driver);
+                            compiler.drivers.insert(component.id, self.drivers.len() - 1);
+                            self.drivers.len() - 1
+                        }
+                    };
+
+                    let delay = placement.delay.unwrap_or(component.delay);
+                    let terminals = self.node(placement, path, driver, delay, widths(&component.inputs), widths(&component.outputs));
+
+                    if path.is_empty() {
+                        self.instances.insert(placement.instance, self.nodes.len() - 1);
+                    }
+
+                    terminals
+                }
+            };
+
+            if path.is_empty() {
+                for (terminal, net) in terminals.outputs.iter().enumerate() {
+                    self.terminals.insert(Connection::output(placement.instance, terminal as u64), *net);
+                }
+            }
+
+            scope.insert(placement.instance, terminals);
+        }
+
+        for (output, inputs) in connections.iter() {
+            let net = match output.terminal {
+                Terminal::Output(terminal) => scope.get(&output.instance)
+                    .and_then(|terminals| terminals.outputs.get(terminal as usize))
+                    .copied(),
+                Terminal::Input(_) => None,
+            }.ok_or(CircuitError::InvalidConnection(*output))?;
+
+            for input in inputs {
+                let (node, terminal) = match input.terminal {
+                    Terminal::Input(terminal) => scope.get(&input.instance)
+                        .and_then(|terminals| terminals.inputs.get(terminal as usize))
+                        .copied(),
+                    Terminal::Output(_) => None,
+                }.ok_or(CircuitError::InvalidConnection(*input))?;
+
+                if self.nodes[node].input_widths[terminal] != self.nets[net].width {
+                    return Err(CircuitError::WidthMismatch { output: *output, input: *input }.into());
+                }
+
+                if self.nodes[node].inputs[terminal].replace(net).is_some() {
+                    return Err(CircuitError::MultipleDrivers(*input).into());
+                }
+
+                if !self.nets[net].sinks.contains(&node) {
+                    self.nets[net].sinks.push(node);
+                }
+            }
+        }
+
+        Ok(scope)
+    }
+
+    // Adds a node with the given terminal widths, along with a net for each of its outputs
+    fn node(&mut self, placement: &Placement, path: &[InstanceId], driver: usize, delay: u64, inputs: Vec<u8>, outputs: Vec<u8>) -> Terminals {
+        let node = self.nodes.len();
+
+        // Nets start out low so that free-running loops such as ring oscillators have something to work from.
+        // Unknown values only come from floating inputs and uninitialised state.
+        let outputs = outputs.into_iter()
+            .map(|width| {
+                self.nets.push(Net { driver: node, width, sinks: vec![] });
+                self.values.push(Value::new(width, 0));
+                self.nets.len() - 1
+            })
+            .collect::<Vec<_>>();
+
+        self.state.push(self.drivers[driver].initial_state());
+        self.nodes.push(Node {
+            instance: placement.instance,
+            path: path.to_vec(),
+            component: placement.component,
+            label: placement.label.clone(),
+            driver,
+            delay,
+            inputs: vec![None; inputs.len()],
+            input_widths: inputs.clone(),
+            outputs: outputs.clone(),
+        });
+
+        Terminals {
+            inputs: (0..inputs.len()).map(|terminal| (node, terminal)).collect(),
+            outputs,
+        }
+    }
+
+    // Matches the ports of a subcomponent to the input and output placements inside it that carry the same label
+    fn expose(&self, project: &Project, component: &Component, body: &HashMap<InstanceId, Placement>, inner: &HashMap<InstanceId, Terminals>) -> Result<Terminals> {
+        let find = |port: &Port, input: bool| body.values()
+            .filter(|placement| placement.label.as_deref() == Some(port.name.as_str()))
+            .find(|placement| project.components.get(&placement.component)
+                .is_some_and(|inner| matches!((&inner.driver, input), (ComponentDriver::Input, true) | (ComponentDriver::Output, false))))
+            .and_then(|placement| inner.get(&placement.instance))
+            .ok_or(CircuitError::PortMismatch { component: component.id, port: port.name.clone() });
+
+        let inputs = component.inputs.iter()
+            .map(|port| {
+                let (node, terminal) = find(port, true)?.inputs[0];
+
+                match self.nodes[node].input_widths[terminal] == port.width {
+                    true => Ok((node, terminal)),
+                    false => Err(CircuitError::PortMismatch { component: component.id, port: port.name.clone() }),
+                }
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let outputs = component.outputs.iter()
+            .map(|port| {
+                let net = find(port, false)?.outputs[0];
+
+                match self.nets[net].width == port.width {
+                    true => Ok(net),
+                    false => Err(CircuitError::PortMismatch { component: component.id, port: port.name.clone() }),
+                }
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(Terminals { inputs, outputs })
+    }
+
+    /// Sets how many passes through the event queue [`Circuit::evaluate`] makes,
+    /// and how many [`Circuit::advance`] makes without time moving forward, before giving up.
+    pub fn with_iteration_limit(mut self, limit: usize) -> Self {
+        self.iteration_limit = limit;
+        self
+    }
+
+    /// Lists the instances whose values are set by the caller, along with their labels.
+    pub fn inputs(&self) -> impl Iterator<Item=(InstanceId, Option<&str>)> {
+        self.nodes.iter()
+            .filter(|node| matches!(self.drivers[node.driver], Driver::Input))
+            .map(|node| (node.instance, node.label.as_deref()))
+    }
+
+    /// Lists the instances whose values are read by the caller, along with their labels.
+    pub fn outputs(&self) -> impl Iterator<Item=(InstanceId, Option<&str>)> {
+        self.nodes.iter()
+            .filter(|node| matches!(self.drivers[node.driver], Driver::Output))
+            .map(|node| (node.instance, node.label.as_deref()))
+    }
+
+    /// Finds the top-level instance with the given placement label. Placements inside subcomponents are not searched.
+    pub fn find(&self, label: &str) -> Option<InstanceId> {
+        self.nodes.iter()
+            .find(|node| node.path.is_empty() && node.label.as_deref() == Some(label))
+            .map(|node| node.instance)
+    }
+
+    /// Drives the terminals of an `Input` instance at the current time.
+    /// The terminals are packed into `value` in order, with the first terminal in the least significant bits.
+    ///
+    /// The change is only propagated once the simulation is advanced with [`Circuit::evaluate`] or [`Circuit::advance`].
+    pub fn set_input(&mut self, instance: InstanceId, value: impl Into<Value>) -> Result<()> {
+        let node = self.instances.get(&instance)
+            .copied()
+            .filter(|node| matches!(self.drivers[self.nodes[*node].driver], Driver::Input))
+            .ok_or(CircuitError::NotAnInput(instance))?;
+
+        let widths = self.nodes[node].outputs.iter()
+            .map(|net| self.nets[*net].width)
+            .collect::<Vec<_>>();
+
+        self.drive(node, unpack(value.into(), &widths), self.time);
+
+        Ok(())
+    }
+
+    /// Reads back the value an `Input` instance is driving.
+    pub fn input(&self, instance: InstanceId) -> Result<Value> {
+        let node = self.instances.get(&instance)
+            .copied()
+            .filter(|node| matches!(self.drivers[self.nodes[*node].driver], Driver::Input))
+            .ok_or(CircuitError::NotAnInput(instance))?;
+
+        Ok(self.nodes[node].outputs.iter()
+            .rev()
+            .fold(Value::new(0, 0), |packed, net| self.values[*net].concat(packed)))
+    }
+
+    /// Reads the terminals of an `Output` instance, packed in the same order as [`Circuit::set_input`].
+    pub fn output(&self, instance: InstanceId) -> Result<Value> {
+        let node = self.instances.get(&instance)
+            .copied()
+            .filter(|node| matches!(self.drivers[self.nodes[*node].driver], Driver::Output))
+            .ok_or(CircuitError::NotAnOutput(instance))?;
+
+        Ok(self.read(node))
+    }
+
+    /// The words held by a memory instance, lowest address first. A ROM lists only the words it was loaded with.
+    pub fn memory(&self, instance: InstanceId) -> Result<Vec<Value>> {
+        let node = self.instances.get(&instance)
+            .copied()
+            .ok_or(CircuitError::NotAMemory(instance))?;
+
+        match (&self.drivers[self.nodes[node].driver], &self.state[node]) {
+            (Driver::Memory { .. }, NodeState::Memory(words)) => Ok(words.clone()),
+            (Driver::Memory { data, contents, .. }, _) => Ok(contents.iter()
+                .map(|word| Value::new(*data, *word))
+                .collect()),
+            _ => Err(CircuitError::NotAMemory(instance).into()),
+        }
+    }
+
+    /// Starts recording a [`Trace`] of every top-level `Input` and `Output` instance, named by their labels.
+    /// Unlabelled instances are named after their instance id.
+    pub fn record(&mut self) {
+        let mut nodes = self.instances.iter()
+            .map(|(instance, node)| (*instance, *node))
+            .filter(|(_, node)| matches!(self.drivers[self.nodes[*node].driver], Driver::Input | Driver::Output))
+            .collect::<Vec<_>>();
+        nodes.sort_unstable();
+
+        for (instance, node) in nodes {
+            let node = &self.nodes[node];
+
+            let nets = match self.drivers[node.driver] {
+                Driver::Input => node.outputs.iter()
+                    .map(|net| (Some(*net), self.nets[*net].width))
+                    .collect(),
+                _ => node.inputs.iter()
+                    .copied()
+                    .zip(node.input_widths.iter().copied())
+                    .collect(),
+            };
+
+            let name = node.label.clone().unwrap_or_else(|| format!("instance{}", instance));
+
+            self.trace.get_or_insert_default().watch(name, nets, self.time, &self.values);
+        }
+    }
+
+    /// Adds the net driven by an output terminal of a top-level instance to the trace, starting one if need be.
+    pub fn watch(&mut self, name: impl Into<String>, terminal: Connection) -> Result<()> {
+        let net = self.terminals.get(&terminal)
+            .copied()
+            .ok_or(CircuitError::InvalidConnection(terminal))?;
+
+        let width = self.nets[net].width;
+        self.trace.get_or_insert_default().watch(name.into(), vec![(Some(net), width)], self.time, &self.values);
+
+        Ok(())
+    }
+
+    /// The trace recorded so far, if [`Circuit::record`] or [`Circuit::watch`] has been called.
+    pub fn trace(&self) -> Option<&Trace> {
+        self.trace.as_ref()
+    }
+
+    /// Stops recording and hands back the trace.
+    pub fn take_trace(&mut self) -> Option<Trace> {
+        self.trace.take()
+    }
+
+    /// Whether the outputs depend only on the current inputs: there are no loops, clocks or storage elements.
+    pub fn is_combinational(&self) -> bool {
+        let stateless = self.state.iter()
+            .all(|state| match state {
+                NodeState::Stateless => true,
+                NodeState::Script(state) => state.is_empty(),
+                _ => false,
+            });
+        let clocked = self.drivers.iter()
+            .any(|driver| matches!(driver, Driver::Clock { .. }));
+
+        stateless && !clocked && analysis::components(self).is_empty()
+    }
+
+    /// Captures the state of the simulation, including pending events and the contents of every RAM.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            time: self.time,
+            values: self.values.clone(),
+            state: self.state.clone(),
+            queue: self.queue.clone(),
+            sequence: self.sequence,
+            projected: self.projected.clone(),
+        }
+    }
+
+    /// Returns the simulation to a snapshot previously taken of this circuit.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.time = snapshot.time;
+        self.values = snapshot.values.clone();
+        self.state = snapshot.state.clone();
+        self.queue = snapshot.queue.clone();
+        self.sequence = snapshot.sequence;
+        self.projected = snapshot.projected.clone();
+    }
+
+    /// The current simulated time
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    /// Processes events until no more are pending, leaving every net stable.
+    /// Stops early at the next clock edge, so that circuits with a free-running clock settle too.
+    ///
+    /// Fails with [`CircuitError::Unstable`] if the circuit is still changing after the iteration limit.
+    pub fn evaluate(&mut self) -> Result<()> {
+        let mut iterations = 0;
+
+        while let Some(Reverse(event)) = self.queue.peek().filter(|Reverse(event)| !matches!(event.kind, EventKind::Tick { .. })) {
+            let active = self.step(event.time);
+
+            iterations += 1;
+            if iterations >= self.iteration_limit && self.queue.iter().any(|Reverse(event)| !matches!(event.kind, EventKind::Tick { .. })) {
+                return Err(self.unstable(active).into());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Processes every event scheduled within the next `units` time units and moves the clock forward by that amount.
+    ///
+    /// Fails with [`CircuitError::Unstable`] if the iteration limit is reached without time moving forward,
+    /// which happens when zero-delay components feed back into each other.
+    pub fn advance(&mut self, units: u64) -> Result<()> {
+        let until = self.time.saturating_add(units);
+        let mut iterations = 0;
+
+        while let Some(Reverse(event)) = self.queue.peek().filter(|Reverse(event)| event.time <= until) {
+            let time = event.time;
+            let active = self.step(time);
+
+            iterations += 1;
+            if self.queue.peek().is_some_and(|Reverse(event)| event.time > time) {
+                iterations = 0;
+            } else if iterations >= self.iteration_limit && !self.queue.is_empty() {
+                return Err(self.unstable(active).into());
+            }
+        }
+
+        self.time = until;
+
+        Ok(())
+    }
+
+    // Names the loops that the nodes evaluated last belong to, falling back to the nodes themselves.
+    fn unstable(&self, active: Vec<NodeId>) -> CircuitError {
+        let mut instances = analysis::components(self)
+            .into_iter()
+            .filter(|component| component.iter().any(|node| active.contains(node)))
+            .flatten()
+            .map(|node| self.nodes[node].root())
+            .collect::<Vec<_>>();
+
+        if instances.is_empty() {
+            instances = active.iter()
+                .map(|node| self.nodes[*node].root())
+                .collect();
+        }
+
+        instances.sort_unstable();
+        instances.dedup();
+
+        CircuitError::Unstable { time: self.time, instances }
+    }
+
+    // Applies every event scheduled for `time`, then re-evaluates the nodes affected by them.
+    // Changes produced by zero-delay nodes are scheduled for the same instant and handled by the next call.
+    // Returns the nodes that were evaluated.
+    fn step(&mut self, time: u64) -> Vec<NodeId> {
+        self.time = time;
+
+        let mut affected = vec![];
+        let mut changed = vec![];
+
+        while let Some(Reverse(event)) = self.queue.peek().filter(|Reverse(event)| event.time == time) {
+            let event = *event;
+            self.queue.pop();
+
+            match event.kind {
+                EventKind::Drive { net, value } if self.values[net] != value => {
+                    self.values[net] = value;
+                    affected.extend(self.nets[net].sinks.iter().copied());
+                    changed.push(net);
+                },
+                EventKind::Drive { .. } => {},
+                EventKind::Evaluate { node } | EventKind::Tick { node } => affected.push(node),
+            }
+        }
+
+        if let Some(trace) = &mut self.trace {
+            trace.update(time, &changed, &self.values);
+        }
+
+        affected.sort_unstable();
+        affected.dedup();
+
+        for node in affected.iter().copied() {
+            let widths = self.nodes[node].outputs.iter()
+                .map(|net| self.nets[*net].width)
+                .collect::<Vec<_>>();
+
+            if let Some(edge) = self.drivers[self.nodes[node].driver].next_edge(time) {
+                self.schedule(edge, EventKind::Tick { node });
+            }
+
+            let inputs = self.gather(node, |net| self.values[net]);
+            let Some(outputs) = self.drivers[self.nodes[node].driver].evaluate_terminals(&inputs, &widths, time, &mut self.state[node]) else {
+                continue;
+            };
+
+            self.drive(node, outputs, time + self.nodes[node].delay);
+        }
+
+        affected
+    }
+
+    fn schedule(&mut self, time: u64, kind: EventKind) {
+        self.sequence += 1;
+        self.queue.push(Reverse(Event { time, sequence: self.sequence, kind }));
+    }
+
+    // Packs the input terminals of `node` into a single value, the first terminal occupying the least significant bits
+    fn read(&self, node: NodeId) -> Value {
+        self.gather(node, |net| self.values[net])
+            .into_iter()
+            .rev()
+            .fold(Value::new(0, 0), |packed, value| value.concat(packed))
+    }
+
+    // The value of each input terminal of `node`. Unconnected terminals float at Z.
+    pub(crate) fn gather(&self, node: NodeId, value: impl Fn(NetId) -> Value) -> Vec<Value> {
+        let node = &self.nodes[node];
+
+        node.inputs.iter()
+            .zip(node.input_widths.iter())
+            .map(|(net, width)| net.map(&value).unwrap_or(Value::z(*width)))
+            .collect()
+    }
+
+    // Schedules each output net of `node` to take its value at `time`, unless it is already headed there.
+    fn drive(&mut self, node: NodeId, values: Vec<Value>, time: u64) {
+        for (net, value) in self.nodes[node].outputs.clone().into_iter().zip(values) {
+            if self.projected[net] != value {
+                self.projected[net] = value;
+                self.schedule(time, EventKind::Drive { net, value });
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum CircuitError {
+    UnknownComponent { instance: InstanceId, component: ComponentId },
+    UnsupportedDriver { instance: InstanceId, component: ComponentId },
+    InvalidConnection(Connection),
+    MultipleDrivers(Connection),
+    WidthMismatch { output: Connection, input: Connection },
+    TooWide(ComponentId),
+    PortMismatch { component: ComponentId, port: String },
+    RecursiveComponent(Vec<ComponentId>),
+    NotAnInput(InstanceId),
+    NotAnOutput(InstanceId),
+    NotAMemory(InstanceId),
+    NotASubcomponent(ComponentId),
+    // A BDD table whose nodes are out of order, or don't match the component's ports
+    InvalidBdd(ComponentId),
+    Unstable { time: u64, instances: Vec<InstanceId> },
+}
+
+impl std::error::Error for CircuitError {}
+impl std::fmt::Display for CircuitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::project::Script;
+
+    // The built-in components with nothing placed yet
+    pub(crate) fn project() -> Project {
+        let mut project = Project::empty();
+        project.body.clear();
+        project
+    }
+
+    pub(crate) fn place(project: &mut Project, instance: InstanceId, component: ComponentId, label: &str) {
+        project.body.insert(instance, Placement {
+            component,
+            instance,
+            label: Some(label.to_owned()),
+            pos: (0.0, 0.0).into(),
+            orientation: 0.0,
+            delay: None,
+        });
+    }
+
+    pub(crate) fn connect(project: &mut Project, (output, from): (InstanceId, u64), (input, to): (InstanceId, u64)) {
+        project.connections.entry(Connection::output(output, from))
+            .or_default()
+            .push(Connection::input(input, to));
+    }
+
+    fn compile_error(project: &Project) -> String {
+        Circuit::compile(project).err().unwrap().to_string()
+    }
+
+    // a AND b, read by an output
+    fn and_gate() -> Project {
+        let mut project = project();
+        place(&mut project, 0, Project::INPUT, "a");
+        place(&mut project, 1, Project::INPUT, "b");
+        place(&mut project, 2, Project::AND, "and");
+        place(&mut project, 3, Project::OUTPUT, "y");
+        connect(&mut project, (0, 0), (2, 0));
+        connect(&mut project, (1, 0), (2, 1));
+        connect(&mut project, (2, 0), (3, 0));
+        project
+    }
+
+    #[test]
+    fn truth_tables() {
+        let mut circuit = Circuit::compile(&and_gate()).unwrap();
+
+        for (a, b) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+            circuit.set_input(0, a).unwrap();
+            circuit.set_input(1, b).unwrap();
+            circuit.evaluate().unwrap();
+
+            assert_eq!(circuit.output(3).unwrap().to_u64(), Some(a & b));
+        }
+
+        assert_eq!(circuit.find("b"), Some(1));
+        assert!(circuit.set_input(2, 1).is_err());
+        assert!(circuit.output(0).is_err());
+    }
+
+    #[test]
+    fn compile_errors() {
+        let mut project = and_gate();
+        place(&mut project, 4, 99, "missing");
+        assert!(compile_error(&project).contains("UnknownComponent { instance: 4, component: 99 }"));
+
+        let mut project = and_gate();
+        connect(&mut project, (0, 0), (2, 5));
+        assert!(compile_error(&project).contains("InvalidConnection"));
+
+        let mut project = and_gate();
+        connect(&mut project, (1, 0), (2, 0));
+        assert!(compile_error(&project).contains("MultipleDrivers"));
+    }
+
+    #[test]
+    fn delays() {
+        let mut project = and_gate();
+        project.body.get_mut(&2).unwrap().delay = Some(5);
+
+        let mut circuit = Circuit::compile(&project).unwrap();
+        circuit.set_input(0, 1).unwrap();
+        circuit.set_input(1, 1).unwrap();
+
+        circuit.advance(4).unwrap();
+        assert_eq!(circuit.output(3).unwrap().to_u64(), Some(0));
+
+        circuit.advance(1).unwrap();
+        assert_eq!(circuit.output(3).unwrap().to_u64(), Some(1));
+        assert_eq!(circuit.time(), 5);
+
+        // Evaluating runs until the circuit settles, however long that takes
+        circuit.set_input(1, 0).unwrap();
+        circuit.evaluate().unwrap();
+        assert_eq!(circuit.output(3).unwrap().to_u64(), Some(0));
+        assert_eq!(circuit.time(), 10);
+    }
+
+    #[test]
+    fn unstable() {
+        // A ring of inverters with no delay never lets time move forward
+        let mut project = project();
+        for instance in 0..3 {
+            place(&mut project, instance, Project::NOT, "not");
+            project.body.get_mut(&instance).unwrap().delay = Some(0);
+            connect(&mut project, (instance, 0), ((instance + 1) % 3, 0));
+        }
+        place(&mut project, 3, Project::NOT, "outside");
+        connect(&mut project, (2, 0), (3, 0));
+
+        assert!(compile_error(&project).contains("Unstable { time: 0, instances: [0, 1, 2] }"));
+    }
+
+    #[test]
+    fn iteration_limit() {
+        // Settling takes one step for the inputs and one for the gate, while the clock keeps ticking
+        let mut project = and_gate();
+        place(&mut project, 4, Project::CLOCK, "clk");
+
+        let mut circuit = Circuit::compile(&project).unwrap().with_iteration_limit(2);
+        let snapshot = circuit.snapshot();
+
+        circuit.set_input(0, 1).unwrap();
+        circuit.set_input(1, 1).unwrap();
+        circuit.evaluate().unwrap();
+        assert_eq!(circuit.output(3).unwrap().to_u64(), Some(1));
+
+        circuit.restore(&snapshot);
+        let mut circuit = circuit.with_iteration_limit(1);
+        circuit.set_input(0, 1).unwrap();
+        circuit.set_input(1, 1).unwrap();
+        assert!(circuit.evaluate().err().unwrap().to_string().contains("Unstable"));
+    }
+
+    #[test]
+    fn buses() {
+        // Adds one to a byte, with the carry out as a second output
+        let mut project = project();
+        project.components.insert(20, Component {
+            id: 20,
+            name: "increment".into(),
+            inputs: vec![Port::new("a", 8)],
+            outputs: vec![Port::new("y", 8), "carry".into()],
+            driver: ComponentDriver::truth((0..256).map(|a| (a, a + 1))),
+            delay: 1,
+        });
+        project.components.insert(21, Component {
+            id: 21,
+            name: "byte".into(),
+            inputs: vec![],
+            outputs: vec![Port::new("q", 8)],
+            driver: ComponentDriver::Input,
+            delay: 1,
+        });
+        project.components.insert(22, Component {
+            id: 22,
+            name: "result".into(),
+            inputs: vec![Port::new("y", 8), "carry".into()],
+            outputs: vec![],
+            driver: ComponentDriver::Output,
+            delay: 1,
+        });
+        place(&mut project, 0, 21, "a");
+        place(&mut project, 1, 20, "increment");
+        place(&mut project, 2, 22, "y");
+        place(&mut project, 3, Project::NOT, "not");
+
+        project.connect(Connection::output(0, 0), Connection::input(1, 0)).unwrap();
+        project.connect(Connection::output(1, 0), Connection::input(2, 0)).unwrap();
+        project.connect(Connection::output(1, 1), Connection::input(2, 1)).unwrap();
+        assert!(project.connect(Connection::output(1, 0), Connection::input(3, 0)).err().unwrap().to_string().contains("WidthMismatch"));
+
+        let mut circuit = Circuit::compile(&project).unwrap();
+        circuit.set_input(0, 41).unwrap();
+        circuit.evaluate().unwrap();
+        assert_eq!(circuit.output(2).unwrap().to_u64(), Some(42));
+
+        circuit.set_input(0, 255).unwrap();
+        circuit.evaluate().unwrap();
+        assert_eq!(circuit.output(2).unwrap().to_u64(), Some(0x100));
+
+        connect(&mut project, (1, 0), (3, 0));
+        assert!(compile_error(&project).contains("WidthMismatch"));
+    }
+
+    #[test]
+    fn unknowns() {
+        // Leaves the AND gate's second input floating
+        let mut project = and_gate();
+        project.connections.remove(&Connection::output(1, 0));
+        place(&mut project, 4, Project::OR, "or");
+        place(&mut project, 5, Project::OUTPUT, "z");
+        connect(&mut project, (2, 0), (4, 0));
+        connect(&mut project, (0, 0), (4, 1));
+        connect(&mut project, (4, 0), (5, 0));
+
+        let mut circuit = Circuit::compile(&project).unwrap();
+        circuit.evaluate().unwrap();
+        assert_eq!(circuit.output(3).unwrap().to_string(), "0");
+        assert_eq!(circuit.output(5).unwrap().to_string(), "0");
+
+        circuit.set_input(0, 1).unwrap();
+        circuit.evaluate().unwrap();
+        assert_eq!(circuit.output(3).unwrap().to_string(), "x");
+        assert_eq!(circuit.output(5).unwrap().to_string(), "1");
+
+        circuit.set_input(0, Value::z(1)).unwrap();
+        circuit.evaluate().unwrap();
+        assert_eq!(circuit.output(3).unwrap().to_string(), "x");
+        assert_eq!(circuit.output(5).unwrap().to_string(), "x");
+    }
+
+    // Wraps the placements of `inner` into a component with a single-bit input `a` and output `y`
+    fn subcomponent(project: &mut Project, id: ComponentId, inner: Project) {
+        project.components.insert(id, Component {
+            id,
+            name: format!("sub{}", id),
+            inputs: vec!["a".into()],
+            outputs: vec!["y".into()],
+            driver: ComponentDriver::Subcomponent { body: inner.body, connections: inner.connections, wires: vec![] },
+            delay: 1,
+        });
+    }
+
+    #[test]
+    fn subcomponents() {
+        let mut project = project();
+
+        let mut inverter = self::project();
+        place(&mut inverter, 0, Project::INPUT, "a");
+        place(&mut inverter, 1, Project::NOT, "not");
+        place(&mut inverter, 2, Project::OUTPUT, "y");
+        connect(&mut inverter, (0, 0), (1, 0));
+        connect(&mut inverter, (1, 0), (2, 0));
+        subcomponent(&mut project, 20, inverter);
+
+        // Inverts three times, once at its own level and twice through the inverter
+        let mut nested = self::project();
+        place(&mut nested, 0, Project::INPUT, "a");
+        place(&mut nested, 1, 20, "first");
+        place(&mut nested, 2, 20, "second");
+        place(&mut nested, 3, Project::NOT, "not");
+        place(&mut nested, 4, Project::OUTPUT, "y");
+        connect(&mut nested, (0, 0), (1, 0));
+        connect(&mut nested, (1, 0), (2, 0));
+        connect(&mut nested, (2, 0), (3, 0));
+        connect(&mut nested, (3, 0), (4, 0));
+        subcomponent(&mut project, 21, nested);
+
+        place(&mut project, 0, Project::INPUT, "a");
+        place(&mut project, 1, 21, "nested");
+        place(&mut project, 2, Project::OUTPUT, "y");
+        connect(&mut project, (0, 0), (1, 0));
+        connect(&mut project, (1, 0), (2, 0));
+
+        let mut circuit = Circuit::compile(&project).unwrap();
+        for a in [0, 1] {
+            circuit.set_input(0, a).unwrap();
+            circuit.evaluate().unwrap();
+            assert_eq!(circuit.output(2).unwrap().to_u64(), Some(a ^ 1));
+        }
+
+        assert_eq!(circuit.find("not"), None);
+        assert_eq!(circuit.inputs().count(), 1);
+
+        let mut inner = Circuit::compile_component(&project, 20).unwrap();
+        inner.set_input(inner.find("a").unwrap(), 1).unwrap();
+        inner.evaluate().unwrap();
+        assert_eq!(inner.output(inner.find("y").unwrap()).unwrap().to_u64(), Some(0));
+    }
+
+    #[test]
+    fn recursion() {
+        // Component 20 places 21, which places 20 again
+        let mut project = project();
+        for (id, child) in [(20, 21), (21, 20)] {
+            let mut inner = self::project();
+            place(&mut inner, 0, Project::INPUT, "a");
+            place(&mut inner, 1, child, "child");
+            place(&mut inner, 2, Project::OUTPUT, "y");
+            connect(&mut inner, (0, 0), (1, 0));
+            connect(&mut inner, (1, 0), (2, 0));
+            subcomponent(&mut project, id, inner);
+        }
+        place(&mut project, 0, 21, "top");
+
+        assert_eq!(analysis::recursion(&project), Some(vec![20, 21]));
+        assert!(compile_error(&project).contains("RecursiveComponent([21, 20])"));
+    }
+
+    #[test]
+    fn wide_drivers() {
+        let script = |inputs: Vec<Port>| Component {
+            id: 20,
+            name: "sum".into(),
+            inputs,
+            outputs: vec![Port::new("q", 64)],
+            driver: ComponentDriver::Script { script: Script { script: "q = a + b;".into() } },
+            delay: 1,
+        };
+
+        let mut project = project();
+        project.components.insert(20, script(vec![Port::new("a", 32), Port::new("b", 32)]));
+        place(&mut project, 0, 20, "sum");
+        assert!(Circuit::compile(&project).is_ok());
+
+        project.components.insert(20, script(["a", "b", "c", "d", "e"].map(|name| Port::new(name, 64)).to_vec()));
+        assert!(compile_error(&project).contains("TooWide(20)"));
+
+        // Its data inputs and clock add up to 65 bits
+        let mut project = self::project();
+        let mut flip_flop = project.components.remove(&Project::JK_FLIP_FLOP).unwrap();
+        flip_flop.inputs = vec![Port::new("j", 32), Port::new("k", 32), "clk".into()];
+        flip_flop.outputs = vec![Port::new("q", 32), Port::new("q!", 32)];
+        project.components.insert(Project::JK_FLIP_FLOP, flip_flop);
+        place(&mut project, 0, Project::JK_FLIP_FLOP, "jk");
+        assert!(compile_error(&project).contains("TooWide(7)"));
+    }
+
+    #[test]
+    fn clocks() {
+        let mut project = project();
+        project.components.get_mut(&Project::CLOCK).unwrap().driver = ComponentDriver::Clock { period: 10, duty: 0.3 };
+        place(&mut project, 0, Project::CLOCK, "clk");
+        place(&mut project, 1, Project::OUTPUT, "y");
+        connect(&mut project, (0, 0), (1, 0));
+
+        let mut circuit = Circuit::compile(&project).unwrap();
+        let mut levels = String::new();
+
+        for _ in 0..20 {
+            levels += &circuit.output(1).unwrap().to_string();
+            circuit.advance(1).unwrap();
+        }
+
+        assert_eq!(levels, "00000001110000000111");
+    }
+
+    // Places a flip-flop with an input for each data terminal, followed by one for the clock, and an output for `q`
+    fn flip_flop(component: ComponentId) -> Circuit {
+        let mut project = project();
+        let inputs = project.components[&component].inputs.len() as InstanceId;
+
+        for input in 0..inputs {
+            place(&mut project, input, Project::INPUT, "input");
+            connect(&mut project, (input, 0), (inputs, input as u64));
+        }
+
+        place(&mut project, inputs, component, "flip-flop");
+        place(&mut project, inputs + 1, Project::OUTPUT, "q");
+        connect(&mut project, (inputs, 0), (inputs + 1, 0));
+
+        Circuit::compile(&project).unwrap()
+    }
+
+    // Sets the data inputs, then raises and lowers the clock, reading `q` after each
+    fn pulse(circuit: &mut Circuit, data: &[u64]) -> String {
+        let clock = data.len();
+        let q = clock + 2;
+        let mut levels = String::new();
+
+        for (input, value) in data.iter().enumerate() {
+            circuit.set_input(input, *value).unwrap();
+        }
+
+        for level in [1, 0] {
+            circuit.set_input(clock, level).unwrap();
+            circuit.evaluate().unwrap();
+            levels += &circuit.output(q).unwrap().to_string();
+        }
+
+        levels
+    }
+
+    #[test]
+    fn flip_flops() {
+        let mut d = flip_flop(Project::D_FLIP_FLOP);
+        d.evaluate().unwrap();
+        assert_eq!(d.output(3).unwrap().to_string(), "x");
+        assert_eq!(pulse(&mut d, &[1]), "11");
+        assert_eq!(pulse(&mut d, &[0]), "00");
+
+        // Data changing between rising edges is ignored until the next one
+        d.set_input(0, 1).unwrap();
+        d.evaluate().unwrap();
+        assert_eq!(d.output(3).unwrap().to_string(), "0");
+
+        let mut jk = flip_flop(Project::JK_FLIP_FLOP);
+        assert_eq!(pulse(&mut jk, &[1, 0]), "11");
+        assert_eq!(pulse(&mut jk, &[0, 0]), "11");
+        assert_eq!(pulse(&mut jk, &[1, 1]), "00");
+        assert_eq!(pulse(&mut jk, &[1, 1]), "11");
+        assert_eq!(pulse(&mut jk, &[0, 1]), "00");
+
+        // Without a reset, toggling never makes a T flip-flop's unknown starting state known
+        let mut t = flip_flop(Project::T_FLIP_FLOP);
+        assert_eq!(pulse(&mut t, &[1]), "xx");
+
+        let t = Driver::FlipFlop { kind: FlipFlopKind::T, width: 1 };
+        let mut state = NodeState::Sequential { clock: Logic::Zero, q: Value::new(1, 0) };
+        let mut edge = |toggle: u64| {
+            t.evaluate(Value::new(2, toggle), 2, 0, &mut state);
+            t.evaluate(Value::new(2, toggle | 0b10), 2, 0, &mut state).unwrap().to_string()
+        };
+
+        assert_eq!(edge(1), "01");
+        assert_eq!(edge(0), "01");
+        assert_eq!(edge(1), "10");
+    }
+
+    #[test]
+    fn latches() {
+        let mut d = flip_flop(Project::D_LATCH);
+        d.set_input(1, 1).unwrap();
+        for value in [1, 0] {
+            d.set_input(0, value).unwrap();
+            d.evaluate().unwrap();
+            assert_eq!(d.output(3).unwrap().to_u64(), Some(value));
+        }
+
+        d.set_input(1, 0).unwrap();
+        d.set_input(0, 1).unwrap();
+        d.evaluate().unwrap();
+        assert_eq!(d.output(3).unwrap().to_u64(), Some(0));
+
+        // Set and reset are the two data inputs, and the latch has no enable
+        let mut sr = flip_flop(Project::SR_LATCH);
+        let mut read = |s: u64, r: u64| {
+            sr.set_input(0, s).unwrap();
+            sr.set_input(1, r).unwrap();
+            sr.evaluate().unwrap();
+            sr.output(3).unwrap().to_string()
+        };
 
-}
\ No newline at end of file
+        assert_eq!(read(1, 0), "1");
+        assert_eq!(read(0, 0), "1");
+        assert_eq!(read(0, 1), "0");
+        assert_eq!(read(1, 1), "x");
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
-    components::component::LogicxComponent, components::surface::LogicxSurface, project::Project,
-    project::Terminal, wire::LogicxWire, State,
+    components::component::LogicxComponent, components::kmap::KarnaughOverlay, components::surface::LogicxSurface,
+    project::Project, project::Terminal, wire::LogicxWire, State,
 };
 use leptos::prelude::*;
 use signal::signal;
@@ -45,5 +45,6 @@ pub fn edit_mode() -> impl IntoView {
                 .map(|(instance, placement)| view!(<LogicxComponent instance=placement.instance />))
                 .collect_view())}
         </g>
-    </LogicxSurface>)
+    </LogicxSurface>
+    <KarnaughOverlay />)
 }
@@ -0,0 +1,87 @@
+use crate::{
+    kmap::Cell,
+    minimise::Form,
+    project::Project,
+    State
+};
+use leptos::prelude::*;
+
+// Size of a cell in pixels
+const CELL: f64 = 32.0;
+
+/// Draws the Karnaugh map chosen in [`State::karnaugh`] over the top of the surface, with each group outlined in its own colour.
+#[component]
+pub fn karnaugh_overlay() -> impl IntoView {
+    let project = use_context::<ArcRwSignal<Project>>().expect("Failed to get project");
+    let state = use_context::<ArcRwSignal<State>>().expect("Failed to get state");
+
+    move || {
+        let (component, output) = state.read().karnaugh?;
+
+        let map = project.with(|project| project.components.get(&component)
+            .map(|component| component.karnaugh_map(output, Form::SumOfProducts)))?
+            .map_err(|err| leptos::logging::error!("Failed to build Karnaugh map: {:?}", err))
+            .ok()?;
+
+        let code = |value: u64, bits: usize| format!("{:0width$b}", value, width = bits);
+        let (row_bits, column_bits) = (map.row_inputs.len(), map.column_inputs.len());
+
+        // Room for the row and column labels
+        let (left, top) = (CELL * 1.5, CELL * 1.5);
+        let width = left + map.columns.len() as f64 * CELL;
+        let height = top + map.rows.len() as f64 * CELL + CELL;
+
+        Some(view!(<svg class="logicx-kmap" xmlns="http://www.w3.org/2000/svg" width=width height=height>
+            <rect class="logicx-kmap-background" width=width height=height />
+
+            <text class="logicx-kmap-label" x=0 y=0>{format!("{} \\ {}", map.row_inputs.concat(), map.column_inputs.concat())}</text>
+
+            {map.columns.iter().enumerate()
+                .map(|(column, value)| view!(<text class="logicx-kmap-label"
+                    x=left + column as f64 * CELL + CELL / 4.0
+                    y=CELL * 0.75>{code(*value, column_bits)}</text>))
+                .collect_view()}
+            {map.rows.iter().enumerate()
+                .map(|(row, value)| view!(<text class="logicx-kmap-label"
+                    x=CELL / 4.0
+                    y=top + row as f64 * CELL + CELL / 4.0>{code(*value, row_bits)}</text>))
+                .collect_view()}
+
+            {map.cells.iter().enumerate()
+                .flat_map(|(row, cells)| cells.iter().enumerate().map(move |(column, cell)| (row, column, *cell)))
+                .map(|(row, column, cell)| view!(<g>
+                    <rect class="logicx-kmap-cell"
+                        x=left + column as f64 * CELL
+                        y=top + row as f64 * CELL
+                        width=CELL
+                        height=CELL />
+                    <text class="logicx-kmap-value"
+                        x=left + column as f64 * CELL + CELL / 3.0
+                        y=top + row as f64 * CELL + CELL / 3.0>{match cell {
+                        Cell::Zero => "0",
+                        Cell::One => "1",
+                        Cell::DontCare => "-",
+                    }}</text>
+                </g>))
+                .collect_view()}
+
+            // Groups are drawn cell by cell, since they may wrap around the edges of the map
+            {map.groups.iter().enumerate()
+                .flat_map(|(index, group)| {
+                    let inset = 2.0 + (index % 5) as f64 * 2.0;
+                    let colour = format!("hsl({}, 70%, 50%)", index * 137 % 360);
+
+                    group.cells.iter().map(move |(row, column)| view!(<rect class="logicx-kmap-group"
+                        x=left + *column as f64 * CELL + inset
+                        y=top + *row as f64 * CELL + inset
+                        width=CELL - 2.0 * inset
+                        height=CELL - 2.0 * inset
+                        rx=4
+                        stroke=colour.clone() />))
+                })
+                .collect_view()}
+
+            <text class="logicx-kmap-label" x=0 y=height - CELL * 0.75>{format!("{} = {}", map.output, map.expression)}</text>
+        </svg>))
+    }
+}
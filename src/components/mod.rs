@@ -1,10 +1,12 @@
 pub mod component;
 pub mod edit;
+pub mod kmap;
 pub mod play;
 pub mod wire;
 mod surface;
 
 pub use component::LogicxComponent;
 pub use edit::EditMode;
+pub use kmap::KarnaughOverlay;
 pub use play::PlayMode;
 pub use wire::LogicxWire;
\ No newline at end of file
@@ -1,9 +1,104 @@
+use crate::{
+    circuit::Circuit,
+    components::surface::LogicxSurface,
+    components::LogicxComponent,
+    components::LogicxWire,
+    project::Project,
+    State
+};
 use leptos::prelude::*;
-use crate::components::surface::LogicxSurface;
+use std::time::Duration;
+
+// How often simulated time moves forward by one unit while playing, which makes the default clock tick once a second
+const TICK: Duration = Duration::from_millis(100);
 
 #[component]
 pub fn play_mode() -> impl IntoView {
+    let project = use_context::<ArcRwSignal<Project>>().expect("Failed to get project");
+    let state = use_context::<ArcRwSignal<State>>().expect("Failed to get state");
+
+    let circuit = use_context::<ArcRwSignal<Option<Circuit>>>().expect("Failed to get circuit");
+
+    circuit.set(project
+        .with_untracked(|project| Circuit::compile(project))
+        .and_then(|mut circuit| {
+            circuit.record();
+            circuit.evaluate().map(|_| circuit)
+        })
+        .map_err(|err| leptos::logging::error!("Failed to simulate project: {:?}", err))
+        .ok());
+
+    // Time moves forward on its own, so that clocks tick and whatever they drive can be watched changing.
+    // A circuit that stops settling is dropped, just as one that fails to compile is never started.
+    let ticking = circuit.clone();
+    let timer = set_interval_with_handle(move || ticking.update(|circuit| {
+        if let Some(Err(err)) = circuit.as_mut().map(|circuit| circuit.advance(1)) {
+            leptos::logging::error!("Failed to simulate project: {:?}", err);
+            *circuit = None;
+        }
+    }), TICK).ok();
+
+    on_cleanup(move || if let Some(timer) = timer {
+        timer.clear();
+    });
+
+    let wires = project.clone();
+    let components = project.clone();
+
     view!(<LogicxSurface>
-        <g />
+        <g class="wires">
+            {move || wires.with(|project| project.wires.iter()
+                .map(|wire| view!(<LogicxWire wire=wire.clone() />))
+                .collect_view())}
+        </g>
+        <g class="components">
+            {move || components.with(|project| project.body.values()
+                .map(|placement| view!(<LogicxComponent instance=placement.instance />))
+                .collect_view())}
+        </g>
+        <g class="values">
+            {move || {
+                let project = project.read();
+                let grid_scale = state.read().grid_scale;
+
+                let values = circuit.with(|circuit| project.body.values()
+                    .filter_map(|placement| {
+                        let circuit = circuit.as_ref()?;
+
+                        let (value, toggle) = match (circuit.input(placement.instance), circuit.output(placement.instance)) {
+                            (Ok(value), _) => (value, true),
+                            (_, Ok(value)) => (value, false),
+                            _ => return None,
+                        };
+
+                        Some((placement.instance, placement.pos, value, toggle))
+                    })
+                    .collect::<Vec<_>>());
+
+                values.into_iter()
+                    .map(|(instance, pos, value, toggle)| {
+                        let circuit = circuit.clone();
+
+                        // Clicking an input counts it up, which flips single-bit inputs
+                        let on_click = move |_| if toggle {
+                            circuit.update(|circuit| if let Some(circuit) = circuit {
+                                let next = value.to_u64().map(|value| value.wrapping_add(1)).unwrap_or(0);
+
+                                if let Err(err) = circuit.set_input(instance, next).and_then(|_| circuit.evaluate()) {
+                                    leptos::logging::error!("Failed to simulate project: {:?}", err);
+                                }
+                            });
+                        };
+
+                        view!(<text class="logicx-value"
+                            class:logicx-unknown=!value.is_known()
+                            class:logicx-toggle=toggle
+                            x=pos.0 * grid_scale
+                            y=pos.1 * grid_scale - grid_scale / 2.0
+                            on:click=on_click>{value.to_string()}</text>)
+                    })
+                    .collect_view()
+            }}
+        </g>
     </LogicxSurface>)
-}
\ No newline at end of file
+}
@@ -1,4 +1,4 @@
-use crate::{project::Coord, project::Project, project::Terminal, project::Wire, State};
+use crate::{project::Connection, project::Coord, project::Project, project::Terminal, project::Wire, State};
 use leptos::prelude::*;
 use signal::signal;
 
@@ -65,16 +65,22 @@ pub fn logicx_wire(wire: Wire) -> impl IntoView {
             })
         })
     });
+    let width = Signal::derive(move || {
+        use_context::<ArcRwSignal<Project>>()
+            .and_then(|project| project.read().width(Connection { instance: wire.from, terminal: wire.from_terminal }))
+            .unwrap_or(1)
+    });
     let state = use_context::<ArcRwSignal<State>>()?;
 
-    signal!(|from, to, delta, state| {
+    signal!(|from, to, delta, state, width| {
         let from = from.as_ref()?.clone();
         let to = to.as_ref()?.clone();
 
         let delta = delta.as_ref()?.clone();
+        let bus = *width > 1;
 
         Some(
-            view!(<path class="logicx-wire" d=format!("M {sx} {sy} C {mx} {sy}, {sx} {my}, {mx} {my}",
+            view!(<path class="logicx-wire" class:logicx-bus=bus d=format!("M {sx} {sy} C {mx} {sy}, {sx} {my}, {mx} {my}",
             sx = from.pos.0 * state.grid_scale + delta.0,
             sy = from.pos.1 * state.grid_scale + delta.1,
             mx = to.0,
@@ -0,0 +1,326 @@
+use crate::{
+    bdd::Bdd,
+    bdd::Bdds,
+    circuit::Circuit,
+    circuit::CircuitError,
+    circuit::Driver,
+    project::Component,
+    project::ComponentDriver,
+    project::ComponentId,
+    project::InstanceId,
+    project::Port,
+    project::Project,
+    Result
+};
+use std::{
+    collections::HashMap,
+    collections::HashSet,
+    fmt::Display,
+    fmt::Formatter
+};
+
+/// The outcome of comparing two components with [`check`].
+#[derive(Debug, Clone)]
+pub enum Equivalence {
+    Equivalent,
+    Counterexample(Counterexample),
+}
+
+/// An input on which two components disagree, and what each of them outputs for it. Values are listed by port.
+#[derive(Debug, Clone)]
+pub struct Counterexample {
+    pub inputs: Vec<(String, u64)>,
+    pub left: Vec<(String, u64)>,
+    pub right: Vec<(String, u64)>,
+}
+
+impl Display for Counterexample {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let list = |values: &[(String, u64)]| values.iter()
+            .map(|(port, value)| format!("{} = {}", port, value))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        write!(f, "{}: {} but {}", list(&self.inputs), list(&self.left), list(&self.right))
+    }
+}
+
+/// Proves that two combinational components compute the same outputs from the same inputs, or finds an input on which they differ.
+///
+/// Ports are matched by name, so both components need the same input and output ports, though not necessarily in the same order.
+/// Each component is turned into a binary decision diagram per output bit, so the comparison doesn't have to try every input.
+pub fn check(project: &Project, left: ComponentId, right: ComponentId) -> Result<Equivalence> {
+    let definition = |id: ComponentId| project.components.get(&id)
+        .ok_or(EquivalenceError::UnknownComponent(id));
+    let (left, right) = (definition(left)?, definition(right)?);
+
+    for (ours, theirs) in [(&left.inputs, &right.inputs), (&left.outputs, &right.outputs)] {
+        let names = |ports: &Vec<Port>| ports.iter().map(|port| (port.name.clone(), port.width)).collect::<HashSet<_>>();
+
+        if let Some(port) = names(ours).symmetric_difference(&names(theirs)).next() {
+            return Err(EquivalenceError::PortMismatch(port.0.clone()).into());
+        }
+    }
+
+    // One variable per input bit. The bits of different ports are interleaved, which keeps the diagrams small
+    // for circuits that combine their inputs bit by bit, such as adders and comparators.
+    let mut bdds = Bdds::new();
+    let mut variables = left.inputs.iter()
+        .map(|port| (port.name.clone(), vec![]))
+        .collect::<HashMap<_, Vec<_>>>();
+    let mut next = 0;
+
+    for bit in 0..left.inputs.iter().map(|port| port.width).max().unwrap_or(0) {
+        for port in left.inputs.iter().filter(|port| bit < port.width) {
+            if let Some(bits) = variables.get_mut(&port.name) {
+                bits.push(bdds.variable(next));
+            }
+
+            next += 1;
+        }
+    }
+
+    let arrange = |component: &Component| component.inputs.iter()
+        .flat_map(|port| variables[&port.name].iter().copied())
+        .collect::<Vec<_>>();
+
+    let left_outputs = outputs(left, symbolic(project, &mut bdds, left, arrange(left))?);
+    let right_outputs = outputs(right, symbolic(project, &mut bdds, right, arrange(right))?);
+
+    let mut differ = Bdds::FALSE;
+
+    for (port, bits) in left_outputs.iter() {
+        for (ours, theirs) in bits.iter().zip(right_outputs[port].iter()) {
+            let difference = bdds.xor(*ours, *theirs);
+            differ = bdds.or(differ, difference);
+        }
+    }
+
+    let Some(assignment) = bdds.satisfy(differ) else {
+        return Ok(Equivalence::Equivalent);
+    };
+
+    let value = |bits: &[Bdd]| bits.iter()
+        .enumerate()
+        .map(|(bit, bdd)| (bdds.evaluate(*bdd, |variable| assignment.contains(&variable)) as u64) << bit)
+        .sum::<u64>();
+    let values = |ports: &[Port], bits: &HashMap<String, Vec<Bdd>>| ports.iter()
+        .map(|port| (port.name.clone(), value(&bits[&port.name])))
+        .collect();
+
+    Ok(Equivalence::Counterexample(Counterexample {
+        inputs: values(&left.inputs, &variables),
+        left: values(&left.outputs, &left_outputs),
+        right: values(&left.outputs, &right_outputs),
+    }))
+}
+
+// Splits packed output bits up by port
+fn outputs(component: &Component, bits: Vec<Bdd>) -> HashMap<String, Vec<Bdd>> {
+    let mut bits = bits.into_iter();
+
+    component.outputs.iter()
+        .map(|port| (port.name.clone(), bits.by_ref().take(port.width as usize).collect()))
+        .collect()
+}
+
+// The output bits of a component in terms of its input bits, both packed the same way as in simulation
+fn symbolic(project: &Project, bdds: &mut Bdds, component: &Component, inputs: Vec<Bdd>) -> Result<Vec<Bdd>> {
+    let width = component.outputs.iter().map(|port| port.width as usize).sum();
+
+    if !matches!(component.driver, ComponentDriver::Subcomponent { .. }) {
+        let driver = Driver::compile(component)?
+            .ok_or(EquivalenceError::Unsupported(component.id))?;
+
+        return drive(bdds, &driver, component.id, inputs, width);
+    }
+
+    let circuit = Circuit::compile_component(project, component.id)?;
+
+    if !circuit.is_combinational() {
+        return Err(EquivalenceError::NotCombinational(component.id).into());
+    }
+
+    let mismatch = |port: &str| CircuitError::PortMismatch { component: component.id, port: port.to_owned() };
+
+    // The bits of each input port, and where the bits of each output port end up
+    let mut ports = HashMap::new();
+    let mut offset = 0;
+    for port in component.inputs.iter() {
+        ports.insert(port.name.as_str(), inputs[offset..offset + port.width as usize].to_vec());
+        offset += port.width as usize;
+    }
+    let mut results = HashMap::new();
+
+    let mut nets = vec![vec![]; circuit.nets.len()];
+
+    // Kahn's algorithm, counting how many connected input terminals of each node are still unknown
+    let mut pending = circuit.nodes.iter()
+        .map(|node| node.inputs.iter().flatten().count())
+        .collect::<Vec<_>>();
+    let mut ready = (0..circuit.nodes.len())
+        .filter(|node| pending[*node] == 0)
+        .collect::<Vec<_>>();
+
+    while let Some(index) = ready.pop() {
+        let node = &circuit.nodes[index];
+
+        let mut input = vec![];
+        for net in node.inputs.iter() {
+            match net {
+                Some(net) => input.extend(nets[*net].iter().copied()),
+                // A floating input reads as Z, which no diagram can represent
+                None => return Err(EquivalenceError::Floating { component: component.id, instance: node.instance }.into()),
+            }
+        }
+
+        let output = match &circuit.drivers[node.driver] {
+            Driver::Input => node.label.as_deref()
+                .and_then(|label| ports.get(label))
+                .cloned()
+                .ok_or_else(|| mismatch(node.label.as_deref().unwrap_or_default()))?,
+            Driver::Output => {
+                results.insert(node.label.clone().unwrap_or_default(), input);
+                vec![]
+            },
+            driver => {
+                let width = node.outputs.iter().map(|net| circuit.nets[*net].width as usize).sum();
+                drive(bdds, driver, node.component, input, width)?
+            },
+        };
+
+        let mut output = output.into_iter();
+
+        for net in node.outputs.iter() {
+            nets[*net] = output.by_ref().take(circuit.nets[*net].width as usize).collect();
+
+            for sink in circuit.nets[*net].sinks.iter().collect::<HashSet<_>>() {
+                pending[*sink] -= circuit.nodes[*sink].inputs.iter().filter(|input| **input == Some(*net)).count();
+
+                if pending[*sink] == 0 {
+                    ready.push(*sink);
+                }
+            }
+        }
+    }
+
+    let mut bits = vec![];
+    for port in component.outputs.iter() {
+        bits.extend(results.remove(&port.name).ok_or_else(|| mismatch(&port.name))?);
+    }
+
+    Ok(bits)
+}
+
+// The output bits of a single driver
+fn drive(bdds: &mut Bdds, driver: &Driver, component: ComponentId, inputs: Vec<Bdd>, width: usize) -> Result<Vec<Bdd>> {
+    match driver {
+        Driver::Buffer => Ok(inputs),
+        Driver::TruthTable { truth } => Ok(bdds.truth_table(truth, &inputs, width)),
+        Driver::Bdd { table } => bdds.load(table, &inputs)
+            .ok_or(EquivalenceError::Unsupported(component).into()),
+        Driver::Expression { inputs: names, outputs } => outputs.iter()
+            .map(|output| match output {
+                Some(expr) => Ok(bdds.expression(expr, &|name| inputs[names[name] as usize])),
+                // Bits without an equation are X
+                None => Err(EquivalenceError::Unsupported(component).into()),
+            })
+            .collect(),
+        _ => Err(EquivalenceError::Unsupported(component).into()),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum EquivalenceError {
+    UnknownComponent(ComponentId),
+    // A port one component has and the other doesn't, or has with a different width
+    PortMismatch(String),
+    NotCombinational(ComponentId),
+    // A component whose behaviour can't be expressed as a Boolean function, such as a script or a flip-flop
+    Unsupported(ComponentId),
+    Floating { component: ComponentId, instance: InstanceId },
+    // A component converted to BDDs whose driver isn't a truth table
+    NotATruthTable(ComponentId),
+}
+
+impl std::error::Error for EquivalenceError {}
+impl std::fmt::Display for EquivalenceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        circuit::tests::connect,
+        circuit::tests::place,
+        circuit::tests::project,
+        project::FlipFlopKind
+    };
+
+    // A component with single-bit inputs `a` and `b` and output `y`
+    fn component(project: &mut Project, id: ComponentId, driver: ComponentDriver) {
+        project.components.insert(id, Component {
+            id,
+            name: format!("component{}", id),
+            inputs: vec!["a".into(), "b".into()],
+            outputs: vec!["y".into()],
+            driver,
+            delay: 1,
+        });
+    }
+
+    fn counterexample(project: &Project, left: ComponentId, right: ComponentId) -> Option<Counterexample> {
+        match project.equivalent(left, right).unwrap() {
+            Equivalence::Equivalent => None,
+            Equivalence::Counterexample(counterexample) => Some(counterexample),
+        }
+    }
+
+    #[test]
+    fn counterexamples() {
+        let mut project = project();
+        component(&mut project, 20, ComponentDriver::truth([(0, 0), (1, 0), (2, 0), (3, 1)]));
+        component(&mut project, 21, ComponentDriver::Expression { equations: vec!["y = b | a".parse().unwrap()] });
+
+        // The same AND, built from a gate
+        let mut gate = self::project();
+        place(&mut gate, 0, Project::INPUT, "b");
+        place(&mut gate, 1, Project::INPUT, "a");
+        place(&mut gate, 2, Project::AND, "and");
+        place(&mut gate, 3, Project::OUTPUT, "y");
+        connect(&mut gate, (0, 0), (2, 0));
+        connect(&mut gate, (1, 0), (2, 1));
+        connect(&mut gate, (2, 0), (3, 0));
+        component(&mut project, 22, ComponentDriver::Subcomponent { body: gate.body, connections: gate.connections, wires: vec![] });
+
+        assert!(counterexample(&project, 20, 22).is_none());
+
+        // AND and OR differ exactly when one input is set
+        let counterexample = counterexample(&project, 22, 21).unwrap();
+        let inputs = counterexample.inputs.iter().map(|(_, value)| *value).collect::<Vec<_>>();
+        assert_eq!(inputs.iter().sum::<u64>(), 1);
+        assert_eq!((counterexample.left, counterexample.right), (vec![("y".to_owned(), 0)], vec![("y".to_owned(), 1)]));
+
+        project.convert_to_bdd(20).unwrap();
+        assert!(self::counterexample(&project, 20, 22).is_none());
+        assert!(self::counterexample(&project, 20, 21).is_some());
+    }
+
+    #[test]
+    fn errors() {
+        let mut project = project();
+        component(&mut project, 20, ComponentDriver::truth([(3, 1)]));
+        component(&mut project, 21, ComponentDriver::FlipFlop { kind: FlipFlopKind::D });
+        component(&mut project, 22, ComponentDriver::truth([]));
+        project.components.get_mut(&22).unwrap().inputs[1] = Port::new("b", 2);
+
+        let error = |left, right| project.equivalent(left, right).err().unwrap().to_string();
+        assert!(error(20, 21).contains("Unsupported(21)"));
+        assert!(error(20, 22).contains("PortMismatch(\"b\")"));
+        assert!(error(20, 99).contains("UnknownComponent(99)"));
+        assert!(project.convert_to_bdd(21).err().unwrap().to_string().contains("NotATruthTable(21)"));
+    }
+}
@@ -28,6 +28,12 @@ macro_rules! multi_error {
                 }
             }
 
+            impl Error {
+                pub fn inner(&self) -> &Inner {
+                    &self.inner
+                }
+            }
+
             impl std::error::Error for Error {}
             impl std::fmt::Display for Error {
                 fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { std::fmt::Debug::fmt(self, f) }
@@ -48,7 +54,21 @@ macro_rules! multi_error {
 }
 
 multi_error! { global();
-    ManualError = crate::error::ManualError
+    ManualError = crate::error::ManualError;
+    CircuitError = crate::circuit::CircuitError;
+    ParseValueError = crate::logic::ParseValueError;
+    ScriptError = crate::script::ScriptError;
+    MemoryError = crate::memory::MemoryError;
+    StimulusError = crate::stimulus::StimulusError;
+    TestBenchError = crate::testbench::TestBenchError;
+    TruthError = crate::truth::TruthError;
+    MinimiseError = crate::minimise::MinimiseError;
+    ExpressionError = crate::expression::ExpressionError;
+    SynthesisError = crate::synthesis::SynthesisError;
+    KarnaughError = crate::kmap::KarnaughError;
+    EquivalenceError = crate::equivalence::EquivalenceError;
+    ExportError = crate::interop::ExportError;
+    ImportError = crate::interop::ImportError
 
 }
 
@@ -0,0 +1,446 @@
+//! Boolean expressions such as `a & !b | c ^ d`.
+//!
+//! From tightest to loosest binding, the operators are `!` (not), `&` (and), `^` (xor) and `|` (or).
+//! Variables are names made of letters, digits and underscores, optionally followed by a bit index such as `a[3]`.
+//! `0` and `1` are constants, and parentheses group as usual.
+//!
+//! Expressions are evaluated recursively, so ones nested more than [`NESTING_LIMIT`] levels deep are rejected when parsed.
+//! Each operator in a chain like `a | b | c` counts as a level, as does each `!` and pair of parentheses.
+
+use crate::logic::Value;
+use serde::{
+    de::Visitor,
+    Deserialize,
+    Deserializer,
+    Serialize,
+    Serializer
+};
+use std::{
+    fmt::Display,
+    fmt::Formatter,
+    str::FromStr
+};
+
+/// How deeply a parsed expression may nest.
+pub const NESTING_LIMIT: usize = 256;
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum Expr {
+    Constant(bool),
+    Variable(String),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Xor(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    /// Every variable in the expression, in the order they first appear.
+    pub fn variables(&self) -> Vec<String> {
+        fn visit(expr: &Expr, variables: &mut Vec<String>) {
+            match expr {
+                Expr::Constant(_) => {},
+                Expr::Variable(name) if variables.contains(name) => {},
+                Expr::Variable(name) => variables.push(name.clone()),
+                Expr::Not(inner) => visit(inner, variables),
+                Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) | Expr::Xor(lhs, rhs) => {
+                    visit(lhs, variables);
+                    visit(rhs, variables);
+                },
+            }
+        }
+
+        let mut variables = vec![];
+        visit(self, &mut variables);
+        variables
+    }
+
+    /// Evaluates the expression with the value of each variable given by `value`.
+    pub fn evaluate(&self, value: &impl Fn(&str) -> bool) -> bool {
+        match self {
+            Self::Constant(constant) => *constant,
+            Self::Variable(name) => value(name),
+            Self::Not(inner) => !inner.evaluate(value),
+            Self::And(lhs, rhs) => lhs.evaluate(value) && rhs.evaluate(value),
+            Self::Or(lhs, rhs) => lhs.evaluate(value) || rhs.evaluate(value),
+            Self::Xor(lhs, rhs) => lhs.evaluate(value) ^ rhs.evaluate(value),
+        }
+    }
+
+    /// Evaluates the expression over single-bit values, so that unknown inputs only make the result unknown where they matter.
+    pub fn evaluate_value(&self, value: &impl Fn(&str) -> Value) -> Value {
+        match self {
+            Self::Constant(constant) => Value::from(*constant),
+            Self::Variable(name) => value(name),
+            Self::Not(inner) => !inner.evaluate_value(value),
+            Self::And(lhs, rhs) => lhs.evaluate_value(value) & rhs.evaluate_value(value),
+            Self::Or(lhs, rhs) => lhs.evaluate_value(value) | rhs.evaluate_value(value),
+            Self::Xor(lhs, rhs) => lhs.evaluate_value(value) ^ rhs.evaluate_value(value),
+        }
+    }
+
+    /// Folds away constants, so that the result is either a constant or has none left in it.
+    pub fn simplify(self) -> Self {
+        use Expr::*;
+
+        match self {
+            Not(inner) => match inner.simplify() {
+                Constant(constant) => Constant(!constant),
+                Not(inner) => *inner,
+                inner => Not(Box::new(inner)),
+            },
+            And(lhs, rhs) => match (lhs.simplify(), rhs.simplify()) {
+                (Constant(false), _) | (_, Constant(false)) => Constant(false),
+                (Constant(true), other) | (other, Constant(true)) => other,
+                (lhs, rhs) => And(Box::new(lhs), Box::new(rhs)),
+            },
+            Or(lhs, rhs) => match (lhs.simplify(), rhs.simplify()) {
+                (Constant(true), _) | (_, Constant(true)) => Constant(true),
+                (Constant(false), other) | (other, Constant(false)) => other,
+                (lhs, rhs) => Or(Box::new(lhs), Box::new(rhs)),
+            },
+            Xor(lhs, rhs) => match (lhs.simplify(), rhs.simplify()) {
+                (Constant(false), other) | (other, Constant(false)) => other,
+                (Constant(true), other) | (other, Constant(true)) => Not(Box::new(other)).simplify(),
+                (lhs, rhs) => Xor(Box::new(lhs), Box::new(rhs)),
+            },
+            expr => expr,
+        }
+    }
+
+    // How tightly an operator binds, so that Display only adds the parentheses it needs
+    pub(crate) fn precedence(&self) -> u8 {
+        match self {
+            Self::Or(..) => 0,
+            Self::Xor(..) => 1,
+            Self::And(..) => 2,
+            Self::Not(..) => 3,
+            Self::Constant(_) | Self::Variable(_) => 4,
+        }
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let operand = |f: &mut Formatter<'_>, expr: &Expr, precedence: u8| match expr.precedence() < precedence {
+            true => write!(f, "({})", expr),
+            false => write!(f, "{}", expr),
+        };
+
+        let (lhs, rhs, op) = match self {
+            Self::Constant(constant) => return write!(f, "{}", *constant as u8),
+            Self::Variable(name) => return write!(f, "{}", name),
+            Self::Not(inner) => {
+                write!(f, "!")?;
+                return operand(f, inner, self.precedence());
+            },
+            Self::And(lhs, rhs) => (lhs, rhs, "&"),
+            Self::Or(lhs, rhs) => (lhs, rhs, "|"),
+            Self::Xor(lhs, rhs) => (lhs, rhs, "^"),
+        };
+
+        // Operators group to the left, so a right operand of the same precedence needs parentheses
+        operand(f, lhs, self.precedence())?;
+        write!(f, " {} ", op)?;
+        operand(f, rhs, self.precedence() + 1)
+    }
+}
+
+impl FromStr for Expr {
+    type Err = ExpressionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            chars: s.chars().collect(),
+            position: 0,
+            depth: 0,
+        };
+
+        let expr = parser.or()?;
+
+        parser.skip_whitespace();
+        match parser.peek() {
+            None => Ok(expr),
+            Some(c) => Err(parser.error(format!("Unexpected '{}'", c))),
+        }
+    }
+}
+
+/// Defines one output bit, written `q = a & (b | !c)`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Equation {
+    pub output: String,
+    pub expr: Expr,
+}
+
+impl Display for Equation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} = {}", self.output, self.expr)
+    }
+}
+
+impl FromStr for Equation {
+    type Err = ExpressionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((output, expr)) = s.split_once('=') else {
+            return Err(ExpressionError {
+                column: 1,
+                message: "Expected 'output = expression'".to_owned(),
+            });
+        };
+
+        if output.trim().is_empty() {
+            return Err(ExpressionError {
+                column: 1,
+                message: "Expected an output name".to_owned(),
+            });
+        }
+
+        // Columns are reported relative to the whole equation
+        let expr = expr.parse().map_err(|err: ExpressionError| ExpressionError {
+            column: err.column + output.chars().count() + 1,
+            ..err
+        })?;
+
+        Ok(Self {
+            output: output.trim().to_owned(),
+            expr,
+        })
+    }
+}
+
+impl Serialize for Equation {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Equation {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>
+    {
+        deserializer.deserialize_str(EquationVisitor)
+    }
+}
+
+struct EquationVisitor;
+
+impl Visitor<'_> for EquationVisitor {
+    type Value = Equation;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "Expecting an equation of format 'output = expression'")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        v.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+    // How many levels deep the expression being parsed is nested
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.position += 1;
+        }
+    }
+
+    // Consumes `op` if it is the next character
+    fn eat(&mut self, op: char) -> bool {
+        self.skip_whitespace();
+
+        match self.peek() == Some(op) {
+            true => {
+                self.position += 1;
+                true
+            },
+            false => false,
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> ExpressionError {
+        ExpressionError {
+            column: self.position + 1,
+            message: message.into(),
+        }
+    }
+
+    // Goes one level deeper, failing once past the nesting limit. Callers step back out by lowering `self.depth`.
+    fn nest(&mut self) -> Result<(), ExpressionError> {
+        self.depth += 1;
+
+        match self.depth > NESTING_LIMIT {
+            true => Err(self.error(format!("Nested more than {} levels deep", NESTING_LIMIT))),
+            false => Ok(()),
+        }
+    }
+
+    // Parses a chain of `op`, each operator nesting the expression one level deeper
+    fn chain(&mut self, op: char, operand: fn(&mut Self) -> Result<Expr, ExpressionError>, build: fn(Box<Expr>, Box<Expr>) -> Expr) -> Result<Expr, ExpressionError> {
+        let mut lhs = operand(self)?;
+        let mut levels = 0;
+
+        while self.eat(op) {
+            self.nest()?;
+            levels += 1;
+
+            lhs = build(Box::new(lhs), Box::new(operand(self)?));
+        }
+
+        self.depth -= levels;
+        Ok(lhs)
+    }
+
+    fn or(&mut self) -> Result<Expr, ExpressionError> {
+        self.chain('|', Self::xor, Expr::Or)
+    }
+
+    fn xor(&mut self) -> Result<Expr, ExpressionError> {
+        self.chain('^', Self::and, Expr::Xor)
+    }
+
+    fn and(&mut self) -> Result<Expr, ExpressionError> {
+        self.chain('&', Self::unary, Expr::And)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ExpressionError> {
+        if self.eat('!') {
+            self.nest()?;
+            let inner = self.unary()?;
+            self.depth -= 1;
+
+            return Ok(Expr::Not(Box::new(inner)));
+        }
+
+        if self.eat('(') {
+            self.nest()?;
+            let inner = self.or()?;
+            self.depth -= 1;
+
+            return match self.eat(')') {
+                true => Ok(inner),
+                false => Err(self.error("Expected ')'")),
+            };
+        }
+
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some('0') => {
+                self.position += 1;
+                Ok(Expr::Constant(false))
+            },
+            Some('1') => {
+                self.position += 1;
+                Ok(Expr::Constant(true))
+            },
+            Some(c) if c.is_alphabetic() || c == '_' => Ok(Expr::Variable(self.name())),
+            Some(c) => Err(self.error(format!("Unexpected '{}'", c))),
+            None => Err(self.error("Unexpected end of expression")),
+        }
+    }
+
+    fn name(&mut self) -> String {
+        let start = self.position;
+
+        while self.peek().is_some_and(|c| c.is_alphanumeric() || c == '_') {
+            self.position += 1;
+        }
+
+        // A bit index, as in `a[3]`
+        if self.peek() == Some('[') {
+            let end = self.chars[self.position..].iter()
+                .position(|c| *c == ']')
+                .map(|end| self.position + end);
+
+            if let Some(end) = end.filter(|end| *end > self.position + 1 && self.chars[self.position + 1..*end].iter().all(char::is_ascii_digit)) {
+                self.position = end + 1;
+            }
+        }
+
+        self.chars[start..self.position].iter().collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExpressionError {
+    pub column: usize,
+    pub message: String,
+}
+
+impl std::error::Error for ExpressionError {}
+impl std::fmt::Display for ExpressionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.column, self.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(text: &str) -> ExpressionError {
+        text.parse::<Expr>().err().unwrap()
+    }
+
+    #[test]
+    fn equations() {
+        let equation = "q = a & !(b[1] | c) ^ 1".parse::<Equation>().unwrap();
+        assert_eq!(equation.output, "q");
+        assert_eq!(equation.to_string(), "q = a & !(b[1] | c) ^ 1");
+
+        let json = serde_json::to_string(&vec![equation.clone()]).unwrap();
+        assert_eq!(json, r#"["q = a & !(b[1] | c) ^ 1"]"#);
+        assert_eq!(serde_json::from_str::<Vec<Equation>>(&json).unwrap(), vec![equation]);
+
+        // Only the parentheses the precedence needs are kept
+        let equation = serde_json::from_str::<Equation>(r#""y = ((a | b)) | (c & d)""#).unwrap();
+        assert_eq!(equation.to_string(), "y = a | b | c & d");
+
+        let error = serde_json::from_str::<Equation>(r#""y = a &""#).err().unwrap().to_string();
+        assert!(error.starts_with("8: Unexpected end of expression"));
+        assert!(serde_json::from_str::<Equation>(r#""a & b""#).is_err());
+    }
+
+    #[test]
+    fn nesting() {
+        let deepest = format!("{}a{}", "(".repeat(NESTING_LIMIT), ")".repeat(NESTING_LIMIT));
+        assert_eq!(deepest.parse::<Expr>().unwrap(), Expr::Variable("a".to_owned()));
+
+        let chain = format!("a{}", " | a".repeat(NESTING_LIMIT));
+        assert!(chain.parse::<Expr>().unwrap().evaluate(&|_| true));
+
+        let message = format!("Nested more than {} levels deep", NESTING_LIMIT);
+
+        let parentheses = error(&format!("{}a{}", "(".repeat(100_000), ")".repeat(100_000)));
+        assert_eq!((parentheses.column, parentheses.message.as_str()), (NESTING_LIMIT + 2, message.as_str()));
+
+        let negations = error(&format!("{}a", "!".repeat(100_000)));
+        assert_eq!((negations.column, negations.message.as_str()), (NESTING_LIMIT + 2, message.as_str()));
+
+        let chain = error(&format!("a{}", " & a".repeat(100_000)));
+        assert_eq!((chain.column, chain.message.as_str()), (4 * NESTING_LIMIT + 4, message.as_str()));
+
+        // Columns count from the start of an equation
+        let equation = format!("q = {}a", "!".repeat(100_000)).parse::<Equation>().err().unwrap();
+        assert_eq!(equation.column, NESTING_LIMIT + 6);
+    }
+}
@@ -0,0 +1,839 @@
+use crate::{
+    bdd::BddTable,
+    bdd::Bdds,
+    circuit::CircuitError,
+    interop,
+    interop::Body,
+    interop::Design,
+    interop::ExportError,
+    interop::ImportError,
+    interop::Module,
+    interop::Naming,
+    interop::Schematic,
+    interop::Signal,
+    minimise,
+    minimise::Form,
+    project::Component,
+    project::ComponentDriver,
+    project::ComponentId,
+    project::Connection,
+    project::FlipFlopKind,
+    project::LatchKind,
+    project::Port,
+    project::Project,
+    truth,
+    Result
+};
+use std::{
+    collections::HashMap,
+    collections::HashSet,
+    fmt::Write
+};
+
+// Names in BLIF only have to avoid whitespace and a few symbols, but keeping to identifiers keeps other tools happy
+const NAMING: Naming = Naming {
+    keywords: &[],
+    strict: false,
+};
+
+/// Writes a project as BLIF, with `top` as the first model and a model after it for every component the project uses.
+///
+/// Every signal is split into bits, with the bits of buses named as in `a[3]`. Truth tables become `.names` covers,
+/// minimised when they are narrow enough and otherwise built from binary decision diagrams, as expressions are.
+/// Flip-flops and latches become `.latch`es controlled by their clock or enable.
+pub fn export(project: &Project, top: &str) -> Result<String> {
+    let design = Design::new(project, top, &NAMING, false)?;
+    let mut models = vec![];
+
+    // Tools take the first model to be the top level
+    for module in design.modules.iter().rev() {
+        let mut blif = String::new();
+        write_model(&mut blif, module)?;
+        models.push(blif);
+    }
+
+    Ok(models.join("\n"))
+}
+
+fn write_model(out: &mut String, module: &Module) -> Result<()> {
+    writeln!(out, ".model {}", module.name).unwrap();
+
+    if !module.inputs.is_empty() {
+        writeln!(out, ".inputs {}", bits(&module.inputs).join(" ")).unwrap();
+    }
+
+    if !module.outputs.is_empty() {
+        writeln!(out, ".outputs {}", bits(&module.outputs).join(" ")).unwrap();
+    }
+
+    match &module.body {
+        Body::Netlist { instances, assigns, .. } => {
+            for instance in instances {
+                let connections = instance.connections.iter()
+                    .filter_map(|(port, net)| net.as_ref().map(|net| (port, net)))
+                    .flat_map(|(port, net)| (0..port.width).map(move |index| format!("{}={}", bit(&port.name, port.width, index), bit(net, port.width, index))))
+                    .collect::<Vec<_>>();
+
+                writeln!(out, ".subckt {} {}", instance.module, connections.join(" ")).unwrap();
+            }
+
+            for (port, net) in assigns {
+                let width = module.outputs.iter()
+                    .find(|signal| signal.name == *port)
+                    .map(|signal| signal.width)
+                    .unwrap_or(1);
+
+                for index in 0..width {
+                    names(out, &[bit(net, width, index)], &bit(port, width, index), &["1"]);
+                }
+            }
+        },
+        Body::Leaf(component) => leaf(out, module, component)?,
+    }
+
+    writeln!(out, ".end").unwrap();
+    Ok(())
+}
+
+fn bit(name: &str, width: u8, index: u8) -> String {
+    match width {
+        1 => name.to_owned(),
+        _ => format!("{}[{}]", name, index),
+    }
+}
+
+fn bits(signals: &[Signal]) -> Vec<String> {
+    signals.iter()
+        .flat_map(|signal| (0..signal.width).map(move |index| bit(&signal.name, signal.width, index)))
+        .collect()
+}
+
+// Writes a single-output cover. Each cube gives the value of every input, or `-` where it doesn't matter, on a row where the output is 1.
+fn names(out: &mut String, inputs: &[String], output: &str, cubes: &[&str]) {
+    let signals = inputs.iter()
+        .map(String::as_str)
+        .chain([output])
+        .collect::<Vec<_>>();
+
+    writeln!(out, ".names {}", signals.join(" ")).unwrap();
+
+    for cube in cubes {
+        match inputs.is_empty() {
+            true => writeln!(out, "1"),
+            false => writeln!(out, "{} 1", cube),
+        }.unwrap();
+    }
+}
+
+// Writes the body of a model for a component that isn't made of other components
+fn leaf(out: &mut String, module: &Module, component: &Component) -> Result<()> {
+    let width = |ports: &[Port]| ports.iter().map(|port| port.width as u32).sum::<u32>();
+    let (inputs, outputs) = (bits(&module.inputs), bits(&module.outputs));
+
+    let mut taken = module.taken(component)?;
+    let mut internal = |name: &str| interop::unique(name, &mut taken);
+
+    match &component.driver {
+        ComponentDriver::TruthTable { truth, dont_care } if width(&component.inputs) <= minimise::EXACT_LIMIT => {
+            for (index, output) in outputs.iter().enumerate() {
+                let expression = minimise::minimise(truth, dont_care, inputs.len() as u32, index as u8, Form::SumOfProducts, inputs.clone());
+                let cubes = expression.terms.iter()
+                    .map(|term| (0..inputs.len())
+                        .map(|bit| match (term.mask >> bit & 1, term.polarity >> bit & 1) {
+                            (0, _) => '-',
+                            (_, 1) => '1',
+                            _ => '0',
+                        })
+                        .collect::<String>())
+                    .collect::<Vec<_>>();
+
+                names(out, &inputs, output, &cubes.iter().map(String::as_str).collect::<Vec<_>>());
+            }
+        },
+        ComponentDriver::TruthTable { truth, .. } => {
+            let table = BddTable::from_truth(truth, inputs.len() as u32, outputs.len());
+            diagram(out, &table, &inputs, &outputs, &mut internal);
+        },
+        ComponentDriver::Expression { equations } => {
+            let index = Port::bit_names(&component.inputs)
+                .into_iter()
+                .enumerate()
+                .map(|(index, name)| (name, index))
+                .collect::<HashMap<_, _>>();
+
+            let mut bdds = Bdds::new();
+            let variables = (0..inputs.len() as u32)
+                .map(|variable| bdds.variable(variable))
+                .collect::<Vec<_>>();
+            let mut roots = vec![];
+
+            for name in Port::bit_names(&component.outputs) {
+                roots.push(match equations.iter().find(|equation| equation.output == name) {
+                    Some(equation) => {
+                        if let Some(variable) = equation.expr.variables().into_iter().find(|variable| !index.contains_key(variable)) {
+                            return Err(CircuitError::PortMismatch { component: component.id, port: variable }.into());
+                        }
+
+                        bdds.expression(&equation.expr, &|name| variables[index[name]])
+                    },
+                    // BLIF has no unknown value, so bits without an equation are 0
+                    None => {
+                        writeln!(out, "# {} is X", outputs[roots.len()]).unwrap();
+                        Bdds::FALSE
+                    },
+                });
+            }
+
+            diagram(out, &bdds.export(&roots), &inputs, &outputs, &mut internal);
+        },
+        ComponentDriver::Bdd { table } => {
+            if !table.is_valid(inputs.len()) {
+                return Err(CircuitError::InvalidBdd(component.id).into());
+            }
+
+            diagram(out, table, &inputs, &outputs, &mut internal);
+        },
+        ComponentDriver::FlipFlop { kind } => {
+            let clock = &module.inputs[module.inputs.len() - 1];
+            let clock = bit(&clock.name, clock.width, 0);
+            let data = |port: usize, index: u8| bit(&module.inputs[port].name, module.inputs[port].width, index);
+            let state = &module.outputs[0];
+
+            for index in 0..state.width {
+                let q = bit(&state.name, state.width, index);
+
+                let next = match kind {
+                    FlipFlopKind::D => data(0, index),
+                    FlipFlopKind::JK => {
+                        let next = internal(&bit("next", state.width, index));
+                        names(out, &[data(0, index), data(1, index), q.clone()], &next, &["1-0", "-01"]);
+                        next
+                    },
+                    FlipFlopKind::T => {
+                        let next = internal(&bit("next", state.width, index));
+                        names(out, &[data(0, index), q.clone()], &next, &["10", "01"]);
+                        next
+                    },
+                };
+
+                // An initial value of 3 leaves the state unknown, as it is in simulation
+                writeln!(out, ".latch {} {} re {} 3", next, q, clock).unwrap();
+                inverse(out, module, index);
+            }
+        },
+        ComponentDriver::Latch { kind } => {
+            let data = |port: usize, index: u8| bit(&module.inputs[port].name, module.inputs[port].width, index);
+            let state = &module.outputs[0];
+
+            for index in 0..state.width {
+                let q = bit(&state.name, state.width, index);
+
+                match kind {
+                    LatchKind::D => writeln!(out, ".latch {} {} ah {} 3", data(0, index), q, data(1, 0)).unwrap(),
+                    // An asynchronous latch holding the state fed back through the set and reset
+                    LatchKind::SR => {
+                        let next = internal(&bit("next", state.width, index));
+                        names(out, &[data(0, index), data(1, index), q.clone()], &next, &["1--", "-01"]);
+                        writeln!(out, ".latch {} {} as NIL 3", next, q).unwrap();
+                    },
+                }
+
+                inverse(out, module, index);
+            }
+        },
+        _ => return Err(ExportError::Unsupported(component.id).into()),
+    }
+
+    Ok(())
+}
+
+// Writes each node of a diagram as a multiplexer, then drives each output from its root
+fn diagram(out: &mut String, table: &BddTable, inputs: &[String], outputs: &[String], internal: &mut impl FnMut(&str) -> String) {
+    let mut nodes = vec![String::new(), String::new()];
+
+    for (variable, low, high) in table.nodes.iter() {
+        let node = internal(&format!("b{}", nodes.len()));
+
+        // Constant children need no column of their own
+        let children = [(*high, '1'), (*low, '0')];
+        let columns = children.iter()
+            .filter(|(child, _)| *child > 1)
+            .collect::<Vec<_>>();
+
+        let cubes = children.iter()
+            .filter(|(child, _)| *child != 0)
+            .map(|(child, value)| std::iter::once(*value)
+                .chain(columns.iter().map(|(column, _)| match column == child {
+                    true => '1',
+                    false => '-',
+                }))
+                .collect::<String>())
+            .collect::<Vec<_>>();
+
+        let signals = std::iter::once(inputs[*variable as usize].clone())
+            .chain(columns.iter().map(|(child, _)| nodes[*child].clone()))
+            .collect::<Vec<_>>();
+
+        names(out, &signals, &node, &cubes.iter().map(String::as_str).collect::<Vec<_>>());
+        nodes.push(node);
+    }
+
+    for (output, root) in outputs.iter().zip(table.outputs.iter()) {
+        match root {
+            0 => names(out, &[], output, &[]),
+            1 => names(out, &[], output, &["1"]),
+            root => names(out, &[nodes[*root].clone()], output, &["1"]),
+        }
+    }
+}
+
+// Drives a bit of the second output, if there is one, from the inverse of the same bit of `q`
+fn inverse(out: &mut String, module: &Module, index: u8) {
+    let state = &module.outputs[0];
+
+    if let Some(inverse) = module.outputs.get(1) {
+        names(out, &[bit(&state.name, state.width, index)], &bit(&inverse.name, inverse.width, index), &["0"]);
+    }
+}
+
+struct Model {
+    name: String,
+    line: usize,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    clocks: Vec<String>,
+    items: Vec<Item>,
+}
+
+enum Item {
+    // The signals of a cover, inputs then output, and each of its rows as the values of the inputs and the output
+    Names { signals: Vec<String>, rows: Vec<(String, bool)>, line: usize },
+    Latch { input: String, output: String, kind: Option<String>, control: Option<String>, line: usize },
+    Subckt { model: String, connections: Vec<(String, String)> },
+}
+
+// Splits source text into lines of words, each with the line it starts on. Comments are dropped and continued lines joined.
+fn lines(source: &str) -> Vec<(Vec<String>, usize)> {
+    let mut lines = vec![];
+    let mut words: Vec<String> = vec![];
+    let mut start = 1;
+
+    for (index, line) in source.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim_end();
+        let (line, continued) = match line.strip_suffix('\\') {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+
+        if words.is_empty() {
+            start = index + 1;
+        }
+
+        words.extend(line.split_whitespace().map(str::to_owned));
+
+        if !continued && !words.is_empty() {
+            lines.push((std::mem::take(&mut words), start));
+        }
+    }
+
+    if !words.is_empty() {
+        lines.push((words, start));
+    }
+
+    lines
+}
+
+// Directives about timing and loads, which don't change what a circuit does
+const IGNORED: &[&str] = &[
+    ".area", ".delay", ".wire_load_slope", ".wire", ".input_arrival", ".default_input_arrival", ".output_required",
+    ".default_output_required", ".input_drive", ".default_input_drive", ".output_load", ".default_output_load", ".end",
+];
+
+fn models(source: &str) -> Result<Vec<Model>> {
+    let mut models: Vec<Model> = vec![];
+    let error = |line: usize, message: String| ImportError::Syntax { line, message };
+
+    for (words, line) in lines(source) {
+        let directive = words[0].as_str();
+
+        if directive == ".model" {
+            models.push(Model {
+                name: words.get(1).cloned().ok_or(error(line, "Expected a model name".to_owned()))?,
+                line,
+                inputs: vec![],
+                outputs: vec![],
+                clocks: vec![],
+                items: vec![],
+            });
+
+            continue;
+        }
+
+        if IGNORED.contains(&directive) {
+            continue;
+        }
+
+        // Files holding a single model may leave out `.model`
+        if models.is_empty() {
+            models.push(Model { name: "top".to_owned(), line, inputs: vec![], outputs: vec![], clocks: vec![], items: vec![] });
+        }
+
+        let model = models.last_mut().unwrap();
+        let rest = words[1..].to_vec();
+
+        match directive {
+            ".inputs" => model.inputs.extend(rest),
+            ".outputs" => model.outputs.extend(rest),
+            ".clock" => model.clocks.extend(rest),
+            ".names" if rest.is_empty() => return Err(error(line, "Expected an output for .names".to_owned()).into()),
+            ".names" => model.items.push(Item::Names { signals: rest, rows: vec![], line }),
+            ".latch" => {
+                let (kind, control, init) = match rest.len() {
+                    2 => (None, None, None),
+                    3 => (None, None, Some(&rest[2])),
+                    4 => (Some(rest[2].clone()), Some(rest[3].clone()), None),
+                    5 => (Some(rest[2].clone()), Some(rest[3].clone()), Some(&rest[4])),
+                    _ => return Err(error(line, "Expected an input, an output and optionally a type, control and initial value for .latch".to_owned()).into()),
+                };
+
+                if let Some(kind) = kind.as_ref().filter(|kind| !["fe", "re", "ah", "al", "as"].contains(&kind.as_str())) {
+                    return Err(error(line, format!("Unknown latch type '{}'", kind)).into());
+                }
+
+                // Every initial value starts out unknown, as flip-flops do in simulation
+                if init.is_some_and(|init| !["0", "1", "2", "3"].contains(&init.as_str())) {
+                    return Err(error(line, "Expected an initial value of 0, 1, 2 or 3".to_owned()).into());
+                }
+
+                model.items.push(Item::Latch { input: rest[0].clone(), output: rest[1].clone(), kind, control, line });
+            },
+            ".subckt" => {
+                let model_name = rest.first().cloned().ok_or(error(line, "Expected a model name for .subckt".to_owned()))?;
+                let connections = rest[1..].iter()
+                    .map(|connection| connection.split_once('=')
+                        .map(|(formal, actual)| (formal.to_owned(), actual.to_owned()))
+                        .ok_or(error(line, format!("Expected formal=actual, found '{}'", connection))))
+                    .collect::<std::result::Result<_, _>>()?;
+
+                model.items.push(Item::Subckt { model: model_name, connections });
+            },
+            directive if directive.starts_with('.') => return Err(error(line, format!("'{}' isn't supported", directive)).into()),
+            // Anything else is a row of the cover before it
+            _ => {
+                let Some(Item::Names { signals, rows, .. }) = model.items.last_mut() else {
+                    return Err(error(line, format!("Unexpected '{}'", directive)).into());
+                };
+
+                let (cube, value) = match words.as_slice() {
+                    [value] if signals.len() == 1 => (String::new(), value),
+                    [cube, value] if cube.len() == signals.len() - 1 => (cube.clone(), value),
+                    _ => return Err(error(line, format!("Expected a row of {} inputs and an output", signals.len() - 1)).into()),
+                };
+
+                if !cube.chars().all(|c| matches!(c, '0' | '1' | '-')) || !matches!(value.as_str(), "0" | "1") {
+                    return Err(error(line, "Rows may only hold 0, 1 and -".to_owned()).into());
+                }
+
+                if rows.first().is_some_and(|(_, first)| *first != (value == "1")) {
+                    return Err(error(line, "Every row of a cover must have the same output".to_owned()).into());
+                }
+
+                rows.push((cube, value == "1"));
+            },
+        }
+    }
+
+    Ok(models)
+}
+
+/// Reads the models in some BLIF into a project, each one a component, with the first model as the top level.
+///
+/// Signals are single bits, so every port is one bit wide, with any brackets in its name replaced so that
+/// `a[3]` becomes the port `a_3`. Covers become truth tables, or the built-in gates where they match one,
+/// and latches become D flip-flops, or D latches when they are level-sensitive. Everything is laid out in columns
+/// running from the inputs to the outputs.
+pub fn import(source: &str) -> Result<Project> {
+    let models = models(source)?;
+
+    let mut project = Project::empty();
+    project.body.clear();
+    project.connections.clear();
+    project.wires.clear();
+
+    // Models may be used before they are defined, so every one gets its component up front
+    let mut components = HashMap::new();
+
+    for model in models.iter() {
+        let id = interop::next_component(&project);
+
+        if components.insert(model.name.clone(), (id, model)).is_some() {
+            return Err(ImportError::Syntax { line: model.line, message: format!("Model '{}' is defined twice", model.name) }.into());
+        }
+
+        let ports = |signals: &[String]| signals.iter()
+            .map(|signal| Port::new(interop::port_name(signal), 1))
+            .collect();
+
+        project.components.insert(id, Component {
+            id,
+            name: model.name.clone(),
+            inputs: ports(&model.inputs.iter().chain(model.clocks.iter()).cloned().collect::<Vec<_>>()),
+            outputs: ports(&model.outputs),
+            driver: Schematic::default().into_driver(),
+            delay: 1,
+        });
+    }
+
+    let mut tables = HashMap::new();
+
+    for (index, model) in models.iter().enumerate() {
+        let schematic = Importer {
+            project: &mut project,
+            components: &components,
+            tables: &mut tables,
+            model,
+            schematic: Schematic::default(),
+            drivers: HashMap::new(),
+            reads: vec![],
+        }.build()?;
+
+        if index == 0 {
+            project.body = schematic.body.clone();
+            project.connections = schematic.connections.clone();
+            project.wires = schematic.wires.clone();
+        }
+
+        if let Some(component) = project.components.get_mut(&components[&model.name].0) {
+            component.driver = schematic.into_driver();
+        }
+    }
+
+    interop::acyclic(&project)?;
+    Ok(project)
+}
+
+// Builds the body of one model
+struct Importer<'a> {
+    project: &'a mut Project,
+    components: &'a HashMap<String, (ComponentId, &'a Model)>,
+    // Truth-table components by number of inputs and rows, shared by every cover with the same function
+    tables: &'a mut HashMap<(usize, Vec<u64>), ComponentId>,
+    model: &'a Model,
+    schematic: Schematic,
+    // The output terminal driving each signal
+    drivers: HashMap<String, Connection>,
+    // The input terminals reading each signal, connected up once every driver is known
+    reads: Vec<(String, Connection)>,
+}
+
+impl Importer<'_> {
+    fn build(mut self) -> Result<Schematic> {
+        for signal in self.model.inputs.iter().chain(self.model.clocks.iter()) {
+            let component = interop::port_component(self.project, true, 1);
+            let instance = self.schematic.place(component, Some(interop::port_name(signal)));
+            self.drive(signal, Connection::output(instance, 0))?;
+        }
+
+        for signal in self.model.outputs.iter() {
+            let component = interop::port_component(self.project, false, 1);
+            let instance = self.schematic.place(component, Some(interop::port_name(signal)));
+            self.reads.push((signal.clone(), Connection::input(instance, 0)));
+        }
+
+        for item in self.model.items.iter() {
+            match item {
+                Item::Names { signals, rows, line } => self.names(signals, rows, *line)?,
+                Item::Latch { input, output, kind, control, line } => self.latch(input, output, kind.as_deref(), control.as_deref(), *line)?,
+                Item::Subckt { model, connections } => self.subckt(model, connections)?,
+            }
+        }
+
+        // Signals nothing drives are left floating
+        for (signal, input) in std::mem::take(&mut self.reads) {
+            if let Some(output) = self.drivers.get(&signal) {
+                self.schematic.connect(*output, input);
+            }
+        }
+
+        self.schematic.lay_out();
+        Ok(self.schematic)
+    }
+
+    fn drive(&mut self, signal: &str, output: Connection) -> Result<()> {
+        match self.drivers.insert(signal.to_owned(), output) {
+            Some(_) => Err(ImportError::MultipleDrivers { module: self.model.name.clone(), signal: signal.to_owned() }.into()),
+            None => Ok(()),
+        }
+    }
+
+    fn names(&mut self, signals: &[String], rows: &[(String, bool)], line: usize) -> Result<()> {
+        let (output, inputs) = signals.split_last().unwrap();
+
+        if inputs.len() > truth::INPUT_LIMIT as usize {
+            return Err(ImportError::Syntax { line, message: format!("Covers may have at most {} inputs", truth::INPUT_LIMIT) }.into());
+        }
+
+        // The rows each cube covers, the first input in the least significant bit
+        let mut covered = HashSet::new();
+
+        for (cube, _) in rows {
+            let fixed = cube.chars().enumerate().fold(0u64, |fixed, (bit, c)| fixed | ((c != '-') as u64) << bit);
+            let value = cube.chars().enumerate().fold(0u64, |value, (bit, c)| value | ((c == '1') as u64) << bit);
+            let free = !fixed & ((1u64 << inputs.len()) - 1);
+            let mut subset = free;
+
+            loop {
+                covered.insert(value | subset);
+
+                if subset == 0 {
+                    break;
+                }
+
+                subset = (subset - 1) & free;
+            }
+        }
+
+        // A cover of the rows where the output is 0 gives the complement, and a cover with no rows is constant 0
+        let on = rows.first().is_none_or(|(_, value)| *value);
+        let mut truth = (0..1u64 << inputs.len())
+            .filter(|row| covered.contains(row) == on)
+            .collect::<Vec<_>>();
+        truth.sort_unstable();
+
+        let component = self.table(inputs.len(), truth);
+        let instance = self.schematic.place(component, None);
+
+        for (terminal, input) in inputs.iter().enumerate() {
+            self.reads.push((input.clone(), Connection::input(instance, terminal as u64)));
+        }
+
+        self.drive(output, Connection::output(instance, 0))
+    }
+
+    // The component computing a function of `inputs` bits that is 1 on exactly the rows in `truth`
+    fn table(&mut self, inputs: usize, truth: Vec<u64>) -> ComponentId {
+        match (inputs, truth.as_slice()) {
+            (1, [0]) => return Project::NOT,
+            (2, [3]) => return Project::AND,
+            (2, [1, 2, 3]) => return Project::OR,
+            _ => {},
+        }
+
+        if let Some(id) = self.tables.get(&(inputs, truth.clone())) {
+            return *id;
+        }
+
+        let names = (0..inputs)
+            .map(interop::input_name)
+            .collect::<Vec<_>>();
+        let rows = truth.iter().map(|row| (*row, 1)).collect::<HashMap<_, _>>();
+
+        // Named after the function it computes
+        let name = match inputs as u32 <= minimise::EXACT_LIMIT {
+            true => minimise::minimise(&rows, &HashSet::new(), inputs as u32, 0, Form::SumOfProducts, names.clone()).to_string(),
+            false => format!("table{}", self.tables.len() + 1),
+        };
+
+        let id = interop::next_component(self.project);
+        self.project.components.insert(id, Component {
+            id,
+            name,
+            inputs: names.iter().map(|name| Port::new(name.as_str(), 1)).collect(),
+            outputs: vec![Port::new("y", 1)],
+            driver: ComponentDriver::truth(rows),
+            delay: 1,
+        });
+
+        self.tables.insert((inputs, truth), id);
+        id
+    }
+
+    fn latch(&mut self, input: &str, output: &str, kind: Option<&str>, control: Option<&str>, line: usize) -> Result<()> {
+        // Latches without a control of their own are clocked by the model's clock
+        let control = match control {
+            Some("NIL") | None => self.model.clocks.first().map(String::as_str),
+            control => control,
+        };
+
+        let (component, inverted) = match kind {
+            Some("ah") => (Project::D_LATCH, false),
+            Some("al") => (Project::D_LATCH, true),
+            Some("fe") => (Project::D_FLIP_FLOP, true),
+            // An asynchronous latch passes its input straight through, which lets feedback loops hold their state
+            Some("as") => (self.table(1, vec![1]), false),
+            _ => (Project::D_FLIP_FLOP, false),
+        };
+
+        let instance = self.schematic.place(component, None);
+        self.reads.push((input.to_owned(), Connection::input(instance, 0)));
+        self.drive(output, Connection::output(instance, 0))?;
+
+        if kind == Some("as") {
+            return Ok(());
+        }
+
+        let control = control
+            .ok_or(ImportError::Syntax { line, message: "Latches need a control, or a clock declared with .clock".to_owned() })?
+            .to_owned();
+
+        match inverted {
+            true => {
+                let not = self.schematic.place(Project::NOT, None);
+                self.reads.push((control, Connection::input(not, 0)));
+                self.schematic.connect(Connection::output(not, 0), Connection::input(instance, 1));
+            },
+            false => self.reads.push((control, Connection::input(instance, 1))),
+        }
+
+        Ok(())
+    }
+
+    fn subckt(&mut self, model: &str, connections: &[(String, String)]) -> Result<()> {
+        let (component, definition) = *self.components.get(model)
+            .ok_or(ImportError::UnknownModule(model.to_owned()))?;
+        let instance = self.schematic.place(component, None);
+
+        let inputs = definition.inputs.iter()
+            .chain(definition.clocks.iter())
+            .collect::<Vec<_>>();
+
+        for (formal, actual) in connections {
+            if let Some(terminal) = inputs.iter().position(|input| *input == formal) {
+                self.reads.push((actual.clone(), Connection::input(instance, terminal as u64)));
+            } else if let Some(terminal) = definition.outputs.iter().position(|output| output == formal) {
+                self.drive(actual, Connection::output(instance, terminal as u64))?;
+            } else {
+                return Err(ImportError::UnknownSignal { module: model.to_owned(), signal: formal.clone() }.into());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::Circuit;
+
+    // Sets the inputs of a circuit by label, then reads the output with the given label
+    fn step(circuit: &mut Circuit, inputs: &[(&str, u64)], output: &str) -> Option<u64> {
+        for (label, value) in inputs {
+            circuit.set_input(circuit.find(label).unwrap(), *value).unwrap();
+        }
+
+        circuit.evaluate().unwrap();
+        circuit.output(circuit.find(output).unwrap()).unwrap().to_u64()
+    }
+
+    fn import_error(source: &str) -> String {
+        import(source).err().unwrap().to_string()
+    }
+
+    #[test]
+    fn covers() {
+        let project = import("# a multiplexer, with a continued line
+            .model mux
+            .inputs a b \\
+                s
+            .outputs y nand one zero
+            .names a b s y
+            1-0 1
+            -11 1
+            .names a b nand
+            11 0
+            .names one
+            1
+            .names zero
+            .end").unwrap();
+        let mut circuit = Circuit::compile(&project).unwrap();
+
+        for row in 0..8 {
+            let (a, b, s) = (row & 1, row >> 1 & 1, row >> 2);
+            let inputs = [("a", a), ("b", b), ("s", s)];
+
+            assert_eq!(step(&mut circuit, &inputs, "y"), Some(if s == 1 { b } else { a }));
+            assert_eq!(step(&mut circuit, &inputs, "nand"), Some(1 - (a & b)));
+            assert_eq!(step(&mut circuit, &inputs, "one"), Some(1));
+            assert_eq!(step(&mut circuit, &inputs, "zero"), Some(0));
+        }
+    }
+
+    #[test]
+    fn gates() {
+        let project = import(".model m\n.inputs a b\n.outputs x y z\n.names a b x\n11 1\n.names a b y\n1- 1\n-1 1\n.names a z\n0 1\n.end").unwrap();
+
+        let mut components = project.body.values()
+            .map(|placement| placement.component)
+            .filter(|component| ![Project::INPUT, Project::OUTPUT].contains(component))
+            .collect::<Vec<_>>();
+        components.sort_unstable();
+
+        assert_eq!(components, [Project::NOT, Project::AND, Project::OR]);
+    }
+
+    #[test]
+    fn latches() {
+        let project = import(".model m\n.inputs d\n.clock clk\n.outputs q l\n.latch d q re clk 3\n.latch d l ah clk 0\n.end").unwrap();
+        let mut circuit = Circuit::compile(&project).unwrap();
+
+        // The flip-flop only takes `d` on a rising edge, while the latch follows it while the clock is high
+        step(&mut circuit, &[("d", 1), ("clk", 0)], "q");
+        assert_eq!(step(&mut circuit, &[("clk", 1)], "q"), Some(1));
+        assert_eq!(step(&mut circuit, &[("d", 0)], "q"), Some(1));
+        assert_eq!(step(&mut circuit, &[], "l"), Some(0));
+        assert_eq!(step(&mut circuit, &[("clk", 0), ("d", 1)], "l"), Some(0));
+        assert_eq!(step(&mut circuit, &[("clk", 1)], "q"), Some(1));
+    }
+
+    #[test]
+    fn subcircuits() {
+        // Models may be used before they are defined
+        let project = import(".model top
+            .inputs a b c
+            .outputs y
+            .subckt and2 x=a y=b z=ab
+            .subckt and2 z=y x=ab y=c
+            .end
+
+            .model and2
+            .inputs x y
+            .outputs z
+            .names x y z
+            11 1
+            .end").unwrap();
+        let mut circuit = Circuit::compile(&project).unwrap();
+
+        for row in 0..8 {
+            assert_eq!(step(&mut circuit, &[("a", row & 1), ("b", row >> 1 & 1), ("c", row >> 2)], "y"), Some((row == 7) as u64));
+        }
+    }
+
+    #[test]
+    fn errors() {
+        assert!(import_error(".model m\n.inputs d\n.outputs q\n.latch d q xx c\n.end").contains("Unknown latch type 'xx'"));
+        assert!(import_error(".model m\n.inputs a\n.outputs y\n.names a y\n1 1\n0 0\n.end").contains("Every row of a cover must have the same output"));
+        assert!(import_error(".model m\n.gate and2 a=x\n.end").contains("'.gate' isn't supported"));
+        assert!(import_error(".model m\n.end\n.model m\n.end").contains("Model 'm' is defined twice"));
+        assert!(import_error(".model m\n.inputs a\n.outputs y\n.names a y\n1 1\n.names a y\n0 1\n.end").contains("MultipleDrivers"));
+        assert!(import_error(".model m\n.subckt missing a=b\n.end").contains("UnknownModule"));
+        assert!(import_error(".model m\n.inputs a\n.outputs y\n.subckt m a=a y=y\n.end").contains("Recursive([\"m\"])"));
+        assert!(import_error(".model top\n.subckt a\n.end\n.model a\n.subckt b\n.end\n.model b\n.subckt a\n.end").contains("Recursive([\"a\", \"b\"])"));
+    }
+
+    #[test]
+    fn round_trip() {
+        // The default project's gate, fed from its input on both sides
+        let mut project = Project::empty();
+        project.connect(Connection::output(0, 0), Connection::input(2, 0)).unwrap();
+        project.connect(Connection::output(0, 0), Connection::input(2, 1)).unwrap();
+        project.connect(Connection::output(2, 0), Connection::input(1, 0)).unwrap();
+
+        let project = import(&export(&project, "top").unwrap()).unwrap();
+        let mut circuit = Circuit::compile(&project).unwrap();
+
+        for value in 0..2 {
+            assert_eq!(step(&mut circuit, &[("Input", value)], "Output"), Some(value));
+        }
+    }
+}
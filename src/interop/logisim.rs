@@ -0,0 +1,1253 @@
+use crate::{
+    expression::Equation,
+    expression::Expr,
+    interop,
+    interop::Bit,
+    interop::Bits,
+    interop::ImportError,
+    interop::Schematic,
+    project::Component,
+    project::ComponentDriver,
+    project::ComponentId,
+    project::Connection,
+    project::Coord,
+    project::Port,
+    project::Project,
+    Result
+};
+use serde::Serialize;
+use std::collections::{
+    HashMap,
+    HashSet,
+    VecDeque
+};
+
+// Logisim draws on a grid of 10 pixels, each one grid unit in LogicX
+const GRID: f64 = 10.0;
+
+type Point = (i64, i64);
+
+/// An element of a circuit with no equivalent in LogicX, such as a component from a library other than the gates and wiring,
+/// along with where it sits in Logisim's coordinates.
+#[derive(Debug, Clone, Serialize)]
+pub struct Untranslated {
+    pub circuit: String,
+    pub element: String,
+    pub location: (i64, i64),
+}
+
+struct Circuit {
+    name: String,
+    attributes: HashMap<String, String>,
+    elements: Vec<Element>,
+    wires: Vec<(Point, Point)>,
+    // The ports of a custom appearance, as the offset from the anchor of each pin's port by where the pin is, and the way the anchor faces
+    appearance: Option<(HashMap<Point, Point>, Facing)>,
+}
+
+struct Element {
+    // The description of the library the element comes from, such as `#Gates`, or none for circuits in the same file
+    library: Option<String>,
+    name: String,
+    loc: Point,
+    attributes: HashMap<String, String>,
+}
+
+impl Element {
+    fn standard(&self, library: &str) -> bool {
+        self.library.as_deref() == Some(library)
+    }
+
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).map(String::as_str)
+    }
+
+    fn number(&self, name: &str, default: u64) -> u64 {
+        self.attribute(name).and_then(number).unwrap_or(default)
+    }
+
+    fn facing(&self) -> Facing {
+        Facing::parse(self.attribute("facing"))
+    }
+
+    fn label(&self) -> Option<String> {
+        self.attribute("label")
+            .filter(|label| !label.is_empty())
+            .map(str::to_owned)
+    }
+
+    fn is_pin(&self) -> bool {
+        self.standard("#Wiring") && self.name == "Pin"
+    }
+
+    // Logisim marks output pins with `output`, Logisim-evolution with `type`
+    fn is_output(&self) -> bool {
+        self.attribute("output") == Some("true") || self.attribute("type") == Some("output")
+    }
+
+    // The name of the port a pin stands for, and the label of its placement
+    fn pin_name(&self) -> String {
+        self.label()
+            .map(|label| interop::port_name(&label))
+            .unwrap_or_else(|| format!("pin{}_{}", self.loc.0, self.loc.1))
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Facing {
+    East,
+    North,
+    West,
+    South,
+}
+
+impl Facing {
+    fn parse(text: Option<&str>) -> Self {
+        match text {
+            Some("north") => Self::North,
+            Some("west") => Self::West,
+            Some("south") => Self::South,
+            _ => Self::East,
+        }
+    }
+
+    fn reverse(self) -> Self {
+        match self {
+            Self::East => Self::West,
+            Self::North => Self::South,
+            Self::West => Self::East,
+            Self::South => Self::North,
+        }
+    }
+
+    // Turns an offset drawn facing `from` to face this way instead
+    fn rotate(self, from: Self, (dx, dy): Point) -> Point {
+        match (self as i64 - from as i64).rem_euclid(4) {
+            0 => (dx, dy),
+            1 => (dy, -dx),
+            2 => (-dx, -dy),
+            _ => (-dy, dx),
+        }
+    }
+}
+
+// Numbers are written in decimal, or in hexadecimal with a leading `0x`
+fn number(text: &str) -> Option<u64> {
+    match text.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+// Points are written `(x,y)`, or without the parentheses in appearances
+fn point(text: &str) -> Option<Point> {
+    let (x, y) = text.trim_matches(|c| c == '(' || c == ')').split_once(',')?;
+    Some((x.trim().parse::<f64>().ok()?.round() as i64, y.trim().parse::<f64>().ok()?.round() as i64))
+}
+
+fn attributes(node: roxmltree::Node) -> HashMap<String, String> {
+    node.children()
+        .filter(|child| child.has_tag_name("a"))
+        .filter_map(|child| Some((child.attribute("name")?.to_owned(), child.attribute("val").or(child.text()).unwrap_or_default().to_owned())))
+        .collect()
+}
+
+// The offset of each port of a custom appearance from the anchor, from the middle of the shapes marking them
+fn appearance(node: roxmltree::Node) -> Option<(HashMap<Point, Point>, Facing)> {
+    let centre = |node: roxmltree::Node| {
+        let get = |name: &str| node.attribute(name).and_then(|value| value.parse::<f64>().ok());
+        Some(((get("x")? + get("width")? / 2.0).round() as i64, (get("y")? + get("height")? / 2.0).round() as i64))
+    };
+
+    let anchor = node.children().find(|child| child.has_tag_name("circ-anchor"))?;
+    let origin = centre(anchor)?;
+
+    let ports = node.children()
+        .filter(|child| child.has_tag_name("circ-port"))
+        .filter_map(|port| {
+            let (x, y) = centre(port)?;
+            Some((point(port.attribute("pin")?)?, (x - origin.0, y - origin.1)))
+        })
+        .collect();
+
+    Some((ports, Facing::parse(anchor.attribute("facing"))))
+}
+
+// Every circuit in the file, the name of the main one, and whether the file comes from Logisim itself rather than Logisim-evolution
+fn parse(source: &str) -> Result<(Vec<Circuit>, Option<String>, bool)> {
+    let document = roxmltree::Document::parse(source)
+        .map_err(|err| ImportError::Syntax { line: err.pos().row as usize, message: err.to_string() })?;
+    let syntax = |node: roxmltree::Node, message: &str| ImportError::Syntax {
+        line: document.text_pos_at(node.range().start).row as usize,
+        message: message.to_owned(),
+    };
+
+    let root = document.root_element();
+    let classic = root.attribute("source").is_some_and(|source| source.starts_with("2."));
+
+    let libraries = root.children()
+        .filter(|child| child.has_tag_name("lib"))
+        .filter_map(|lib| Some((lib.attribute("name")?, lib.attribute("desc")?)))
+        .collect::<HashMap<_, _>>();
+    let main = root.children()
+        .find(|child| child.has_tag_name("main"))
+        .and_then(|main| main.attribute("name"))
+        .map(str::to_owned);
+
+    let mut circuits = vec![];
+
+    for node in root.children().filter(|child| child.has_tag_name("circuit")) {
+        let mut circuit = Circuit {
+            name: node.attribute("name").unwrap_or_default().to_owned(),
+            attributes: attributes(node),
+            elements: vec![],
+            wires: vec![],
+            appearance: None,
+        };
+
+        for child in node.children() {
+            match child.tag_name().name() {
+                "comp" => circuit.elements.push(Element {
+                    library: child.attribute("lib").map(|lib| libraries.get(lib).copied().unwrap_or(lib).to_owned()),
+                    name: child.attribute("name").unwrap_or_default().to_owned(),
+                    loc: child.attribute("loc").and_then(point).ok_or_else(|| syntax(child, "component without a location"))?,
+                    attributes: attributes(child),
+                }),
+                "wire" => {
+                    let from = child.attribute("from").and_then(point).ok_or_else(|| syntax(child, "wire without a start"))?;
+                    let to = child.attribute("to").and_then(point).ok_or_else(|| syntax(child, "wire without an end"))?;
+                    circuit.wires.push((from, to));
+                },
+                "appear" => circuit.appearance = appearance(child),
+                _ => {},
+            }
+        }
+
+        circuits.push(circuit);
+    }
+
+    Ok((circuits, main, classic))
+}
+
+/// Reads a Logisim or Logisim-evolution `.circ` file into a project, each circuit a component, with the main circuit as the top level.
+/// Returns the elements left out along with it.
+///
+/// Gates, pins, clocks, constants, tunnels, splitters and circuits placed in other circuits are carried over, each placement
+/// where the element was, a Logisim grid step to a grid unit, and each wire along the route it took.
+/// Gates only keep the inputs something is attached to. Text is dropped, and everything else ends up in the list of elements left out.
+pub fn import(source: &str) -> Result<(Project, Vec<Untranslated>)> {
+    let (circuits, main, classic) = parse(source)?;
+
+    let mut project = Project::empty();
+    project.body.clear();
+    project.connections.clear();
+    project.wires.clear();
+
+    // Circuits may be placed before they are defined, so every one gets its component up front
+    let mut interfaces = HashMap::new();
+
+    for circuit in circuits.iter() {
+        let pins = |output: bool| {
+            let mut pins = circuit.elements.iter()
+                .filter(|element| element.is_pin() && element.is_output() == output)
+                .collect::<Vec<_>>();
+            pins.sort_by_key(|pin| (pin.loc.1, pin.loc.0));
+            pins
+        };
+        let (inputs, outputs) = (pins(false), pins(true));
+        let ports = |pins: &[&Element]| pins.iter()
+            .map(|pin| match pin.number("width", 1) {
+                width @ 1..=64 => Ok(Port::new(pin.pin_name(), width as u8)),
+                _ => Err(ImportError::WidthMismatch { module: circuit.name.clone(), signal: pin.pin_name() }.into()),
+            })
+            .collect::<Result<Vec<_>>>();
+
+        let id = interop::next_component(&project);
+        project.components.insert(id, Component {
+            id,
+            name: circuit.name.clone(),
+            inputs: ports(&inputs)?,
+            outputs: ports(&outputs)?,
+            driver: Schematic::default().into_driver(),
+            delay: 1,
+        });
+
+        let look = circuit.attributes.get("appearance").map(String::as_str);
+        let appearance = match (&circuit.appearance, look) {
+            (Some((ports, facing)), Some("custom") | None) => Appearance::Fixed(ports.clone(), *facing),
+            (_, Some("classic")) => classic_appearance(inputs.iter().chain(outputs.iter()).copied()),
+            (_, None) if classic => classic_appearance(inputs.iter().chain(outputs.iter()).copied()),
+            _ => Appearance::Evolution,
+        };
+
+        interfaces.insert(circuit.name.clone(), Interface {
+            component: id,
+            inputs: inputs.iter().map(|pin| pin.loc).collect(),
+            outputs: outputs.iter().map(|pin| pin.loc).collect(),
+            appearance,
+        });
+    }
+
+    let main = main
+        .filter(|main| interfaces.contains_key(main))
+        .or(circuits.first().map(|circuit| circuit.name.clone()));
+
+    let mut gates = HashMap::new();
+    let mut untranslated = vec![];
+
+    for circuit in circuits.iter() {
+        let schematic = Importer {
+            project: &mut project,
+            interfaces: &interfaces,
+            gates: &mut gates,
+            untranslated: &mut untranslated,
+            circuit,
+            classic,
+            connected: circuit.wires.iter()
+                .flat_map(|(from, to)| [*from, *to])
+                .chain(circuit.elements.iter().map(|element| element.loc))
+                .collect(),
+            schematic: Schematic::default(),
+            bits: Bits::default(),
+        }.build()?;
+
+        if main.as_ref() == Some(&circuit.name) {
+            project.body = schematic.body.clone();
+            project.connections = schematic.connections.clone();
+            project.wires = schematic.wires.clone();
+        }
+
+        if let Some(component) = project.components.get_mut(&interfaces[&circuit.name].component) {
+            component.driver = schematic.into_driver();
+        }
+    }
+
+    Ok((project, untranslated))
+}
+
+// How a circuit looks from the outside when placed in another
+struct Interface {
+    component: ComponentId,
+    // Where the pin behind each port is inside the circuit, in port order
+    inputs: Vec<Point>,
+    outputs: Vec<Point>,
+    appearance: Appearance,
+}
+
+enum Appearance {
+    // The offset of each pin's port from the anchor by where the pin is, drawn facing some way
+    Fixed(HashMap<Point, Point>, Facing),
+    // Logisim-evolution's own look, with outputs 20 apart down the anchor's side and inputs down the far side, however far apart
+    // the length of the pins' labels puts the two
+    Evolution,
+}
+
+// Logisim's original look, a box with each pin on the side opposite the way it faces, and the ports on each side spread about its middle
+fn classic_appearance<'a>(pins: impl Iterator<Item = &'a Element>) -> Appearance {
+    // The pins on each side, by the way the side faces
+    let mut sides: [Vec<Point>; 4] = Default::default();
+
+    for pin in pins {
+        sides[pin.facing().reverse() as usize].push(pin.loc);
+    }
+
+    for (side, pins) in sides.iter_mut().enumerate() {
+        match side % 2 {
+            0 => pins.sort_by_key(|(x, y)| (*y, *x)),
+            _ => pins.sort(),
+        }
+    }
+
+    let [east, north, west, south] = sides.each_ref().map(|pins| pins.len() as i64);
+    let (vertical, horizontal) = (north.max(south), east.max(west));
+
+    let offset = |count: i64, opposite: i64, others: i64| {
+        let most = count.max(opposite);
+        let start = match most {
+            0 | 1 if others == 0 => 15,
+            0..=2 => 10,
+            _ if others == 0 => 5,
+            _ => 10,
+        };
+
+        start + 10 * ((most - count) / 2)
+    };
+    let dimension = |most: i64, others: i64| match most {
+        0..=2 => 30,
+        _ if others == 0 => 10 * most,
+        _ => 10 * most + 10,
+    };
+
+    let (width, height) = (dimension(vertical, horizontal), dimension(horizontal, vertical));
+    let starts = [
+        (width, offset(east, west, vertical)),
+        (offset(north, south, horizontal), 0),
+        (0, offset(west, east, vertical)),
+        (offset(south, north, horizontal), height),
+    ];
+
+    // The anchor is the first port on the first side with any, going round from the east
+    let anchor = [east, north, west, south].iter()
+        .position(|count| *count > 0)
+        .map(|side| starts[side])
+        .unwrap_or_default();
+
+    let ports = sides.iter()
+        .zip(starts.iter())
+        .enumerate()
+        .flat_map(|(side, (pins, (x, y)))| pins.iter()
+            .enumerate()
+            .map(move |(index, pin)| {
+                let index = index as i64;
+                let (x, y) = match side % 2 {
+                    0 => (*x, y + 10 * index),
+                    _ => (x + 10 * index, *y),
+                };
+
+                (*pin, (x - anchor.0, y - anchor.1))
+            }))
+        .collect();
+
+    Appearance::Fixed(ports, Facing::East)
+}
+
+#[derive(Copy, Clone)]
+struct Attachment {
+    point: Point,
+    width: usize,
+}
+
+// What an element becomes, before the nets joining them are worked out
+enum Part {
+    // A placement, with the terminals something may be attached to
+    Placed {
+        component: ComponentId,
+        label: Option<String>,
+        inputs: Vec<(u64, Attachment)>,
+        outputs: Vec<(u64, Attachment)>,
+    },
+    Tunnel {
+        label: String,
+        attachment: Attachment,
+    },
+    // A splitter, with the bits of the combined end each other end carries
+    Splitter {
+        combined: Attachment,
+        ends: Vec<(Attachment, Vec<usize>)>,
+    },
+}
+
+// Sets of nets or bits joined together
+#[derive(Default)]
+struct Sets(Vec<usize>);
+
+impl Sets {
+    fn add(&mut self) -> usize {
+        self.0.push(self.0.len());
+        self.0.len() - 1
+    }
+
+    fn find(&mut self, mut item: usize) -> usize {
+        while self.0[item] != item {
+            self.0[item] = self.0[self.0[item]];
+            item = self.0[item];
+        }
+
+        item
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        self.0[b] = a;
+    }
+}
+
+// The points along the wires from one point to another, where they turn, if wires join the two at all
+fn route(adjacent: &HashMap<Point, Vec<Point>>, from: Point, to: Point) -> Vec<Point> {
+    let mut previous = HashMap::from([(from, from)]);
+    let mut queue = VecDeque::from([from]);
+
+    while let Some(point) = queue.pop_front() {
+        if point == to {
+            break;
+        }
+
+        for next in adjacent.get(&point).into_iter().flatten() {
+            if !previous.contains_key(next) {
+                previous.insert(*next, point);
+                queue.push_back(*next);
+            }
+        }
+    }
+
+    if !previous.contains_key(&to) {
+        return vec![];
+    }
+
+    let mut path = vec![to];
+    while path[path.len() - 1] != from {
+        path.push(previous[&path[path.len() - 1]]);
+    }
+    path.reverse();
+
+    path.windows(3)
+        .filter(|points| {
+            let ((x0, y0), (x1, y1), (x2, y2)) = (points[0], points[1], points[2]);
+            (x1 - x0) * (y2 - y1) != (y1 - y0) * (x2 - x1)
+        })
+        .map(|points| points[1])
+        .collect()
+}
+
+// Builds the body of one circuit
+struct Importer<'a> {
+    project: &'a mut Project,
+    interfaces: &'a HashMap<String, Interface>,
+    // Components standing in for gates and constants, by kind, number of inputs, width and inverted inputs or value,
+    // shared by every element they fit
+    gates: &'a mut HashMap<(String, usize, usize, u64), ComponentId>,
+    untranslated: &'a mut Vec<Untranslated>,
+    circuit: &'a Circuit,
+    classic: bool,
+    // Points with a wire end or an element at them
+    connected: HashSet<Point>,
+    schematic: Schematic,
+    bits: Bits,
+}
+
+impl Importer<'_> {
+    fn build(mut self) -> Result<Schematic> {
+        let mut parts = vec![];
+
+        for element in self.circuit.elements.iter() {
+            if element.standard("#Base") && element.name == "Text" {
+                continue;
+            }
+
+            match self.part(element) {
+                Some(part) => parts.push(part),
+                None => self.untranslated.push(Untranslated {
+                    circuit: self.circuit.name.clone(),
+                    element: element.name.clone(),
+                    location: element.loc,
+                }),
+            }
+        }
+
+        // Nets join wherever wire ends meet, and across tunnels with the same label
+        let mut nets = Sets::default();
+        let mut points = HashMap::new();
+        let mut net = |point: Point, nets: &mut Sets| {
+            let id = *points.entry(point).or_insert_with(|| nets.add());
+            nets.find(id)
+        };
+
+        for (from, to) in self.circuit.wires.iter() {
+            let (from, to) = (net(*from, &mut nets), net(*to, &mut nets));
+            nets.union(from, to);
+        }
+
+        let mut attachments = vec![];
+        let mut tunnels = HashMap::new();
+
+        for part in parts.iter() {
+            match part {
+                Part::Placed { inputs, outputs, .. } => attachments.extend(inputs.iter().chain(outputs.iter()).map(|(_, attachment)| *attachment)),
+                Part::Tunnel { label, attachment } => {
+                    attachments.push(*attachment);
+
+                    let id = net(attachment.point, &mut nets);
+                    let first = *tunnels.entry(label.clone()).or_insert(id);
+                    nets.union(first, id);
+                },
+                Part::Splitter { combined, ends } => attachments.extend(std::iter::once(*combined).chain(ends.iter().map(|(end, _)| *end))),
+            }
+        }
+
+        let mut widths = HashMap::new();
+
+        for attachment in attachments.iter() {
+            let width = widths.entry(net(attachment.point, &mut nets)).or_default();
+            *width = attachment.width.max(*width);
+        }
+
+        let labels = tunnels.into_iter()
+            .map(|(label, id)| (nets.find(id), label))
+            .collect::<HashMap<_, _>>();
+
+        // Each net gets a bit of its own for each bit it carries, joined across splitters
+        let mut roots = widths.keys().copied().collect::<Vec<_>>();
+        roots.sort_unstable();
+
+        let mut bits = Sets::default();
+        let mut first = HashMap::new();
+
+        for root in roots {
+            first.insert(root, bits.0.len());
+
+            for _ in 0..widths[&root] {
+                bits.add();
+            }
+        }
+
+        for part in parts.iter() {
+            let Part::Splitter { combined, ends } = part else {
+                continue;
+            };
+
+            let combined = net(combined.point, &mut nets);
+
+            for (end, carried) in ends.iter() {
+                let end = net(end.point, &mut nets);
+
+                for (index, bit) in carried.iter().enumerate() {
+                    if *bit < widths[&combined] && index < widths[&end] {
+                        bits.union(first[&combined] + bit, first[&end] + index);
+                    }
+                }
+            }
+        }
+
+        // The bits a terminal is attached to, and the net they belong to
+        let mut attached = |attachment: &Attachment| {
+            let root = net(attachment.point, &mut nets);
+            let bits = (0..attachment.width)
+                .map(|index| match index < widths[&root] {
+                    true => Bit::Net(bits.find(first[&root] + index) as u64),
+                    false => Bit::Unknown,
+                })
+                .collect::<Vec<_>>();
+
+            (bits, root)
+        };
+
+        // Where each terminal is, for routing the wires afterwards
+        let mut terminals = HashMap::new();
+
+        for part in parts {
+            let Part::Placed { component, label, inputs, outputs } = part else {
+                continue;
+            };
+
+            let instance = self.schematic.place(component, label);
+
+            let corner = inputs.iter()
+                .chain(outputs.iter())
+                .map(|(_, attachment)| attachment.point)
+                .reduce(|(x0, y0), (x1, y1)| (x0.min(x1), y0.min(y1)));
+            if let (Some((x, y)), Some(placement)) = (corner, self.schematic.body.get_mut(&instance)) {
+                placement.pos = Coord(x as f64 / GRID, y as f64 / GRID - 0.5);
+            }
+
+            for (terminal, attachment) in outputs {
+                let output = Connection::output(instance, terminal);
+                let (bits, root) = attached(&attachment);
+                terminals.insert(output, attachment.point);
+
+                self.bits.drive(&bits, output).map_err(|_| ImportError::MultipleDrivers {
+                    module: self.circuit.name.clone(),
+                    signal: labels.get(&root).cloned().unwrap_or(format!("{:?}", attachment.point)),
+                })?;
+            }
+
+            for (terminal, attachment) in inputs {
+                let input = Connection::input(instance, terminal);
+                let (bits, root) = attached(&attachment);
+                terminals.insert(input, attachment.point);
+
+                let name = format!("{}.{}", self.circuit.name, labels.get(&root).map(String::as_str).unwrap_or("bits"));
+                self.bits.read(bits, input, name);
+            }
+        }
+
+        let placed = self.schematic.body.len();
+        self.bits.connect(self.project, &mut self.schematic);
+
+        // Components picking out bits sit just before the terminal they feed
+        for instance in placed..self.schematic.body.len() {
+            let point = self.schematic.connections.get(&Connection::output(instance, 0))
+                .and_then(|inputs| inputs.first())
+                .and_then(|input| terminals.get(input));
+
+            if let (Some((x, y)), Some(placement)) = (point, self.schematic.body.get_mut(&instance)) {
+                placement.pos = Coord(*x as f64 / GRID - 2.0, *y as f64 / GRID - 0.5);
+            }
+        }
+
+        let mut adjacent = HashMap::<Point, Vec<Point>>::new();
+
+        for (from, to) in self.circuit.wires.iter() {
+            adjacent.entry(*from).or_default().push(*to);
+            adjacent.entry(*to).or_default().push(*from);
+        }
+
+        for wire in self.schematic.wires.iter_mut() {
+            let from = terminals.get(&Connection { instance: wire.from, terminal: wire.from_terminal });
+            let to = terminals.get(&Connection { instance: wire.to, terminal: wire.to_terminal });
+
+            if let (Some(from), Some(to)) = (from, to) {
+                wire.points = route(&adjacent, *from, *to)
+                    .into_iter()
+                    .map(|(x, y)| Coord(x as f64 / GRID, y as f64 / GRID))
+                    .collect();
+            }
+        }
+
+        Ok(self.schematic)
+    }
+
+    fn part(&mut self, element: &Element) -> Option<Part> {
+        let (x, y) = element.loc;
+        let width = element.number("width", 1) as usize;
+        let at = |point: Point, width: usize| Attachment { point, width };
+
+        if element.library.is_none() {
+            let interface = self.interfaces.get(&element.name)?;
+            return Some(self.subcircuit(element, interface));
+        }
+
+        if element.standard("#Gates") {
+            return self.gate(element);
+        }
+
+        // Buses are at most 64 bits wide
+        if !element.standard("#Wiring") || !(1..=64).contains(&width) {
+            return None;
+        }
+
+        let part = match element.name.as_str() {
+            "Pin" => match element.is_output() {
+                true => Part::Placed {
+                    component: interop::port_component(self.project, false, width as u8),
+                    label: Some(element.pin_name()),
+                    inputs: vec![(0, at(element.loc, width))],
+                    outputs: vec![],
+                },
+                false => Part::Placed {
+                    component: interop::port_component(self.project, true, width as u8),
+                    label: Some(element.pin_name()),
+                    inputs: vec![],
+                    outputs: vec![(0, at(element.loc, width))],
+                },
+            },
+            "Clock" => Part::Placed {
+                component: Project::CLOCK,
+                label: element.label(),
+                inputs: vec![],
+                outputs: vec![(0, at(element.loc, 1))],
+            },
+            "Constant" | "Power" | "Ground" => {
+                let value = match element.name.as_str() {
+                    "Constant" => element.number("value", 1),
+                    "Power" => u64::MAX,
+                    _ => 0,
+                };
+
+                Part::Placed {
+                    component: self.constant(width, value),
+                    label: element.label(),
+                    inputs: vec![],
+                    outputs: vec![(0, at(element.loc, width))],
+                }
+            },
+            "Tunnel" => Part::Tunnel {
+                label: element.label().unwrap_or_default(),
+                attachment: at(element.loc, width),
+            },
+            "Splitter" => {
+                let fanout = element.number("fanout", 2) as i64;
+                let incoming = element.number("incoming", 2) as usize;
+
+                if !(1..=64).contains(&fanout) || !(1..=64).contains(&incoming) {
+                    return None;
+                }
+
+                // Without their own attributes, bits are shared out in order, the first ends taking one more when they don't divide evenly
+                let shared = (0..fanout as usize)
+                    .flat_map(|end| std::iter::repeat_n(end, incoming / fanout as usize + (end < incoming % fanout as usize) as usize))
+                    .collect::<Vec<_>>();
+                let ends = (0..incoming)
+                    .map(|bit| match element.attribute(&format!("bit{}", bit)) {
+                        Some(end) => end.parse().ok(),
+                        None if fanout as usize >= incoming => Some(bit),
+                        None => shared.get(bit).copied(),
+                    })
+                    .collect::<Vec<Option<usize>>>();
+
+                let justify = match element.attribute("appear") {
+                    Some("right") => 1,
+                    Some("center") | Some("legacy") => 0,
+                    _ => -1,
+                };
+                let step = 10 * element.number("spacing", 1) as i64;
+
+                let ((dx, dy), (ddx, ddy)) = match element.facing() {
+                    facing @ (Facing::North | Facing::South) => {
+                        let m = if facing == Facing::North { 1 } else { -1 };
+                        let dx = match justify {
+                            0 => step * ((fanout + 1) / 2 - 1),
+                            _ if m * justify < 0 => -10,
+                            _ => step * fanout,
+                        };
+
+                        ((dx, -m * 20), (-step, 0))
+                    },
+                    facing => {
+                        let m = if facing == Facing::West { -1 } else { 1 };
+                        let dy = match justify {
+                            0 => -step * (fanout / 2),
+                            _ if m * justify > 0 => 10,
+                            _ => -step * fanout,
+                        };
+
+                        ((m * 20, dy), (0, step))
+                    },
+                };
+
+                Part::Splitter {
+                    combined: at(element.loc, incoming),
+                    ends: (0..fanout)
+                        .map(|end| {
+                            let carried = (0..incoming)
+                                .filter(|bit| ends[*bit] == Some(end as usize))
+                                .collect::<Vec<_>>();
+
+                            (at((x + dx + ddx * end, y + dy + ddy * end), carried.len()), carried)
+                        })
+                        .collect(),
+                }
+            },
+            _ => return None,
+        };
+
+        Some(part)
+    }
+
+    fn gate(&mut self, element: &Element) -> Option<Part> {
+        let (kind, bonus, inverted) = match element.name.as_str() {
+            "AND Gate" => ("and", 0, false),
+            "OR Gate" => ("or", 0, false),
+            "NAND Gate" => ("and", 0, true),
+            "NOR Gate" => ("or", 0, true),
+            // Logisim's XOR gates are high when exactly one input is, unless set to look for an odd number of them
+            "XOR Gate" | "XNOR Gate" => (
+                match element.attribute("xor") {
+                    Some("odd") => "parity",
+                    _ => "xor",
+                },
+                10,
+                element.name == "XNOR Gate",
+            ),
+            "Odd Parity" => ("parity", 10, false),
+            "Even Parity" => ("parity", 10, true),
+            "Buffer" => ("buffer", 0, false),
+            "NOT Gate" => ("buffer", 0, true),
+            _ => return None,
+        };
+
+        let (x, y) = element.loc;
+        let width = element.number("width", 1) as usize;
+        let size = element.number("size", 50) as i64;
+        let inputs = match kind {
+            "buffer" => 1,
+            _ => element.number("inputs", if self.classic { 5 } else { 2 }) as i64,
+        };
+        let negated = |index: i64| element.attribute(&format!("negate{}", index)) == Some("true");
+
+        // Each input gets a bit of the mask of inverted inputs
+        if !(1..=64).contains(&width) || !(1..=64).contains(&inputs) {
+            return None;
+        }
+
+        // Where Logisim puts each input, from the output, for a gate facing east
+        let (start, distance, lower) = match inputs {
+            0..=3 if size < 40 => (-5, 10, 10),
+            0..=3 if size < 60 || inputs <= 2 => (-10, 20, 20),
+            0..=3 => (-15, 30, 30),
+            4 if size >= 60 => (-5, 20, 0),
+            _ => (-5, 10, 10),
+        };
+        let offset = |index: i64| {
+            let dx = match element.name.as_str() {
+                "NOT Gate" if size == 20 => 20,
+                "NOT Gate" => 30,
+                "Buffer" => 20,
+                _ => size + bonus + if inverted { 10 } else { 0 } + if negated(index) { 10 } else { 0 },
+            };
+            let dy = match inputs % 2 {
+                1 => start * (inputs - 1) + distance * index,
+                _ => start * inputs + distance * index + if index >= inputs / 2 { lower } else { 0 },
+            };
+
+            // Gates facing west are mirrored rather than turned around
+            match element.facing() {
+                Facing::East => (x - dx, y + dy),
+                Facing::West => (x + dx, y + dy),
+                Facing::North => (x + dy, y + dx),
+                Facing::South => (x + dy, y - dx),
+            }
+        };
+
+        let mut attached = (0..inputs)
+            .filter(|index| self.connected.contains(&offset(*index)))
+            .collect::<Vec<_>>();
+        if attached.is_empty() {
+            attached = (0..inputs).collect();
+        }
+
+        let mask = attached.iter()
+            .enumerate()
+            .filter(|(_, index)| negated(**index))
+            .fold(0, |mask, (input, _)| mask | 1 << input);
+
+        let component = match (kind, inverted, attached.len(), width, mask) {
+            ("and", false, 2, 1, 0) => Project::AND,
+            ("or", false, 2, 1, 0) => Project::OR,
+            ("buffer", true, 1, 1, 0) => Project::NOT,
+            _ => self.gate_component(element, kind, inverted, attached.len(), width, mask),
+        };
+
+        Some(Part::Placed {
+            component,
+            label: element.label(),
+            inputs: attached.iter()
+                .enumerate()
+                .map(|(terminal, index)| (terminal as u64, Attachment { point: offset(*index), width }))
+                .collect(),
+            outputs: vec![(0, Attachment { point: element.loc, width })],
+        })
+    }
+
+    // An expression component doing a gate's job on each bit
+    fn gate_component(&mut self, element: &Element, kind: &str, inverted: bool, inputs: usize, width: usize, mask: u64) -> ComponentId {
+        let name = element.name.trim_end_matches(" Gate").to_lowercase();
+        let key = (name.clone(), inputs, width, mask);
+
+        if let Some(id) = self.gates.get(&key) {
+            return *id;
+        }
+
+        let input_ports = (0..inputs)
+            .map(|index| Port::new(interop::input_name(index), width as u8))
+            .collect::<Vec<_>>();
+        let output_ports = vec![Port::new("y", width as u8)];
+
+        let bits = |port: &Port| Port::bit_names(std::slice::from_ref(port));
+        let all = |operands: Vec<Expr>, operator: fn(Box<Expr>, Box<Expr>) -> Expr| operands.into_iter()
+            .reduce(|lhs, rhs| operator(Box::new(lhs), Box::new(rhs)))
+            .unwrap_or(Expr::Constant(false));
+
+        let equations = bits(&output_ports[0])
+            .into_iter()
+            .enumerate()
+            .map(|(bit, output)| {
+                let operands = input_ports.iter()
+                    .enumerate()
+                    .map(|(input, port)| match mask >> input & 1 {
+                        1 => Expr::Not(Box::new(Expr::Variable(bits(port)[bit].clone()))),
+                        _ => Expr::Variable(bits(port)[bit].clone()),
+                    })
+                    .collect::<Vec<_>>();
+
+                let expr = match kind {
+                    "and" => all(operands, Expr::And),
+                    "or" => all(operands, Expr::Or),
+                    "xor" if inputs > 2 => all((0..inputs)
+                        .map(|high| all(operands.iter()
+                            .enumerate()
+                            .map(|(input, operand)| match input == high {
+                                true => operand.clone(),
+                                false => Expr::Not(Box::new(operand.clone())),
+                            })
+                            .collect(), Expr::And))
+                        .collect(), Expr::Or),
+                    "xor" | "parity" => all(operands, Expr::Xor),
+                    _ => all(operands, Expr::And),
+                };
+
+                Equation {
+                    output,
+                    expr: match inverted {
+                        true => Expr::Not(Box::new(expr)),
+                        false => expr,
+                    },
+                }
+            })
+            .collect();
+
+        let id = interop::next_component(self.project);
+        self.project.components.insert(id, Component {
+            id,
+            name,
+            inputs: input_ports,
+            outputs: output_ports,
+            driver: ComponentDriver::Expression { equations },
+            delay: 1,
+        });
+
+        self.gates.insert(key, id);
+        id
+    }
+
+    fn constant(&mut self, width: usize, value: u64) -> ComponentId {
+        let value = match width {
+            0..64 => value & ((1 << width) - 1),
+            _ => value,
+        };
+        let key = ("constant".to_owned(), 0, width, value);
+
+        if let Some(id) = self.gates.get(&key) {
+            return *id;
+        }
+
+        let output = Port::new("y", width as u8);
+        let equations = Port::bit_names(std::slice::from_ref(&output))
+            .into_iter()
+            .enumerate()
+            .map(|(bit, output)| Equation { output, expr: Expr::Constant(value >> bit & 1 == 1) })
+            .collect();
+
+        let id = interop::next_component(self.project);
+        self.project.components.insert(id, Component {
+            id,
+            name: format!("{:#x}", value),
+            inputs: vec![],
+            outputs: vec![output],
+            driver: ComponentDriver::Expression { equations },
+            delay: 1,
+        });
+
+        self.gates.insert(key, id);
+        id
+    }
+
+    fn subcircuit(&self, element: &Element, interface: &Interface) -> Part {
+        let (x, y) = element.loc;
+        let facing = element.facing();
+        let component = &self.project.components[&interface.component];
+
+        let offsets = match &interface.appearance {
+            Appearance::Fixed(ports, from) => interface.inputs.iter()
+                .chain(interface.outputs.iter())
+                .map(|pin| ports.get(pin).map(|offset| facing.rotate(*from, *offset)))
+                .collect::<Vec<_>>(),
+            Appearance::Evolution => {
+                let at = |dx: i64, index: usize| facing.rotate(Facing::East, (dx, 20 * index as i64));
+
+                // How wide the box is depends on the labels and font, so take the width that lines the most inputs up with something
+                let width = match interface.outputs.is_empty() {
+                    true => 0,
+                    false => (3..=50)
+                        .map(|steps| 10 * steps)
+                        .max_by_key(|width| {
+                            let lined_up = (0..interface.inputs.len())
+                                .filter(|index| {
+                                    let (dx, dy) = at(-width, *index);
+                                    self.connected.contains(&(x + dx, y + dy))
+                                })
+                                .count();
+
+                            (lined_up, std::cmp::Reverse(*width))
+                        })
+                        .unwrap_or_default(),
+                };
+
+                (0..interface.inputs.len())
+                    .map(|index| Some(at(-width, index)))
+                    .chain((0..interface.outputs.len()).map(|index| Some(at(0, index))))
+                    .collect()
+            },
+        };
+
+        // Ports the appearance leaves out are left unattached
+        let (inputs, outputs) = offsets.split_at(interface.inputs.len());
+        let attachments = |offsets: &[Option<Point>], ports: &[Port]| offsets.iter()
+            .zip(ports.iter())
+            .enumerate()
+            .filter_map(|(terminal, (offset, port))| offset.map(|(dx, dy)| (terminal as u64, Attachment { point: (x + dx, y + dy), width: port.width as usize })))
+            .collect();
+
+        Part::Placed {
+            component: interface.component,
+            label: element.label(),
+            inputs: attachments(inputs, &component.inputs),
+            outputs: attachments(outputs, &component.outputs),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::Circuit as Simulation;
+
+    // Logisim 2.7: an AND gate, an inverter circuit drawn the classic way, and an LED
+    const CLASSIC: &str = r##"<?xml version="1.0" encoding="UTF-8" standalone="no"?>
+        <project source="2.7.1" version="1.0">
+          <lib desc="#Wiring" name="0"/>
+          <lib desc="#Gates" name="1"/>
+          <lib desc="#Base" name="5"/>
+          <lib desc="#I/O" name="6"/>
+          <main name="main"/>
+          <circuit name="main">
+            <wire from="(100,100)" to="(140,100)"/>
+            <wire from="(100,140)" to="(140,140)"/>
+            <wire from="(190,120)" to="(240,120)"/>
+            <wire from="(100,100)" to="(100,200)"/>
+            <wire from="(100,200)" to="(270,200)"/>
+            <wire from="(300,200)" to="(340,200)"/>
+            <comp lib="0" loc="(100,100)" name="Pin"><a name="label" val="a"/></comp>
+            <comp lib="0" loc="(100,140)" name="Pin"><a name="label" val="b"/></comp>
+            <comp lib="1" loc="(190,120)" name="AND Gate"><a name="inputs" val="2"/></comp>
+            <comp lib="0" loc="(240,120)" name="Pin">
+              <a name="facing" val="west"/>
+              <a name="output" val="true"/>
+              <a name="label" val="y"/>
+            </comp>
+            <comp loc="(300,200)" name="inverter"/>
+            <comp lib="0" loc="(340,200)" name="Pin">
+              <a name="facing" val="west"/>
+              <a name="output" val="true"/>
+              <a name="label" val="na"/>
+            </comp>
+            <comp lib="5" loc="(100,300)" name="Text"><a name="text" val="notes"/></comp>
+            <comp lib="6" loc="(400,300)" name="LED"/>
+          </circuit>
+          <circuit name="inverter">
+            <wire from="(100,100)" to="(130,100)"/>
+            <wire from="(160,100)" to="(200,100)"/>
+            <comp lib="0" loc="(100,100)" name="Pin"><a name="label" val="in"/></comp>
+            <comp lib="1" loc="(160,100)" name="NOT Gate"/>
+            <comp lib="0" loc="(200,100)" name="Pin">
+              <a name="facing" val="west"/>
+              <a name="output" val="true"/>
+              <a name="label" val="out"/>
+            </comp>
+          </circuit>
+        </project>"##;
+
+    // Logisim-evolution: a bus split in two, one half carried by a tunnel into an inverter circuit, and elements too wide to keep
+    const EVOLUTION: &str = r##"<?xml version="1.0" encoding="UTF-8" standalone="no"?>
+        <project source="3.8.0" version="1.0">
+          <lib desc="#Wiring" name="0"/>
+          <lib desc="#Gates" name="1"/>
+          <main name="main"/>
+          <circuit name="main">
+            <wire from="(100,100)" to="(150,100)"/>
+            <wire from="(170,80)" to="(220,80)"/>
+            <wire from="(300,300)" to="(340,300)"/>
+            <wire from="(400,300)" to="(440,300)"/>
+            <comp lib="0" loc="(100,100)" name="Pin">
+              <a name="width" val="4"/>
+              <a name="label" val="bus"/>
+            </comp>
+            <comp lib="0" loc="(150,100)" name="Splitter">
+              <a name="incoming" val="4"/>
+              <a name="fanout" val="2"/>
+            </comp>
+            <comp lib="0" loc="(220,80)" name="Pin">
+              <a name="facing" val="west"/>
+              <a name="type" val="output"/>
+              <a name="width" val="2"/>
+              <a name="label" val="low"/>
+            </comp>
+            <comp lib="0" loc="(170,90)" name="Tunnel">
+              <a name="width" val="2"/>
+              <a name="label" val="high"/>
+            </comp>
+            <comp lib="0" loc="(300,300)" name="Tunnel">
+              <a name="facing" val="west"/>
+              <a name="width" val="2"/>
+              <a name="label" val="high"/>
+            </comp>
+            <comp loc="(400,300)" name="inverter"/>
+            <comp lib="0" loc="(440,300)" name="Pin">
+              <a name="facing" val="west"/>
+              <a name="type" val="output"/>
+              <a name="width" val="2"/>
+              <a name="label" val="nhigh"/>
+            </comp>
+            <comp lib="0" loc="(600,100)" name="Splitter"><a name="fanout" val="0"/></comp>
+            <comp lib="0" loc="(600,200)" name="Splitter">
+              <a name="incoming" val="70"/>
+              <a name="fanout" val="65"/>
+            </comp>
+            <comp lib="0" loc="(600,300)" name="Tunnel"><a name="width" val="100"/></comp>
+            <comp lib="1" loc="(600,400)" name="AND Gate"><a name="inputs" val="65"/></comp>
+            <comp lib="1" loc="(600,500)" name="OR Gate"><a name="width" val="65"/></comp>
+          </circuit>
+          <circuit name="inverter">
+            <wire from="(100,100)" to="(130,100)"/>
+            <wire from="(160,100)" to="(200,100)"/>
+            <comp lib="0" loc="(100,100)" name="Pin">
+              <a name="width" val="2"/>
+              <a name="label" val="in"/>
+            </comp>
+            <comp lib="1" loc="(160,100)" name="NOT Gate"><a name="width" val="2"/></comp>
+            <comp lib="0" loc="(200,100)" name="Pin">
+              <a name="facing" val="west"/>
+              <a name="type" val="output"/>
+              <a name="width" val="2"/>
+              <a name="label" val="out"/>
+            </comp>
+          </circuit>
+        </project>"##;
+
+    // Sets the inputs of a circuit by label, then reads the output with the given label
+    fn step(circuit: &mut Simulation, inputs: &[(&str, u64)], output: &str) -> Option<u64> {
+        for (label, value) in inputs {
+            circuit.set_input(circuit.find(label).unwrap(), *value).unwrap();
+        }
+
+        circuit.evaluate().unwrap();
+        circuit.output(circuit.find(output).unwrap()).unwrap().to_u64()
+    }
+
+    fn elements(untranslated: &[Untranslated]) -> Vec<(&str, (i64, i64))> {
+        untranslated.iter()
+            .map(|element| (element.element.as_str(), element.location))
+            .collect()
+    }
+
+    #[test]
+    fn classic() {
+        let (project, untranslated) = import(CLASSIC).unwrap();
+        assert_eq!(elements(&untranslated), [("LED", (400, 300))]);
+
+        let mut circuit = Simulation::compile(&project).unwrap();
+
+        for row in 0..4 {
+            let (a, b) = (row & 1, row >> 1);
+            let inputs = [("a", a), ("b", b)];
+
+            assert_eq!(step(&mut circuit, &inputs, "y"), Some(a & b));
+            assert_eq!(step(&mut circuit, &inputs, "na"), Some(1 - a));
+        }
+    }
+
+    #[test]
+    fn evolution() {
+        let (project, untranslated) = import(EVOLUTION).unwrap();
+        assert_eq!(elements(&untranslated), [
+            ("Splitter", (600, 100)),
+            ("Splitter", (600, 200)),
+            ("Tunnel", (600, 300)),
+            ("AND Gate", (600, 400)),
+            ("OR Gate", (600, 500)),
+        ]);
+
+        let mut circuit = Simulation::compile(&project).unwrap();
+
+        for bus in 0..16 {
+            assert_eq!(step(&mut circuit, &[("bus", bus)], "low"), Some(bus & 3));
+            assert_eq!(step(&mut circuit, &[("bus", bus)], "nhigh"), Some(!bus >> 2 & 3));
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let wide = EVOLUTION.replace(r#"<a name="width" val="4"/>"#, r#"<a name="width" val="65"/>"#);
+        assert!(import(&wide).err().unwrap().to_string().contains("bus"));
+        assert!(import("<project").is_err());
+    }
+}
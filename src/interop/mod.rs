@@ -0,0 +1,793 @@
+//! Reading and writing the formats of other logic design tools.
+
+pub mod blif;
+pub mod logisim;
+pub mod verilog;
+pub mod vhdl;
+pub mod yosys;
+
+use crate::{
+    analysis,
+    circuit::CircuitError,
+    expression::Equation,
+    expression::Expr,
+    layout,
+    project::Component,
+    project::ComponentDriver,
+    project::ComponentId,
+    project::Connection,
+    project::Coord,
+    project::FlipFlopKind,
+    project::InstanceId,
+    project::Placement,
+    project::Port,
+    project::Project,
+    project::Wire,
+    Result
+};
+use std::collections::{
+    HashMap,
+    HashSet
+};
+
+/// A project flattened into named modules the way hardware description languages see it, ready to be written out.
+/// Every subcomponent and every other component it uses becomes a module of its own, listed before the modules using it.
+/// The last module is the top level of the project, named after `top` made into an identifier.
+pub(crate) struct Design<'a> {
+    pub(crate) modules: Vec<Module<'a>>,
+}
+
+pub(crate) struct Module<'a> {
+    pub(crate) name: String,
+    pub(crate) inputs: Vec<Signal>,
+    pub(crate) outputs: Vec<Signal>,
+    pub(crate) body: Body<'a>,
+    // Whether the project uses the module, as opposed to it only being written out for completeness
+    pub(crate) used: bool,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Signal {
+    pub(crate) name: String,
+    pub(crate) width: u8,
+}
+
+pub(crate) enum Body<'a> {
+    // A component whose behaviour comes from its driver
+    Leaf(&'a Component),
+    Netlist {
+        wires: Vec<Signal>,
+        instances: Vec<Instance>,
+        // Output ports driven straight from a net, as `(port, net)`
+        assigns: Vec<(String, String)>,
+    },
+}
+
+pub(crate) struct Instance {
+    pub(crate) name: String,
+    pub(crate) module: String,
+    // Each input port of the module followed by each output port, along with the net connected to it, if any
+    pub(crate) connections: Vec<(Signal, Option<String>)>,
+    // How many of the connections are to input ports
+    pub(crate) inputs: usize,
+}
+
+impl<'a> Module<'a> {
+    /// Names every input bit the way `Port::bit_names` does, mapped to how `bit` writes that bit in some language.
+    pub(crate) fn bits(&self, component: &Component, bit: impl Fn(&Signal, u8) -> String) -> HashMap<String, String> {
+        let names = |ports: &[Port], signals: &[Signal]| Port::bit_names(ports)
+            .into_iter()
+            .zip(signals.iter().flat_map(|signal| (0..signal.width).map(move |index| (signal, index))))
+            .map(|(name, (signal, index))| (name, bit(signal, index)))
+            .collect::<Vec<_>>();
+
+        names(&component.inputs, &self.inputs)
+            .into_iter()
+            .chain(names(&component.outputs, &self.outputs))
+            .collect()
+    }
+
+    /// The names taken by the ports of a module for a component that isn't made of other components, which its internal
+    /// signals must not clash with. Fails on stateful components with fewer ports than simulation expects,
+    /// since they are written in terms of their ports.
+    pub(crate) fn taken(&self, component: &Component) -> Result<HashSet<String>> {
+        let (inputs, outputs) = match &component.driver {
+            ComponentDriver::FlipFlop { kind: FlipFlopKind::JK } => (3, 1),
+            ComponentDriver::FlipFlop { .. } | ComponentDriver::Latch { .. } | ComponentDriver::Memory { .. } => (2, 1),
+            _ => (0, 0),
+        };
+
+        if self.inputs.len() < inputs || self.outputs.len() < outputs {
+            let port = component.inputs.iter().chain(component.outputs.iter()).last().map(|port| port.name.clone()).unwrap_or_default();
+            return Err(CircuitError::PortMismatch { component: component.id, port }.into());
+        }
+
+        Ok(self.inputs.iter()
+            .chain(self.outputs.iter())
+            .map(|signal| signal.name.to_lowercase())
+            .collect())
+    }
+
+    /// Drives the first output of a flip-flop or latch from its stored `state` and the second output, if any, from the inverse,
+    /// with `assign` writing out each output and its value.
+    pub(crate) fn stored(&self, state: &str, operators: &Operators, mut assign: impl FnMut(&str, &str)) {
+        assign(&self.outputs[0].name, state);
+
+        if let Some(inverse) = self.outputs.get(1) {
+            assign(&inverse.name, &format!("{}{}", operators.not, state));
+        }
+    }
+}
+
+impl<'a> Design<'a> {
+    /// Names every module and net with identifiers that are valid in a language.
+    /// With `unused`, components the project doesn't use get modules too, unless they can't be written out.
+    pub(crate) fn new(project: &'a Project, top: &str, naming: &Naming, unused: bool) -> Result<Self> {
+        if let Some(cycle) = analysis::recursion(project) {
+            return Err(CircuitError::RecursiveComponent(cycle).into());
+        }
+
+        let top = identifier(top, naming);
+        let mut taken = HashSet::from([top.to_lowercase()]);
+        let mut names = HashMap::new();
+        let mut order = vec![];
+
+        used(project, placed(&project.body), &mut names, &mut order, &mut taken, naming);
+        let reachable = order.len();
+
+        if unused {
+            let mut components = project.components.keys().copied().collect::<Vec<_>>();
+            components.sort_unstable();
+
+            used(project, components, &mut names, &mut order, &mut taken, naming);
+        }
+
+        let mut modules = vec![];
+
+        for (index, id) in order.into_iter().enumerate() {
+            let component = &project.components[&id];
+            let (inputs, outputs) = signals(component, naming);
+
+            let body = match (widthless(component), &component.driver) {
+                (Some(port), _) => Err(CircuitError::PortMismatch { component: id, port }.into()),
+                (None, ComponentDriver::Subcomponent { body, connections, .. }) => subcomponent(project, component, body, connections, &names, &inputs, &outputs, naming),
+                (None, _) => Ok(Body::Leaf(component)),
+            };
+
+            let used = index < reachable;
+            let body = match body {
+                Ok(body) => body,
+                Err(_) if !used => continue,
+                Err(err) => return Err(err),
+            };
+
+            modules.push(Module { name: names[&id].clone(), inputs, outputs, body, used });
+        }
+
+        let mut inputs = vec![];
+        let mut outputs = vec![];
+        let mut ports = HashMap::new();
+        let mut taken = HashSet::new();
+
+        let mut placements = project.body.values().collect::<Vec<_>>();
+        placements.sort_by_key(|placement| placement.instance);
+
+        // Every terminal of a top-level input, output or clock becomes a port, named after the placement's label
+        for placement in placements {
+            let Some(component) = project.components.get(&placement.component) else {
+                continue;
+            };
+            if let Some(port) = widthless(component) {
+                return Err(CircuitError::PortMismatch { component: component.id, port }.into());
+            }
+            let (terminals, signals, terminal): (_, _, fn(InstanceId, u64) -> Connection) = match component.driver {
+                ComponentDriver::Input | ComponentDriver::Clock { .. } => (&component.outputs, &mut inputs, Connection::output),
+                ComponentDriver::Output => (&component.inputs, &mut outputs, Connection::input),
+                _ => continue,
+            };
+
+            let label = placement.label.clone()
+                .unwrap_or_else(|| format!("instance{}", placement.instance));
+
+            for (index, port) in terminals.iter().enumerate() {
+                let name = match terminals.len() {
+                    1 => label.clone(),
+                    _ => format!("{}_{}", label, port.name),
+                };
+                let name = unique(&identifier(&name, naming), &mut taken);
+
+                ports.insert(terminal(placement.instance, index as u64), name.clone());
+                signals.push(Signal { name, width: port.width });
+            }
+        }
+
+        let body = netlist(project, &project.body, &project.connections, &names, ports, naming)?;
+        modules.push(Module { name: top, inputs, outputs, body, used: true });
+
+        Ok(Self { modules })
+    }
+}
+
+// A port of no width, which no language can declare
+fn widthless(component: &Component) -> Option<String> {
+    component.inputs.iter()
+        .chain(component.outputs.iter())
+        .find(|port| port.width == 0)
+        .map(|port| port.name.clone())
+}
+
+// The components placed in a body, each once
+fn placed(body: &HashMap<InstanceId, Placement>) -> Vec<ComponentId> {
+    let mut components = body.values()
+        .map(|placement| placement.component)
+        .collect::<Vec<_>>();
+    components.sort_unstable();
+    components.dedup();
+
+    components
+}
+
+// Lists the components that need modules of their own, each after the components it uses
+fn used(project: &Project, components: Vec<ComponentId>, names: &mut HashMap<ComponentId, String>, order: &mut Vec<ComponentId>, taken: &mut HashSet<String>, naming: &Naming) {
+    for id in components {
+        let Some(component) = project.components.get(&id) else {
+            continue;
+        };
+
+        // Ports take the place of inputs, outputs and clocks
+        if names.contains_key(&id) || matches!(component.driver, ComponentDriver::Input | ComponentDriver::Output | ComponentDriver::Clock { .. }) {
+            continue;
+        }
+
+        names.insert(id, unique(&identifier(&component.name, naming), taken));
+
+        if let ComponentDriver::Subcomponent { body, .. } = &component.driver {
+            used(project, placed(body), names, order, taken, naming);
+        }
+
+        order.push(id);
+    }
+}
+
+// Connects up the body of a subcomponent
+#[allow(clippy::too_many_arguments)]
+fn subcomponent<'a>(
+    project: &Project,
+    component: &Component,
+    body: &HashMap<InstanceId, Placement>,
+    connections: &HashMap<Connection, Vec<Connection>>,
+    names: &HashMap<ComponentId, String>,
+    inputs: &[Signal],
+    outputs: &[Signal],
+    naming: &Naming,
+) -> Result<Body<'a>> {
+    // Ports are matched by name to the labels of the input and output placements inside
+    let mut ports = HashMap::new();
+
+    for placement in body.values() {
+        let inner = project.components.get(&placement.component)
+            .ok_or(CircuitError::UnknownComponent { instance: placement.instance, component: placement.component })?;
+        let (terminal, own, signals): (fn(u64) -> Connection, _, _) = match inner.driver {
+            ComponentDriver::Input => (|terminal| Connection::output(0, terminal), &component.inputs, inputs),
+            ComponentDriver::Output => (|terminal| Connection::input(0, terminal), &component.outputs, outputs),
+            ComponentDriver::Clock { .. } => return Err(ExportError::Unsupported(inner.id).into()),
+            _ => continue,
+        };
+
+        let label = placement.label.clone().unwrap_or_default();
+        let index = own.iter()
+            .position(|port| port.name == label)
+            .ok_or(CircuitError::PortMismatch { component: component.id, port: label })?;
+
+        ports.insert(Connection { instance: placement.instance, ..terminal(0) }, signals[index].name.clone());
+    }
+
+    netlist(project, body, connections, names, ports, naming)
+}
+
+// Connects up the placements in a body. `ports` names the signal each terminal of the body's input and output placements stands for.
+fn netlist<'a>(
+    project: &Project,
+    body: &HashMap<InstanceId, Placement>,
+    connections: &HashMap<Connection, Vec<Connection>>,
+    names: &HashMap<ComponentId, String>,
+    ports: HashMap<Connection, String>,
+    naming: &Naming,
+) -> Result<Body<'a>> {
+    let mut placements = body.values().collect::<Vec<_>>();
+    placements.sort_by_key(|placement| placement.instance);
+
+    let mut taken = ports.values()
+        .map(|port| port.to_lowercase())
+        .chain(naming.keywords.iter().map(|keyword| keyword.to_lowercase()))
+        .collect::<HashSet<_>>();
+
+    // A net for every output terminal, named after the port it is when there is one
+    let mut nets = HashMap::new();
+    let mut wires = vec![];
+
+    for placement in placements.iter() {
+        let component = project.components.get(&placement.component)
+            .ok_or(CircuitError::UnknownComponent { instance: placement.instance, component: placement.component })?;
+
+        for (terminal, port) in component.outputs.iter().enumerate() {
+            let connection = Connection::output(placement.instance, terminal as u64);
+
+            let net = match ports.get(&connection) {
+                Some(port) => port.clone(),
+                None => {
+                    let net = unique(&format!("n{}_{}", placement.instance, terminal), &mut taken);
+                    wires.push(Signal { name: net.clone(), width: port.width });
+                    net
+                },
+            };
+
+            nets.insert(connection, net);
+        }
+    }
+
+    // The net driving each input terminal
+    let mut driven = HashMap::new();
+
+    for (output, inputs) in connections.iter() {
+        let net = nets.get(output).ok_or(CircuitError::InvalidConnection(*output))?;
+
+        for input in inputs {
+            if driven.insert(*input, net.clone()).is_some() {
+                return Err(CircuitError::MultipleDrivers(*input).into());
+            }
+        }
+    }
+
+    let mut instances = vec![];
+    let mut assigns = vec![];
+
+    for placement in placements {
+        let component = &project.components[&placement.component];
+
+        match component.driver {
+            ComponentDriver::Output => for terminal in 0..component.inputs.len() {
+                let connection = Connection::input(placement.instance, terminal as u64);
+
+                // Undriven outputs are left floating
+                if let (Some(port), Some(net)) = (ports.get(&connection), driven.get(&connection)) {
+                    assigns.push((port.clone(), net.clone()));
+                }
+            },
+            ComponentDriver::Input | ComponentDriver::Clock { .. } => {},
+            _ => {
+                let (inputs, outputs) = signals(component, naming);
+                let inputs = inputs.into_iter()
+                    .enumerate()
+                    .map(|(terminal, signal)| (signal, driven.get(&Connection::input(placement.instance, terminal as u64)).cloned()));
+                let outputs = outputs.into_iter()
+                    .enumerate()
+                    .map(|(terminal, signal)| (signal, nets.get(&Connection::output(placement.instance, terminal as u64)).cloned()));
+
+                instances.push(Instance {
+                    name: unique(&format!("u{}", placement.instance), &mut taken),
+                    module: names[&placement.component].clone(),
+                    connections: inputs.chain(outputs).collect(),
+                    inputs: component.inputs.len(),
+                });
+            },
+        }
+    }
+
+    Ok(Body::Netlist { wires, instances, assigns })
+}
+
+/// How a language spells identifiers.
+pub(crate) struct Naming {
+    // Reserved words, compared without regard to case
+    pub(crate) keywords: &'static [&'static str],
+    // Whether underscores can't lead, trail or follow each other, as in VHDL
+    pub(crate) strict: bool,
+}
+
+/// Turns a name into an identifier made of letters, digits and underscores that doesn't start with a digit or clash with a keyword.
+pub(crate) fn identifier(name: &str, naming: &Naming) -> String {
+    let mut identifier = name.chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c,
+            false => '_',
+        })
+        .collect::<String>();
+
+    if naming.strict {
+        identifier = identifier.split('_')
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("_");
+    }
+
+    if identifier.chars().next().is_none_or(|c| c.is_ascii_digit()) {
+        identifier.insert(0, 'n');
+    }
+
+    if naming.keywords.iter().any(|keyword| keyword.eq_ignore_ascii_case(&identifier)) {
+        identifier.push_str(match naming.strict {
+            true => "_1",
+            false => "_",
+        });
+    }
+
+    identifier
+}
+
+// Suffixes a number onto an identifier until it hasn't been used yet
+pub(crate) fn unique(identifier: &str, taken: &mut HashSet<String>) -> String {
+    let mut candidate = identifier.to_owned();
+    let mut suffix = 1;
+
+    while !taken.insert(candidate.to_lowercase()) {
+        suffix += 1;
+        candidate = format!("{}_{}", identifier, suffix);
+    }
+
+    candidate
+}
+
+// The ports of a component, inputs then outputs, named so that none of them clash
+fn signals(component: &Component, naming: &Naming) -> (Vec<Signal>, Vec<Signal>) {
+    let mut taken = HashSet::new();
+    let mut signals = |ports: &[Port]| ports.iter()
+        .map(|port| Signal { name: unique(&identifier(&port.name, naming), &mut taken), width: port.width })
+        .collect::<Vec<_>>();
+
+    let inputs = signals(&component.inputs);
+    (inputs, signals(&component.outputs))
+}
+
+/// How a language writes Boolean operators.
+pub(crate) struct Operators {
+    pub(crate) not: &'static str,
+    pub(crate) and: &'static str,
+    pub(crate) or: &'static str,
+    pub(crate) xor: &'static str,
+    pub(crate) constants: [&'static str; 2],
+    // Whether the binary operators bind with different strengths, as they do in `Expr`, or all need parentheses when mixed
+    pub(crate) precedence: bool,
+}
+
+/// Writes an expression in some language, using `variable` to refer to each input bit.
+pub(crate) fn expression(expr: &Expr, operators: &Operators, variable: &impl Fn(&str) -> String) -> String {
+    let operand = |inner: &Expr, right: bool| {
+        let text = expression(inner, operators, variable);
+
+        let leaf = matches!(inner, Expr::Constant(_) | Expr::Variable(_));
+        let bracket = match (operators.precedence, expr) {
+            (true, _) => inner.precedence() < expr.precedence() + right as u8,
+            (false, Expr::Not(_)) => !leaf,
+            (false, _) => !leaf && !matches!(inner, Expr::Not(_)) && (right || std::mem::discriminant(inner) != std::mem::discriminant(expr)),
+        };
+
+        match bracket {
+            true => format!("({})", text),
+            false => text,
+        }
+    };
+
+    match expr {
+        Expr::Constant(constant) => operators.constants[*constant as usize].to_owned(),
+        Expr::Variable(name) => variable(name),
+        Expr::Not(inner) => format!("{}{}", operators.not, operand(inner, false)),
+        Expr::And(lhs, rhs) => format!("{} {} {}", operand(lhs, false), operators.and, operand(rhs, true)),
+        Expr::Or(lhs, rhs) => format!("{} {} {}", operand(lhs, false), operators.or, operand(rhs, true)),
+        Expr::Xor(lhs, rhs) => format!("{} {} {}", operand(lhs, false), operators.xor, operand(rhs, true)),
+    }
+}
+
+/// The contents of a circuit being read in from another format, either the top level of a project or the body of a subcomponent.
+#[derive(Default)]
+pub(crate) struct Schematic {
+    pub(crate) body: HashMap<InstanceId, Placement>,
+    pub(crate) connections: HashMap<Connection, Vec<Connection>>,
+    pub(crate) wires: Vec<Wire>,
+}
+
+impl Schematic {
+    pub(crate) fn place(&mut self, component: ComponentId, label: Option<String>) -> InstanceId {
+        let instance = self.body.len();
+
+        self.body.insert(instance, Placement {
+            component,
+            instance,
+            label,
+            pos: Coord(0.0, 0.0),
+            orientation: 0.0,
+            delay: None,
+        });
+
+        instance
+    }
+
+    pub(crate) fn connect(&mut self, output: Connection, input: Connection) {
+        self.connections.entry(output)
+            .or_default()
+            .push(input);
+
+        self.wires.push(Wire {
+            from: output.instance,
+            from_terminal: output.terminal,
+            points: vec![],
+            to: input.instance,
+            to_terminal: input.terminal,
+        });
+    }
+
+    /// Positions every placement in columns by how far it is from the inputs. Feedback loops are broken wherever they are found.
+    pub(crate) fn lay_out(&mut self) {
+        let mut predecessors = vec![HashSet::new(); self.body.len()];
+
+        for (output, inputs) in self.connections.iter() {
+            for input in inputs {
+                predecessors[input.instance].insert(output.instance);
+            }
+        }
+
+        // Kahn's algorithm, cutting the wires into the earliest placement left whenever only loops remain
+        let mut order = vec![];
+        let mut placed = vec![false; self.body.len()];
+        let mut remaining = predecessors.clone();
+
+        while order.len() < self.body.len() {
+            let next = (0..self.body.len())
+                .find(|instance| !placed[*instance] && remaining[*instance].is_empty())
+                .or_else(|| (0..self.body.len()).find(|instance| !placed[*instance]))
+                .unwrap_or_default();
+
+            placed[next] = true;
+            order.push(next);
+
+            for inputs in remaining.iter_mut() {
+                inputs.remove(&next);
+            }
+        }
+
+        let index = order.iter()
+            .enumerate()
+            .map(|(index, instance)| (*instance, index))
+            .collect::<HashMap<_, _>>();
+        let predecessors = order.iter()
+            .map(|instance| predecessors[*instance].iter()
+                .map(|predecessor| index[predecessor])
+                .filter(|predecessor| *predecessor < index[instance])
+                .collect())
+            .collect::<Vec<_>>();
+
+        for (instance, pos) in order.iter().zip(layout::layered(&predecessors, Coord(3.0, 3.0))) {
+            if let Some(placement) = self.body.get_mut(instance) {
+                placement.pos = pos;
+            }
+        }
+    }
+
+    pub(crate) fn into_driver(self) -> ComponentDriver {
+        ComponentDriver::Subcomponent {
+            body: self.body,
+            connections: self.connections,
+            wires: self.wires,
+        }
+    }
+}
+
+/// One bit of a bus, for formats that wire buses up a bit at a time.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum Bit {
+    Net(u64),
+    Constant(bool),
+    // Unknown or floating
+    Unknown,
+}
+
+/// Terminals wired up a bit at a time, for formats where buses can be sliced and joined anywhere.
+#[derive(Default)]
+pub(crate) struct Bits {
+    // The output terminal driving each net, along with the bit of the terminal it is and the terminal's width
+    drivers: HashMap<u64, (Connection, usize, usize)>,
+    // The input terminals reading each list of bits, along with a name for the component picking them out should one be needed
+    reads: Vec<(Vec<Bit>, Connection, String)>,
+}
+
+impl Bits {
+    /// Records the nets an output terminal drives, failing with the first net something else drives already.
+    pub(crate) fn drive(&mut self, bits: &[Bit], output: Connection) -> std::result::Result<(), u64> {
+        for (index, bit) in bits.iter().enumerate() {
+            // Constants on outputs just go unused
+            if let Bit::Net(net) = bit {
+                if self.drivers.insert(*net, (output, index, bits.len())).is_some() {
+                    return Err(*net);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn read(&mut self, bits: Vec<Bit>, input: Connection, name: String) {
+        self.reads.push((bits, input, name));
+    }
+
+    /// Wires every input terminal to the bits it reads, once every driver is known.
+    /// A whole output is wired straight across, while anything else, such as a slice of a bus, several buses joined together
+    /// or constants, goes through an expression component picking out the bits.
+    pub(crate) fn connect(&mut self, project: &mut Project, schematic: &mut Schematic) {
+        for (bits, input, name) in std::mem::take(&mut self.reads) {
+            let sources = bits.iter()
+                .map(|bit| match bit {
+                    Bit::Net(net) => self.drivers.get(net).copied(),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+
+            if let Some(Some((output, _, width))) = sources.first() {
+                let whole = *width == bits.len() && sources.iter()
+                    .enumerate()
+                    .all(|(index, source)| matches!(source, Some((from, bit, _)) if from == output && *bit == index));
+
+                if whole {
+                    schematic.connect(*output, input);
+                    continue;
+                }
+            }
+
+            // Nets nothing drives are left floating
+            if bits.iter().all(|bit| matches!(bit, Bit::Net(_))) && sources.iter().all(Option::is_none) {
+                continue;
+            }
+
+            // Each output read from becomes an input port of its own
+            let mut outputs = vec![];
+            for (output, _, width) in sources.iter().flatten() {
+                if !outputs.iter().any(|(existing, _)| existing == output) {
+                    outputs.push((*output, *width));
+                }
+            }
+
+            let ports = outputs.iter()
+                .enumerate()
+                .map(|(index, (_, width))| Port::new(input_name(index), *width as u8))
+                .collect::<Vec<_>>();
+            let output = Port::new("y", bits.len() as u8);
+
+            let equations = Port::bit_names(std::slice::from_ref(&output))
+                .into_iter()
+                .zip(bits.iter().zip(sources.iter()))
+                .filter_map(|(name, (bit, source))| {
+                    let expr = match (bit, source) {
+                        (_, Some((from, bit, _))) => {
+                            let port = outputs.iter().position(|(output, _)| output == from)?;
+                            Expr::Variable(Port::bit_names(std::slice::from_ref(&ports[port]))[*bit].clone())
+                        },
+                        (Bit::Constant(constant), _) => Expr::Constant(*constant),
+                        // Unknown, floating and undriven bits are X
+                        _ => return None,
+                    };
+
+                    Some(Equation { output: name, expr })
+                })
+                .collect();
+
+            let id = next_component(project);
+            project.components.insert(id, Component {
+                id,
+                name,
+                inputs: ports,
+                outputs: vec![output],
+                driver: ComponentDriver::Expression { equations },
+                delay: 1,
+            });
+
+            let instance = schematic.place(id, None);
+
+            for (terminal, (output, _)) in outputs.into_iter().enumerate() {
+                schematic.connect(output, Connection::input(instance, terminal as u64));
+            }
+
+            schematic.connect(Connection::output(instance, 0), input);
+        }
+    }
+}
+
+/// The id the next component added to a project should take.
+pub(crate) fn next_component(project: &Project) -> ComponentId {
+    project.components.keys().max().map(|max| max + 1).unwrap_or(0)
+}
+
+/// Fails with [`ImportError::Recursive`] if an imported module instantiates itself, directly or through other modules,
+/// since the project would never finish compiling.
+pub(crate) fn acyclic(project: &Project) -> Result<()> {
+    match analysis::recursion(project) {
+        Some(cycle) => Err(ImportError::Recursive(cycle.iter()
+            .map(|component| project.components[component].name.clone())
+            .collect()).into()),
+        None => Ok(()),
+    }
+}
+
+/// The name of the `index`th input of a component made up while importing: `a` to `z`, then `i26` onwards.
+pub(crate) fn input_name(index: usize) -> String {
+    match index {
+        0..26 => ((b'a' + index as u8) as char).to_string(),
+        _ => format!("i{}", index),
+    }
+}
+
+/// A signal name made safe to use as a port name, since brackets would be read as the width of the port when the project is loaded.
+pub(crate) fn port_name(signal: &str) -> String {
+    signal.replace('[', "_").replace(']', "")
+}
+
+/// An input or output component with a single port of the given width, added to the project unless there already is one.
+pub(crate) fn port_component(project: &mut Project, input: bool, width: u8) -> ComponentId {
+    let existing = project.components.values()
+        .filter(|component| match input {
+            true => matches!(component.driver, ComponentDriver::Input) && component.outputs.len() == 1 && component.outputs[0].width == width,
+            false => matches!(component.driver, ComponentDriver::Output) && component.inputs.len() == 1 && component.inputs[0].width == width,
+        })
+        .map(|component| component.id)
+        .min();
+
+    if let Some(id) = existing {
+        return id;
+    }
+
+    let id = next_component(project);
+    let (name, driver) = match input {
+        true => ("input", ComponentDriver::Input),
+        false => ("output", ComponentDriver::Output),
+    };
+    let port = Port::new("q", width);
+
+    project.components.insert(id, Component {
+        id,
+        name: name.to_owned(),
+        inputs: match input {
+            true => vec![],
+            false => vec![port.clone()],
+        },
+        outputs: match input {
+            true => vec![port],
+            false => vec![],
+        },
+        driver,
+        delay: 1,
+    });
+
+    id
+}
+
+#[derive(Debug, Clone)]
+pub enum ExportError {
+    // A component with no equivalent in the format, such as a script, or a clock inside a subcomponent
+    Unsupported(ComponentId),
+}
+
+impl std::error::Error for ExportError {}
+impl std::fmt::Display for ExportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ImportError {
+    // Text that couldn't be read, with the line it is on, counting from 1
+    Syntax { line: usize, message: String },
+    UnknownModule(String),
+    UnknownSignal { module: String, signal: String },
+    MultipleDrivers { module: String, signal: String },
+    WidthMismatch { module: String, signal: String },
+    // The modules of a cycle in which each instantiates the next, and the last the first
+    Recursive(Vec<String>),
+    // Something the importer has no equivalent for, such as a kind of cell or an inout port
+    Unsupported(String),
+}
+
+impl std::error::Error for ImportError {}
+impl std::fmt::Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
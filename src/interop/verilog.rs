@@ -0,0 +1,1622 @@
+use crate::{
+    circuit::CircuitError,
+    expression::Equation,
+    expression::Expr,
+    expression::NESTING_LIMIT,
+    interop,
+    interop::Body,
+    interop::Design,
+    interop::ExportError,
+    interop::ImportError,
+    interop::Module,
+    interop::Naming,
+    interop::Operators,
+    interop::Schematic,
+    interop::Signal,
+    minimise,
+    minimise::Form,
+    project::Component,
+    project::ComponentDriver,
+    project::ComponentId,
+    project::Connection,
+    project::FlipFlopKind,
+    project::InstanceId,
+    project::LatchKind,
+    project::MemoryKind,
+    project::Port,
+    project::Project,
+    Result
+};
+use std::{
+    collections::HashMap,
+    collections::HashSet,
+    fmt::Write
+};
+
+const NAMING: Naming = Naming {
+    keywords: &[
+        "always", "and", "assign", "begin", "buf", "case", "casex", "casez", "default", "else", "end", "endcase", "endfunction",
+        "endgenerate", "endmodule", "for", "function", "generate", "genvar", "if", "initial", "inout", "input", "integer",
+        "localparam", "module", "nand", "negedge", "nor", "not", "or", "output", "parameter", "posedge", "reg", "signed",
+        "supply0", "supply1", "task", "tri", "wire", "xnor", "xor",
+    ],
+    strict: false,
+};
+
+const OPERATORS: Operators = Operators {
+    not: "~",
+    and: "&",
+    or: "|",
+    xor: "^",
+    constants: ["1'b0", "1'b1"],
+    precedence: true,
+};
+
+/// Writes a project as Verilog, with a module for every component it uses and `top` for the project itself.
+///
+/// Subcomponents become modules instantiating the modules of their contents, and the project's inputs, outputs and clocks
+/// become ports of `top` named after their labels. Truth tables become `assign`s of their minimised expressions,
+/// or a `case` block when they have too many inputs to minimise.
+pub fn export(project: &Project, top: &str) -> Result<String> {
+    let design = Design::new(project, top, &NAMING, false)?;
+    let mut modules = vec![];
+
+    for module in design.modules.iter() {
+        let mut verilog = String::new();
+        write_module(&mut verilog, module)?;
+        modules.push(verilog);
+    }
+
+    Ok(modules.join("\n"))
+}
+
+fn write_module(out: &mut String, module: &Module) -> Result<()> {
+    let ports = module.inputs.iter()
+        .map(|signal| format!("    input wire {}{}", range(signal.width), signal.name))
+        .chain(module.outputs.iter().map(|signal| format!("    output wire {}{}", range(signal.width), signal.name)))
+        .collect::<Vec<_>>();
+
+    match ports.is_empty() {
+        true => writeln!(out, "module {};", module.name),
+        false => writeln!(out, "module {} (\n{}\n);", module.name, ports.join(",\n")),
+    }.unwrap();
+
+    match &module.body {
+        Body::Netlist { wires, instances, assigns } => {
+            for wire in wires {
+                writeln!(out, "    wire {}{};", range(wire.width), wire.name).unwrap();
+            }
+
+            for instance in instances {
+                let connections = instance.connections.iter()
+                    .map(|(port, net)| format!(".{}({})", port.name, net.as_deref().unwrap_or_default()))
+                    .collect::<Vec<_>>();
+
+                writeln!(out, "    {} {} ({});", instance.module, instance.name, connections.join(", ")).unwrap();
+            }
+
+            for (port, net) in assigns {
+                writeln!(out, "    assign {} = {};", port, net).unwrap();
+            }
+        },
+        Body::Leaf(component) => leaf(out, module, component)?,
+    }
+
+    writeln!(out, "endmodule").unwrap();
+    Ok(())
+}
+
+fn range(width: u8) -> String {
+    match width {
+        1 => String::new(),
+        width => format!("[{}:0] ", width - 1),
+    }
+}
+
+fn bit(signal: &Signal, index: u8) -> String {
+    match signal.width {
+        1 => signal.name.clone(),
+        _ => format!("{}[{}]", signal.name, index),
+    }
+}
+
+// Ports packed into one vector the way simulation packs them, the first port in the least significant bits
+fn concat(signals: &[Signal]) -> String {
+    match signals {
+        [signal] => signal.name.clone(),
+        signals => format!("{{{}}}", signals.iter().rev().map(|signal| signal.name.as_str()).collect::<Vec<_>>().join(", ")),
+    }
+}
+
+// Writes the body of a module for a component that isn't made of other components
+fn leaf(out: &mut String, module: &Module, component: &Component) -> Result<()> {
+    let bits = |ports: &[Port]| ports.iter().map(|port| port.width as u32).sum::<u32>();
+    let (inputs, outputs) = (bits(&component.inputs), bits(&component.outputs));
+
+    let mut taken = module.taken(component)?;
+    let mut internal = |name: &str| interop::unique(name, &mut taken);
+
+    match &component.driver {
+        ComponentDriver::TruthTable { truth, dont_care } if inputs <= minimise::EXACT_LIMIT => {
+            let names = module.bits(component, bit);
+            let outputs = module.outputs.iter()
+                .flat_map(|signal| (0..signal.width).map(move |index| bit(signal, index)));
+
+            for (index, output) in outputs.enumerate() {
+                let expression = minimise::minimise(truth, dont_care, inputs, index as u8, Form::SumOfProducts, Port::bit_names(&component.inputs));
+                let expression = interop::expression(&expression.to_expr(), &OPERATORS, &|name| names[name].clone());
+
+                writeln!(out, "    assign {} = {};", output, expression).unwrap();
+            }
+        },
+        // Without outputs there is nothing to assign
+        ComponentDriver::TruthTable { .. } if outputs == 0 => {},
+        ComponentDriver::TruthTable { truth, .. } => {
+            let row = internal("row");
+            let mut keys = truth.keys().collect::<Vec<_>>();
+            keys.sort_unstable();
+
+            writeln!(out, "    reg {}{};", range(outputs as u8), row).unwrap();
+            writeln!(out, "    always @* begin\n        case ({})", concat(&module.inputs)).unwrap();
+
+            for key in keys {
+                writeln!(out, "            {}'d{}: {} = {}'d{};", inputs, key, row, outputs, truth[key]).unwrap();
+            }
+
+            // Missing rows output 0, as they do in simulation
+            writeln!(out, "            default: {} = {}'d0;\n        endcase\n    end", row, outputs).unwrap();
+            writeln!(out, "    assign {} = {};", concat(&module.outputs), row).unwrap();
+        },
+        ComponentDriver::Expression { equations } => {
+            let names = module.bits(component, bit);
+
+            for (name, output) in Port::bit_names(&component.outputs).iter().zip(module.outputs.iter().flat_map(|signal| (0..signal.width).map(move |index| bit(signal, index)))) {
+                let expression = match equations.iter().find(|equation| equation.output == *name) {
+                    Some(equation) => {
+                        if let Some(variable) = equation.expr.variables().into_iter().find(|variable| !names.contains_key(variable)) {
+                            return Err(CircuitError::PortMismatch { component: component.id, port: variable }.into());
+                        }
+
+                        interop::expression(&equation.expr, &OPERATORS, &|name| names[name].clone())
+                    },
+                    // Bits without an equation are X
+                    None => "1'bx".to_owned(),
+                };
+
+                writeln!(out, "    assign {} = {};", output, expression).unwrap();
+            }
+        },
+        ComponentDriver::Bdd { table } => {
+            if !table.is_valid(inputs as usize) {
+                return Err(CircuitError::InvalidBdd(component.id).into());
+            }
+
+            let input = module.inputs.iter()
+                .flat_map(|signal| (0..signal.width).map(move |index| bit(signal, index)))
+                .collect::<Vec<_>>();
+            let mut nodes = vec![OPERATORS.constants[0].to_owned(), OPERATORS.constants[1].to_owned()];
+
+            for (variable, low, high) in table.nodes.iter() {
+                let node = internal(&format!("b{}", nodes.len()));
+                writeln!(out, "    wire {} = {} ? {} : {};", node, input[*variable as usize], nodes[*high], nodes[*low]).unwrap();
+                nodes.push(node);
+            }
+
+            let outputs = module.outputs.iter()
+                .flat_map(|signal| (0..signal.width).map(move |index| bit(signal, index)));
+
+            for (output, root) in outputs.zip(table.outputs.iter()) {
+                writeln!(out, "    assign {} = {};", output, nodes[*root]).unwrap();
+            }
+        },
+        ComponentDriver::FlipFlop { kind } => {
+            let state = internal("state");
+            let data = |index: usize| module.inputs[index].name.as_str();
+            let clock = &module.inputs[module.inputs.len() - 1].name;
+
+            let next = match kind {
+                FlipFlopKind::D => data(0).to_owned(),
+                FlipFlopKind::JK => format!("{j} & ~{q} | ~{k} & {q}", j = data(0), k = data(1), q = state),
+                FlipFlopKind::T => format!("{} ^ {}", data(0), state),
+            };
+
+            writeln!(out, "    reg {}{};", range(module.outputs[0].width), state).unwrap();
+            writeln!(out, "    always @(posedge {}) {} <= {};", clock, state, next).unwrap();
+            module.stored(&state, &OPERATORS, |output, value| writeln!(out, "    assign {} = {};", output, value).unwrap());
+        },
+        ComponentDriver::Latch { kind } => {
+            let state = internal("state");
+            let data = |index: usize| module.inputs[index].name.as_str();
+
+            writeln!(out, "    reg {}{};", range(module.outputs[0].width), state).unwrap();
+
+            match kind {
+                LatchKind::D => writeln!(out, "    always @* if ({}) {} = {};", data(1), state, data(0)),
+                LatchKind::SR => writeln!(out, "    always @* {q} = {s} | {q} & ~{r};", q = state, s = data(0), r = data(1)),
+            }.unwrap();
+
+            module.stored(&state, &OPERATORS, |output, value| writeln!(out, "    assign {} = {};", output, value).unwrap());
+        },
+        ComponentDriver::Memory { kind: MemoryKind::Rom, address, data, contents, .. } => {
+            let word = internal("word");
+
+            writeln!(out, "    reg {}{};", range(*data), word).unwrap();
+            writeln!(out, "    always @* begin\n        case ({})", module.inputs[0].name).unwrap();
+
+            for (index, value) in contents.iter().enumerate() {
+                writeln!(out, "            {}'d{}: {} = {}'d{};", address, index, word, data, value).unwrap();
+            }
+
+            writeln!(out, "            default: {} = {}'d0;\n        endcase\n    end", word, data).unwrap();
+
+            // The output floats while the read enable is low
+            writeln!(out, "    assign {} = {} ? {} : {{{}{{1'bz}}}};", module.outputs[0].name, module.inputs[1].name, word, data).unwrap();
+        },
+        _ => return Err(ExportError::Unsupported(component.id).into()),
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Identifier(String),
+    Number { width: Option<u32>, value: u64 },
+    Symbol(String),
+}
+
+// Splits source text into tokens, each with the line it starts on. Comments, attributes and compiler directives are skipped.
+fn tokenise(source: &str) -> Result<Vec<(Token, usize)>> {
+    let chars = source.chars().collect::<Vec<_>>();
+    let mut tokens = vec![];
+    let mut line = 1;
+    let mut index = 0;
+
+    let error = |line: usize, message: &str| ImportError::Syntax { line, message: message.to_owned() };
+
+    while index < chars.len() {
+        let c = chars[index];
+        let rest = |pattern: &str| chars[index..].iter().take(pattern.len()).copied().eq(pattern.chars());
+
+        // Skips up to and including `end`, counting lines
+        let mut skip = |index: &mut usize, end: &str| {
+            while *index < chars.len() && !chars[*index..].iter().take(end.len()).copied().eq(end.chars()) {
+                line += (chars[*index] == '\n') as usize;
+                *index += 1;
+            }
+
+            *index += end.len();
+        };
+
+        if c.is_whitespace() {
+            line += (c == '\n') as usize;
+            index += 1;
+        } else if rest("//") || c == '`' {
+            skip(&mut index, "\n");
+            line += 1;
+        } else if rest("/*") {
+            skip(&mut index, "*/");
+        } else if rest("(*") && !rest("(*)") {
+            skip(&mut index, "*)");
+        } else if c.is_ascii_alphabetic() || c == '_' || c == '\\' {
+            let start = index;
+            index += 1;
+
+            let escaped = c == '\\';
+            while index < chars.len() && match escaped {
+                true => !chars[index].is_whitespace(),
+                false => chars[index].is_ascii_alphanumeric() || chars[index] == '_' || chars[index] == '$',
+            } {
+                index += 1;
+            }
+
+            tokens.push((Token::Identifier(chars[start + escaped as usize..index].iter().collect()), line));
+        } else if c.is_ascii_digit() || c == '\'' {
+            let digits = |index: &mut usize, valid: &dyn Fn(char) -> bool| {
+                let start = *index;
+                while *index < chars.len() && (valid(chars[*index]) || chars[*index] == '_') {
+                    *index += 1;
+                }
+
+                chars[start..*index].iter().filter(|c| **c != '_').collect::<String>()
+            };
+
+            let size = digits(&mut index, &|c| c.is_ascii_digit());
+
+            // Sizes and bases may be separated by spaces
+            let mut lookahead = index;
+            while lookahead < chars.len() && chars[lookahead] == ' ' {
+                lookahead += 1;
+            }
+
+            if lookahead < chars.len() && chars[lookahead] == '\'' {
+                index = lookahead + 1;
+
+                if index < chars.len() && matches!(chars[index], 's' | 'S') {
+                    index += 1;
+                }
+
+                let radix = match chars.get(index).map(|c| c.to_ascii_lowercase()) {
+                    Some('b') => 2,
+                    Some('o') => 8,
+                    Some('d') => 10,
+                    Some('h') => 16,
+                    _ => return Err(error(line, "Expected a base after '").into()),
+                };
+                index += 1;
+
+                while index < chars.len() && chars[index] == ' ' {
+                    index += 1;
+                }
+
+                let value = digits(&mut index, &|c| c.is_ascii_alphanumeric() || c == '?');
+
+                if value.chars().any(|c| matches!(c.to_ascii_lowercase(), 'x' | 'z' | '?')) {
+                    return Err(error(line, "Unknown and floating bits in constants aren't supported").into());
+                }
+
+                tokens.push((Token::Number {
+                    width: match size.is_empty() {
+                        true => None,
+                        false => Some(size.parse().map_err(|_| error(line, "Invalid size"))?),
+                    },
+                    value: u64::from_str_radix(&value, radix).map_err(|_| error(line, "Invalid number"))?,
+                }, line));
+            } else {
+                tokens.push((Token::Number { width: None, value: size.parse().map_err(|_| error(line, "Invalid number"))? }, line));
+            }
+        } else {
+            let symbol = ["~^", "^~", "~&", "~|", "&&", "||"].into_iter()
+                .find(|symbol| rest(symbol))
+                .map(str::to_owned)
+                .unwrap_or_else(|| c.to_string());
+
+            index += symbol.len();
+            tokens.push((Token::Symbol(symbol), line));
+        }
+    }
+
+    Ok(tokens)
+}
+
+// An expression as written in Verilog, before it is split into bits
+#[derive(Debug, Clone)]
+enum VExpr {
+    Signal(String),
+    Bit(String, i64),
+    Constant(u64),
+    Not(Box<VExpr>),
+    And(Box<VExpr>, Box<VExpr>),
+    Or(Box<VExpr>, Box<VExpr>),
+    Xor(Box<VExpr>, Box<VExpr>),
+    Mux(Box<VExpr>, Box<VExpr>, Box<VExpr>),
+}
+
+// The bits of a signal, written `[msb:lsb]`. The least significant bit is bit 0 of the port.
+#[derive(Debug, Copy, Clone)]
+struct Range {
+    msb: i64,
+    lsb: i64,
+}
+
+impl Range {
+    const BIT: Range = Range { msb: 0, lsb: 0 };
+
+    fn width(&self) -> u8 {
+        (self.msb - self.lsb).unsigned_abs() as u8 + 1
+    }
+
+    fn offset(&self, index: i64) -> Option<u8> {
+        let offset = match self.msb >= self.lsb {
+            true => index - self.lsb,
+            false => self.lsb - index,
+        };
+
+        (0..self.width() as i64).contains(&offset).then_some(offset as u8)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Direction {
+    Input,
+    Output,
+    Wire,
+}
+
+struct VModule {
+    name: String,
+    line: usize,
+    ports: Vec<String>,
+    signals: HashMap<String, (Direction, Range)>,
+    statements: Vec<Statement>,
+}
+
+impl VModule {
+    fn ports(&self, direction: Direction) -> Vec<Port> {
+        self.ports.iter()
+            .filter(|port| self.signals[*port].0 == direction)
+            .map(|port| Port::new(port.as_str(), self.signals[port].1.width()))
+            .collect()
+    }
+}
+
+enum Statement {
+    Assign { target: VExpr, expr: VExpr, line: usize },
+    // Terminals of a primitive gate, outputs first
+    Gate { kind: String, delay: Option<u64>, terminals: Vec<VExpr>, line: usize },
+    Instance { module: String, name: String, connections: Connections, line: usize },
+}
+
+enum Connections {
+    Ordered(Vec<Option<VExpr>>),
+    Named(Vec<(String, Option<VExpr>)>),
+}
+
+const GATES: &[&str] = &["and", "or", "xor", "nand", "nor", "xnor", "not", "buf"];
+
+// How many levels of nesting a pair of parentheses counts for
+const PARENTHESES: usize = 4;
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    // How many levels deep the expression being parsed is nested
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens.get(self.position)
+            .or(self.tokens.last())
+            .map(|(_, line)| *line)
+            .unwrap_or(1)
+    }
+
+    fn error(&self, message: impl Into<String>) -> crate::Error {
+        ImportError::Syntax { line: self.line(), message: message.into() }.into()
+    }
+
+    fn next(&mut self) -> Result<Token> {
+        let token = self.peek().cloned().ok_or_else(|| self.error("Unexpected end of file"))?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn is(&self, symbol: &str) -> bool {
+        matches!(self.peek(), Some(Token::Symbol(s)) if s == symbol)
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Identifier(s)) if s == keyword)
+    }
+
+    fn eat(&mut self, symbol: &str) -> bool {
+        let is = self.is(symbol);
+        self.position += is as usize;
+        is
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<()> {
+        match self.eat(symbol) {
+            true => Ok(()),
+            false => Err(self.error(format!("Expected '{}'", symbol))),
+        }
+    }
+
+    fn identifier(&mut self) -> Result<String> {
+        match self.next()? {
+            Token::Identifier(name) => Ok(name),
+            _ => {
+                self.position -= 1;
+                Err(self.error("Expected an identifier"))
+            },
+        }
+    }
+
+    fn number(&mut self) -> Result<i64> {
+        let negative = self.eat("-");
+
+        match self.next()? {
+            Token::Number { value, .. } => Ok(match negative {
+                true => -(value as i64),
+                false => value as i64,
+            }),
+            _ => {
+                self.position -= 1;
+                Err(self.error("Expected a number"))
+            },
+        }
+    }
+
+    fn modules(&mut self) -> Result<Vec<VModule>> {
+        let mut modules = vec![];
+
+        while self.peek().is_some() {
+            match self.identifier()?.as_str() {
+                "module" | "macromodule" => modules.push(self.module()?),
+                keyword => return Err(self.error(format!("Expected a module, found '{}'", keyword))),
+            }
+        }
+
+        Ok(modules)
+    }
+
+    fn module(&mut self) -> Result<VModule> {
+        let mut module = VModule {
+            line: self.line(),
+            name: self.identifier()?,
+            ports: vec![],
+            signals: HashMap::new(),
+            statements: vec![],
+        };
+
+        if self.is("#") {
+            return Err(self.error("Parameters aren't supported"));
+        }
+
+        if self.eat("(") && !self.eat(")") {
+            // Ports declared in the header carry their direction until the next one
+            let mut direction = None;
+
+            loop {
+                if let Some(next) = self.direction() {
+                    let range = self.declaration_type()?;
+                    direction = Some((next, range));
+                }
+
+                let name = self.identifier()?;
+
+                if let Some((direction, range)) = direction {
+                    self.declare(&mut module, &name, direction, Some(range))?;
+                }
+
+                module.ports.push(name);
+
+                if !self.eat(",") {
+                    break;
+                }
+            }
+
+            self.expect(")")?;
+        }
+
+        self.expect(";")?;
+
+        loop {
+            let line = self.line();
+            let keyword = self.identifier()?;
+
+            match keyword.as_str() {
+                "endmodule" => break,
+                "input" | "output" | "wire" | "tri" => {
+                    let direction = match keyword.as_str() {
+                        "input" => Direction::Input,
+                        "output" => Direction::Output,
+                        _ => Direction::Wire,
+                    };
+                    let range = self.declaration_type()?;
+
+                    loop {
+                        let name = self.identifier()?;
+                        self.declare(&mut module, &name, direction, Some(range))?;
+
+                        // Net declaration assignments
+                        if self.eat("=") {
+                            let expr = self.expression()?;
+                            module.statements.push(Statement::Assign { target: VExpr::Signal(name), expr, line });
+                        }
+
+                        if !self.eat(",") {
+                            break;
+                        }
+                    }
+
+                    self.expect(";")?;
+                },
+                "assign" => {
+                    loop {
+                        let target = self.primary()?;
+                        self.expect("=")?;
+                        let expr = self.expression()?;
+                        module.statements.push(Statement::Assign { target, expr, line });
+
+                        if !self.eat(",") {
+                            break;
+                        }
+                    }
+
+                    self.expect(";")?;
+                },
+                gate if GATES.contains(&gate) => {
+                    let delay = match self.eat("#") {
+                        true => Some(self.number()? as u64),
+                        false => None,
+                    };
+
+                    loop {
+                        // Gate instances may go unnamed
+                        if !self.is("(") {
+                            self.identifier()?;
+                        }
+
+                        self.expect("(")?;
+                        let mut terminals = vec![self.expression()?];
+                        while self.eat(",") {
+                            terminals.push(self.expression()?);
+                        }
+                        self.expect(")")?;
+
+                        if terminals.len() < 2 {
+                            return Err(self.error(format!("'{}' needs an output and at least one input", gate)));
+                        }
+
+                        module.statements.push(Statement::Gate { kind: gate.to_owned(), delay, terminals, line });
+
+                        if !self.eat(",") {
+                            break;
+                        }
+                    }
+
+                    self.expect(";")?;
+                },
+                "inout" | "reg" | "always" | "initial" | "parameter" | "localparam" | "function" | "task" | "generate" | "genvar" | "integer" => {
+                    self.position -= 1;
+                    return Err(self.error(format!("'{}' isn't supported", keyword)));
+                },
+                _ => {
+                    if self.is("#") {
+                        return Err(self.error("Parameters aren't supported"));
+                    }
+
+                    loop {
+                        let name = self.identifier()?;
+                        let connections = self.connections()?;
+                        module.statements.push(Statement::Instance { module: keyword.clone(), name, connections, line });
+
+                        if !self.eat(",") {
+                            break;
+                        }
+                    }
+
+                    self.expect(";")?;
+                },
+            }
+        }
+
+        if let Some(port) = module.ports.iter().find(|port| !module.signals.get(*port).is_some_and(|(direction, _)| *direction != Direction::Wire)) {
+            return Err(self.error(format!("Port '{}' has no direction", port)));
+        }
+
+        Ok(module)
+    }
+
+    fn direction(&mut self) -> Option<Direction> {
+        let direction = match self.peek() {
+            Some(Token::Identifier(keyword)) if keyword == "input" => Direction::Input,
+            Some(Token::Identifier(keyword)) if keyword == "output" => Direction::Output,
+            _ => return None,
+        };
+
+        self.position += 1;
+        Some(direction)
+    }
+
+    // The optional net type and range after a direction
+    fn declaration_type(&mut self) -> Result<Range> {
+        if self.is_keyword("reg") || self.is_keyword("inout") {
+            return Err(self.error("Registers and bidirectional ports aren't supported"));
+        }
+
+        while self.is_keyword("wire") || self.is_keyword("tri") || self.is_keyword("signed") {
+            self.position += 1;
+        }
+
+        if !self.eat("[") {
+            return Ok(Range::BIT);
+        }
+
+        let msb = self.number()?;
+        self.expect(":")?;
+        let lsb = self.number()?;
+        self.expect("]")?;
+
+        match (msb - lsb).abs() < 64 {
+            true => Ok(Range { msb, lsb }),
+            false => Err(self.error("Signals can be at most 64 bits wide")),
+        }
+    }
+
+    // Directions and ranges may be given in more than one declaration of the same signal
+    fn declare(&self, module: &mut VModule, name: &str, direction: Direction, range: Option<Range>) -> Result<()> {
+        let (existing, existing_range) = module.signals.get(name).copied().unwrap_or((Direction::Wire, Range::BIT));
+
+        let direction = match (existing, direction) {
+            (existing, Direction::Wire) => existing,
+            (Direction::Wire, direction) => direction,
+            _ => return Err(self.error(format!("'{}' is declared twice", name))),
+        };
+        let range = match range {
+            Some(range) if range.width() > 1 => range,
+            _ => existing_range,
+        };
+
+        module.signals.insert(name.to_owned(), (direction, range));
+        Ok(())
+    }
+
+    fn connections(&mut self) -> Result<Connections> {
+        self.expect("(")?;
+
+        if self.is(".") {
+            let mut named = vec![];
+
+            loop {
+                self.expect(".")?;
+                let port = self.identifier()?;
+                self.expect("(")?;
+                let expr = match self.is(")") {
+                    true => None,
+                    false => Some(self.expression()?),
+                };
+                self.expect(")")?;
+                named.push((port, expr));
+
+                if !self.eat(",") {
+                    break;
+                }
+            }
+
+            self.expect(")")?;
+            return Ok(Connections::Named(named));
+        }
+
+        let mut ordered = vec![];
+
+        if !self.is(")") {
+            loop {
+                ordered.push(match self.is(",") || self.is(")") {
+                    true => None,
+                    false => Some(self.expression()?),
+                });
+
+                if !self.eat(",") {
+                    break;
+                }
+            }
+        }
+
+        self.expect(")")?;
+        Ok(Connections::Ordered(ordered))
+    }
+
+    // Goes `levels` deeper, failing once past the nesting limit of expressions. Callers step back out by lowering `self.depth`.
+    fn nest(&mut self, levels: usize) -> Result<()> {
+        self.depth += levels;
+
+        match self.depth > NESTING_LIMIT {
+            true => Err(self.error(format!("Nested more than {} levels deep", NESTING_LIMIT))),
+            false => Ok(()),
+        }
+    }
+
+    // Conditional, then |, then ^, then &, binding tighter in that order
+    fn expression(&mut self) -> Result<VExpr> {
+        let condition = self.binary(0)?;
+
+        if !self.eat("?") {
+            return Ok(condition);
+        }
+
+        self.nest(1)?;
+        let then = self.expression()?;
+        self.expect(":")?;
+        let otherwise = self.expression()?;
+        self.depth -= 1;
+
+        Ok(VExpr::Mux(Box::new(condition), Box::new(then), Box::new(otherwise)))
+    }
+
+    fn binary(&mut self, level: u8) -> Result<VExpr> {
+        if level == 3 {
+            return self.unary();
+        }
+
+        let mut lhs = self.binary(level + 1)?;
+        let mut levels = 0;
+
+        // Each operator in a chain like `a | b | c` nests the expression one level deeper
+        loop {
+            let operator = match level {
+                0 if self.eat("|") => VExpr::Or as fn(_, _) -> _,
+                1 if self.eat("^") => VExpr::Xor,
+                1 if self.eat("~^") || self.eat("^~") => |lhs, rhs| VExpr::Not(Box::new(VExpr::Xor(lhs, rhs))),
+                2 if self.eat("&") => VExpr::And,
+                _ if self.is("&&") || self.is("||") => return Err(self.error("Logical operators aren't supported")),
+                _ => break,
+            };
+
+            self.nest(1)?;
+            levels += 1;
+
+            let rhs = self.binary(level + 1)?;
+            lhs = operator(Box::new(lhs), Box::new(rhs));
+        }
+
+        self.depth -= levels;
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<VExpr> {
+        if self.eat("~") || self.eat("!") {
+            self.nest(1)?;
+            let inner = self.unary()?;
+            self.depth -= 1;
+
+            return Ok(VExpr::Not(Box::new(inner)));
+        }
+
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<VExpr> {
+        match self.next()? {
+            // Parsing inside parentheses recurses through every level of precedence again, so they count for more than an operator
+            Token::Symbol(symbol) if symbol == "(" => {
+                self.nest(PARENTHESES)?;
+                let expr = self.expression()?;
+                self.depth -= PARENTHESES;
+
+                self.expect(")")?;
+                Ok(expr)
+            },
+            Token::Number { value, .. } => Ok(VExpr::Constant(value)),
+            Token::Identifier(name) => match self.eat("[") {
+                true => {
+                    let index = self.number()?;
+
+                    if self.is(":") {
+                        return Err(self.error("Part selects aren't supported"));
+                    }
+
+                    self.expect("]")?;
+                    Ok(VExpr::Bit(name, index))
+                },
+                false => Ok(VExpr::Signal(name)),
+            },
+            Token::Symbol(symbol) => {
+                self.position -= 1;
+                Err(self.error(format!("Unexpected '{}'", symbol)))
+            },
+        }
+    }
+}
+
+/// Reads the modules in some Verilog into a project, each one a component, with the last module that no other module instantiates
+/// as the top level. Only ports, wires, continuous assignments, primitive gates and module instances are understood.
+///
+/// Assignments become expression components, and `and`, `or` and `not` gates become the built-in gates wherever they fit.
+/// Everything is laid out in columns running from the inputs to the outputs.
+pub fn import(source: &str) -> Result<Project> {
+    let modules = Parser { tokens: tokenise(source)?, position: 0, depth: 0 }.modules()?;
+
+    let mut project = Project::empty();
+    project.body.clear();
+    project.connections.clear();
+    project.wires.clear();
+
+    // Modules may be instantiated before they are defined, so every one gets its component up front
+    let mut components = HashMap::new();
+
+    for module in modules.iter() {
+        let id = interop::next_component(&project);
+
+        if components.insert(module.name.clone(), (id, module)).is_some() {
+            return Err(ImportError::Syntax { line: module.line, message: format!("Module '{}' is defined twice", module.name) }.into());
+        }
+
+        project.components.insert(id, Component {
+            id,
+            name: module.name.clone(),
+            inputs: module.ports(Direction::Input),
+            outputs: module.ports(Direction::Output),
+            driver: Schematic::default().into_driver(),
+            delay: 1,
+        });
+    }
+
+    let instantiated = modules.iter()
+        .flat_map(|module| module.statements.iter())
+        .filter_map(|statement| match statement {
+            Statement::Instance { module, .. } => Some(module.as_str()),
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+    let top = modules.iter()
+        .rev()
+        .find(|module| !instantiated.contains(module.name.as_str()))
+        .or(modules.last())
+        .map(|module| module.name.clone());
+
+    let mut gates = HashMap::new();
+
+    for module in modules.iter() {
+        let schematic = Importer {
+            project: &mut project,
+            components: &components,
+            gates: &mut gates,
+            module,
+            signals: module.signals.clone(),
+            schematic: Schematic::default(),
+            drivers: HashMap::new(),
+            reads: vec![],
+            bits: HashMap::new(),
+            order: vec![],
+        }.build()?;
+
+        if top.as_ref() == Some(&module.name) {
+            project.body = schematic.body.clone();
+            project.connections = schematic.connections.clone();
+            project.wires = schematic.wires.clone();
+        }
+
+        if let Some(component) = project.components.get_mut(&components[&module.name].0) {
+            component.driver = schematic.into_driver();
+        }
+    }
+
+    interop::acyclic(&project)?;
+    Ok(project)
+}
+
+// Builds the body of one module
+struct Importer<'a> {
+    project: &'a mut Project,
+    components: &'a HashMap<String, (ComponentId, &'a VModule)>,
+    // Expression components standing in for primitive gates, by kind, number of inputs, number of outputs and width
+    gates: &'a mut HashMap<(String, usize, usize, u8), ComponentId>,
+    module: &'a VModule,
+    // The module's signals, along with any nets declared implicitly by connecting to them
+    signals: HashMap<String, (Direction, Range)>,
+    schematic: Schematic,
+    // The output terminal driving each signal
+    drivers: HashMap<String, Connection>,
+    // The input terminals reading each signal, connected up once every driver is known
+    reads: Vec<(String, Connection)>,
+    // Signals assigned or driven one bit at a time, with what each bit is set to
+    bits: HashMap<String, Vec<(u8, VExpr)>>,
+    // The signals set one bit at a time in the order they first appear, with the line they first appear on
+    order: Vec<(String, usize)>,
+}
+
+impl Importer<'_> {
+    fn build(mut self) -> Result<Schematic> {
+        for port in self.module.ports.iter() {
+            let (direction, range) = self.signals[port];
+            let component = interop::port_component(self.project, direction == Direction::Input, range.width());
+            let instance = self.schematic.place(component, Some(port.clone()));
+
+            match direction {
+                Direction::Input => self.drivers.insert(port.clone(), Connection::output(instance, 0)),
+                _ => {
+                    self.reads.push((port.clone(), Connection::input(instance, 0)));
+                    None
+                },
+            };
+        }
+
+        for statement in self.module.statements.iter() {
+            match statement {
+                Statement::Assign { target: VExpr::Signal(name), expr, line } => {
+                    let width = self.range(name)?.width();
+                    let bits = (0..width).map(|bit| (bit, bit, expr)).collect();
+                    let instance = self.expression(bits, name, width, format!("{}.{}", self.module.name, name))?;
+
+                    self.drive(&VExpr::Signal(name.clone()), Connection::output(instance, 0), width, *line)?;
+                },
+                Statement::Assign { target: VExpr::Bit(name, index), expr, line } => self.assign(name, *index, expr.clone(), *line)?,
+                Statement::Assign { line, .. } => {
+                    return Err(ImportError::Syntax { line: *line, message: "Only signals and their bits can be assigned to".to_owned() }.into());
+                },
+                Statement::Gate { kind, delay, terminals, line } => self.gate(kind, *delay, terminals, *line)?,
+                Statement::Instance { module, name, connections, line } => self.instance(module, name, connections, *line)?,
+            }
+        }
+
+        // All the bits set one at a time make up one expression component per signal
+        for (name, line) in std::mem::take(&mut self.order) {
+            let width = self.range(&name)?.width();
+            let bits = self.bits.remove(&name).unwrap_or_default();
+            let bits = bits.iter().map(|(offset, expr)| (*offset, 0, expr)).collect();
+            let instance = self.expression(bits, &name, width, format!("{}.{}", self.module.name, name))?;
+
+            self.drive(&VExpr::Signal(name), Connection::output(instance, 0), width, line)?;
+        }
+
+        // Signals nothing drives are left floating
+        for (signal, input) in std::mem::take(&mut self.reads) {
+            if let Some(output) = self.drivers.get(&signal) {
+                self.schematic.connect(*output, input);
+            }
+        }
+
+        self.schematic.lay_out();
+        Ok(self.schematic)
+    }
+
+    fn range(&self, signal: &str) -> Result<Range> {
+        self.signals.get(signal)
+            .map(|(_, range)| *range)
+            .ok_or(ImportError::UnknownSignal { module: self.module.name.clone(), signal: signal.to_owned() }.into())
+    }
+
+    fn width(&self, expr: &VExpr) -> Result<u8> {
+        Ok(match expr {
+            VExpr::Signal(name) => self.range(name)?.width(),
+            VExpr::Bit(..) => 1,
+            VExpr::Constant(_) => 64,
+            VExpr::Not(inner) => self.width(inner)?,
+            VExpr::And(lhs, rhs) | VExpr::Or(lhs, rhs) | VExpr::Xor(lhs, rhs) | VExpr::Mux(_, lhs, rhs) => self.width(lhs)?.max(self.width(rhs)?),
+        })
+    }
+
+    // One bit of an expression in terms of the bits of the signals it reads, which are zero-extended to its width.
+    // It recurses once per level of the expression, which the parser keeps within the nesting limit.
+    fn bit(&self, expr: &VExpr, index: u8) -> Result<Expr> {
+        let name = |signal: &str, range: Range, bit: u8| Expr::Variable(match range.width() {
+            1 => signal.to_owned(),
+            _ => format!("{}[{}]", signal, bit),
+        });
+        let binary = |lhs: &VExpr, rhs: &VExpr| Ok::<_, crate::Error>((Box::new(self.bit(lhs, index)?), Box::new(self.bit(rhs, index)?)));
+
+        Ok(match expr {
+            VExpr::Signal(signal) => match self.range(signal)? {
+                range if index < range.width() => name(signal, range, index),
+                _ => Expr::Constant(false),
+            },
+            VExpr::Bit(signal, bit) => {
+                let range = self.range(signal)?;
+                let offset = range.offset(*bit)
+                    .ok_or(ImportError::WidthMismatch { module: self.module.name.clone(), signal: signal.clone() })?;
+
+                match index {
+                    0 => name(signal, range, offset),
+                    _ => Expr::Constant(false),
+                }
+            },
+            VExpr::Constant(value) => Expr::Constant(index < 64 && value >> index & 1 == 1),
+            VExpr::Not(inner) => Expr::Not(Box::new(self.bit(inner, index)?)),
+            VExpr::And(lhs, rhs) => {
+                let (lhs, rhs) = binary(lhs, rhs)?;
+                Expr::And(lhs, rhs)
+            },
+            VExpr::Or(lhs, rhs) => {
+                let (lhs, rhs) = binary(lhs, rhs)?;
+                Expr::Or(lhs, rhs)
+            },
+            VExpr::Xor(lhs, rhs) => {
+                let (lhs, rhs) = binary(lhs, rhs)?;
+                Expr::Xor(lhs, rhs)
+            },
+            VExpr::Mux(condition, then, otherwise) => {
+                // Any bit of the condition being set selects the first branch
+                let condition = (0..self.width(condition)?)
+                    .map(|bit| self.bit(condition, bit))
+                    .reduce(|lhs, rhs| Ok(Expr::Or(Box::new(lhs?), Box::new(rhs?))))
+                    .unwrap_or(Ok(Expr::Constant(false)))?;
+                let (then, otherwise) = binary(then, otherwise)?;
+
+                Expr::Or(
+                    Box::new(Expr::And(Box::new(condition.clone()), then)),
+                    Box::new(Expr::And(Box::new(Expr::Not(Box::new(condition))), otherwise)),
+                )
+            },
+        })
+    }
+
+    // The signals an expression reads, in the order they first appear
+    fn signals(expr: &VExpr, signals: &mut Vec<String>) {
+        match expr {
+            VExpr::Signal(name) | VExpr::Bit(name, _) => if !signals.contains(name) {
+                signals.push(name.clone());
+            },
+            VExpr::Constant(_) => {},
+            VExpr::Not(inner) => Self::signals(inner, signals),
+            VExpr::And(lhs, rhs) | VExpr::Or(lhs, rhs) | VExpr::Xor(lhs, rhs) => {
+                Self::signals(lhs, signals);
+                Self::signals(rhs, signals);
+            },
+            VExpr::Mux(condition, then, otherwise) => {
+                Self::signals(condition, signals);
+                Self::signals(then, signals);
+                Self::signals(otherwise, signals);
+            },
+        }
+    }
+
+    // Places a new expression component with the output port `output`, reading each signal on a port of the same name.
+    // Each of `bits` sets an output bit to a bit of an expression. Output bits left out are X.
+    fn expression(&mut self, bits: Vec<(u8, u8, &VExpr)>, output: &str, width: u8, name: String) -> Result<InstanceId> {
+        let mut signals = vec![];
+        for (_, _, expr) in bits.iter() {
+            Self::signals(expr, &mut signals);
+        }
+
+        let outputs = vec![Port::new(output, width)];
+        let names = Port::bit_names(&outputs);
+        let equations = bits.into_iter()
+            .map(|(output, bit, expr)| Ok(Equation { output: names[output as usize].clone(), expr: self.bit(expr, bit)?.simplify() }))
+            .collect::<Result<Vec<_>>>()?;
+
+        let id = interop::next_component(self.project);
+        self.project.components.insert(id, Component {
+            id,
+            name,
+            inputs: signals.iter()
+                .map(|signal| Ok(Port::new(signal.as_str(), self.range(signal)?.width())))
+                .collect::<Result<_>>()?,
+            outputs,
+            driver: ComponentDriver::Expression { equations },
+            delay: 1,
+        });
+
+        let instance = self.schematic.place(id, None);
+
+        for (terminal, signal) in signals.into_iter().enumerate() {
+            self.reads.push((signal, Connection::input(instance, terminal as u64)));
+        }
+
+        Ok(instance)
+    }
+
+    // Signals only appearing in connections are implicitly declared as wires
+    fn implicit(&mut self, expr: &VExpr, width: u8) {
+        if let VExpr::Signal(name) = expr {
+            self.signals.entry(name.clone())
+                .or_insert((Direction::Wire, Range { msb: width as i64 - 1, lsb: 0 }));
+        }
+    }
+
+    // Connects an expression to an input terminal, through an expression component unless it is a signal of the right width
+    fn read(&mut self, expr: &VExpr, width: u8, input: Connection) -> Result<()> {
+        self.implicit(expr, width);
+
+        match expr {
+            VExpr::Signal(name) if self.range(name)?.width() == width => self.reads.push((name.clone(), input)),
+            _ => {
+                let bits = (0..width).map(|bit| (bit, bit, expr)).collect();
+                let instance = self.expression(bits, "y", width, format!("{}.expression", self.module.name))?;
+                self.schematic.connect(Connection::output(instance, 0), input);
+            },
+        }
+
+        Ok(())
+    }
+
+    // Sets one bit of a signal, which is joined up with the signal's other bits once they are all known
+    fn assign(&mut self, name: &str, index: i64, expr: VExpr, line: usize) -> Result<()> {
+        let offset = self.range(name)?.offset(index)
+            .ok_or(ImportError::WidthMismatch { module: self.module.name.clone(), signal: name.to_owned() })?;
+
+        if !self.bits.contains_key(name) {
+            self.order.push((name.to_owned(), line));
+        }
+
+        let bits = self.bits.entry(name.to_owned()).or_default();
+
+        if bits.iter().any(|(bit, _)| *bit == offset) {
+            return Err(ImportError::MultipleDrivers { module: self.module.name.clone(), signal: format!("{}[{}]", name, index) }.into());
+        }
+
+        bits.push((offset, expr));
+        Ok(())
+    }
+
+    fn drive(&mut self, expr: &VExpr, output: Connection, width: u8, line: usize) -> Result<()> {
+        self.implicit(expr, width);
+
+        // A bit is driven through a wire of its own, which then sets the bit like an assignment would
+        if let VExpr::Bit(name, index) = expr {
+            if width != 1 {
+                return Err(ImportError::WidthMismatch { module: self.module.name.clone(), signal: name.clone() }.into());
+            }
+
+            let mut wire = format!("{}_{}", name, index.unsigned_abs());
+            while self.signals.contains_key(&wire) {
+                wire.push('_');
+            }
+            self.signals.insert(wire.clone(), (Direction::Wire, Range::BIT));
+
+            self.assign(name, *index, VExpr::Signal(wire.clone()), line)?;
+            return self.drive(&VExpr::Signal(wire), output, width, line);
+        }
+
+        let VExpr::Signal(name) = expr else {
+            return Err(ImportError::Syntax { line, message: "Only signals and their bits can be driven".to_owned() }.into());
+        };
+
+        let multiple = ImportError::MultipleDrivers { module: self.module.name.clone(), signal: name.clone() };
+
+        match self.signals[name] {
+            (Direction::Input, _) => return Err(multiple.into()),
+            (_, range) if range.width() != width => return Err(ImportError::WidthMismatch { module: self.module.name.clone(), signal: name.clone() }.into()),
+            _ => {},
+        }
+
+        match self.drivers.insert(name.clone(), output) {
+            Some(_) => Err(multiple.into()),
+            None => Ok(()),
+        }
+    }
+
+    fn gate(&mut self, kind: &str, delay: Option<u64>, terminals: &[VExpr], line: usize) -> Result<()> {
+        // Buffers and inverters may drive several outputs from their last terminal, while other gates drive their first
+        let split = match kind {
+            "not" | "buf" => terminals.len() - 1,
+            _ => 1,
+        };
+        let (outputs, inputs) = terminals.split_at(split);
+
+        let width = match &outputs[0] {
+            VExpr::Signal(name) => self.signals.get(name).map(|(_, range)| range.width()).unwrap_or(1),
+            _ => 1,
+        };
+
+        let component = match (kind, inputs.len(), outputs.len(), width) {
+            ("not", 1, 1, 1) => Project::NOT,
+            ("and", 2, 1, 1) => Project::AND,
+            ("or", 2, 1, 1) => Project::OR,
+            _ => self.gate_component(kind, inputs.len(), outputs.len(), width),
+        };
+
+        let instance = self.schematic.place(component, None);
+        if let Some(placement) = self.schematic.body.get_mut(&instance) {
+            placement.delay = delay;
+        }
+
+        for (terminal, output) in outputs.iter().enumerate() {
+            self.drive(output, Connection::output(instance, terminal as u64), width, line)?;
+        }
+
+        for (terminal, input) in inputs.iter().enumerate() {
+            self.read(input, width, Connection::input(instance, terminal as u64))?;
+        }
+
+        Ok(())
+    }
+
+    // An expression component acting as a gate the built-in ones can't stand in for
+    fn gate_component(&mut self, kind: &str, inputs: usize, outputs: usize, width: u8) -> ComponentId {
+        let key = (kind.to_owned(), inputs, outputs, width);
+
+        if let Some(id) = self.gates.get(&key) {
+            return *id;
+        }
+
+        let names = (0..inputs)
+            .map(interop::input_name)
+            .collect::<Vec<_>>();
+        let output_ports = (0..outputs)
+            .map(|output| match outputs {
+                1 => Port::new("y", width),
+                _ => Port::new(format!("y{}", output), width),
+            })
+            .collect::<Vec<_>>();
+
+        let input_ports = names.iter()
+            .map(|name| Port::new(name.as_str(), width))
+            .collect::<Vec<_>>();
+        let bits = |port: &Port| Port::bit_names(std::slice::from_ref(port));
+
+        let equations = (0..width as usize)
+            .flat_map(|bit| output_ports.iter().map(move |port| (bit, port)))
+            .map(|(bit, port)| {
+                let operands = input_ports.iter().map(|input| Expr::Variable(bits(input)[bit].clone()));
+                let combine = |op: fn(Box<Expr>, Box<Expr>) -> Expr| operands.clone()
+                    .reduce(|lhs, rhs| op(Box::new(lhs), Box::new(rhs)))
+                    .unwrap_or(Expr::Constant(false));
+
+                let expr = match kind {
+                    "and" | "nand" => combine(Expr::And),
+                    "or" | "nor" => combine(Expr::Or),
+                    "xor" | "xnor" => combine(Expr::Xor),
+                    _ => combine(Expr::And),
+                };
+                let expr = match kind {
+                    "nand" | "nor" | "xnor" | "not" => Expr::Not(Box::new(expr)),
+                    _ => expr,
+                };
+
+                Equation { output: bits(port)[bit].clone(), expr }
+            })
+            .collect();
+
+        let id = interop::next_component(self.project);
+        self.project.components.insert(id, Component {
+            id,
+            name: match (kind, inputs) {
+                ("not" | "buf", _) | (_, 2) => kind.to_owned(),
+                _ => format!("{}{}", kind, inputs),
+            },
+            inputs: input_ports,
+            outputs: output_ports,
+            driver: ComponentDriver::Expression { equations },
+            delay: 1,
+        });
+
+        self.gates.insert(key, id);
+        id
+    }
+
+    fn instance(&mut self, module: &str, name: &str, connections: &Connections, line: usize) -> Result<()> {
+        let (id, target) = *self.components.get(module)
+            .ok_or(ImportError::UnknownModule(module.to_owned()))?;
+
+        let connections = match connections {
+            Connections::Named(named) => named.clone(),
+            Connections::Ordered(ordered) if ordered.len() > target.ports.len() => {
+                return Err(ImportError::Syntax { line, message: format!("'{}' has only {} ports", module, target.ports.len()) }.into());
+            },
+            Connections::Ordered(ordered) => target.ports.iter().cloned().zip(ordered.iter().cloned()).collect(),
+        };
+
+        let instance = self.schematic.place(id, Some(name.to_owned()));
+        let index = |direction: Direction, port: &str| target.ports.iter()
+            .filter(|port| target.signals[*port].0 == direction)
+            .position(|name| name == port)
+            .unwrap_or_default() as u64;
+
+        for (port, expr) in connections {
+            let Some(expr) = expr else {
+                continue;
+            };
+
+            let (direction, range) = target.signals.get(&port)
+                .filter(|(direction, _)| *direction != Direction::Wire && target.ports.contains(&port))
+                .copied()
+                .ok_or(ImportError::UnknownSignal { module: module.to_owned(), signal: port.clone() })?;
+
+            match direction {
+                Direction::Input => self.read(&expr, range.width(), Connection::input(instance, index(direction, &port)))?,
+                _ => self.drive(&expr, Connection::output(instance, index(direction, &port)), range.width(), line)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::circuit::{
+        tests::connect,
+        tests::place,
+        tests::project,
+        Circuit
+    };
+
+    // Sets the inputs of a project by label, then reads the output with the given label
+    fn simulate(project: &Project, inputs: &[(&str, u64)], output: &str) -> Option<u64> {
+        let mut circuit = Circuit::compile(project).unwrap();
+
+        for (label, value) in inputs {
+            circuit.set_input(circuit.find(label).unwrap(), *value).unwrap();
+        }
+
+        circuit.evaluate().unwrap();
+        circuit.output(circuit.find(output).unwrap()).unwrap().to_u64()
+    }
+
+    fn import_error(source: &str) -> String {
+        import(source).err().unwrap().to_string()
+    }
+
+    // Clocks `a ^ b` into a D flip-flop, computing it with a truth table inside the subcomponent `wrapper`
+    pub(crate) fn design() -> Project {
+        let mut project = project();
+        project.components.insert(20, Component {
+            id: 20,
+            name: "xor gate".into(),
+            inputs: vec!["a".into(), "b".into()],
+            outputs: vec!["y".into()],
+            driver: ComponentDriver::truth([(0, 0), (1, 1), (2, 1), (3, 0)]),
+            delay: 1,
+        });
+
+        let mut inner = self::project();
+        place(&mut inner, 0, Project::INPUT, "a");
+        place(&mut inner, 1, Project::INPUT, "b");
+        place(&mut inner, 2, 20, "xor");
+        place(&mut inner, 3, Project::OUTPUT, "y");
+        connect(&mut inner, (0, 0), (2, 0));
+        connect(&mut inner, (1, 0), (2, 1));
+        connect(&mut inner, (2, 0), (3, 0));
+        project.components.insert(21, Component {
+            id: 21,
+            name: "wrapper".into(),
+            inputs: vec!["a".into(), "b".into()],
+            outputs: vec!["y".into()],
+            driver: ComponentDriver::Subcomponent { body: inner.body, connections: inner.connections, wires: vec![] },
+            delay: 1,
+        });
+
+        place(&mut project, 0, Project::INPUT, "a");
+        place(&mut project, 1, Project::INPUT, "b");
+        place(&mut project, 2, Project::CLOCK, "clock");
+        place(&mut project, 3, 21, "wrapper");
+        place(&mut project, 4, Project::D_FLIP_FLOP, "register");
+        place(&mut project, 5, Project::OUTPUT, "q");
+        connect(&mut project, (0, 0), (3, 0));
+        connect(&mut project, (1, 0), (3, 1));
+        connect(&mut project, (3, 0), (4, 0));
+        connect(&mut project, (2, 0), (4, 1));
+        connect(&mut project, (4, 0), (5, 0));
+        project
+    }
+
+    #[test]
+    fn bit_assigns() {
+        let project = import("module m(input [1:0] a, output [2:0] y);
+            assign y[0] = a[1];
+            assign y[2] = a[0] & a[1];
+            assign y[1] = ~a[0];
+        endmodule").unwrap();
+
+        for a in 0..4 {
+            let y = (a >> 1) | (!a & 1) << 1 | (a & a >> 1 & 1) << 2;
+            assert_eq!(simulate(&project, &[("a", a)], "y"), Some(y));
+        }
+    }
+
+    #[test]
+    fn bit_drives() {
+        let project = import("module half_adder(input a, input b, output s, output c);
+            xor (s, a, b);
+            and (c, a, b);
+        endmodule
+
+        module m(input a, input b, output [3:0] y);
+            half_adder h (.a(a), .b(b), .s(y[0]), .c(y[1]));
+            or (y[3], a, b);
+            assign y[2] = 1'b0;
+        endmodule").unwrap();
+
+        for (a, b) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+            let y = (a ^ b) | (a & b) << 1 | (a | b) << 3;
+            assert_eq!(simulate(&project, &[("a", a), ("b", b)], "y"), Some(y));
+        }
+    }
+
+    #[test]
+    fn connections() {
+        let project = import("module sub(input a, input b, output y);
+            assign y = a & ~b;
+        endmodule
+
+        module m(input p, input q, output ordered, output named);
+            sub s1 (p, q, ordered);
+            sub s2 (.y(named), .b(q), .a(p));
+        endmodule").unwrap();
+
+        for (p, q) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+            assert_eq!(simulate(&project, &[("p", p), ("q", q)], "ordered"), Some(p & !q & 1));
+            assert_eq!(simulate(&project, &[("p", p), ("q", q)], "named"), Some(p & !q & 1));
+        }
+
+        assert!(import_error("module sub(input a, output y); assign y = a; endmodule
+            module m(input a, output y); sub s (a, y, a); endmodule").contains("has only 2 ports"));
+        assert!(import_error("module sub(input a, output y); assign y = a; endmodule
+            module m(input a, output y); sub s (.a(a), .z(y)); endmodule").contains("UnknownSignal"));
+    }
+
+    #[test]
+    fn multiple_drivers() {
+        let errors = [
+            "module m(input a, output y); assign y = a; assign y = ~a; endmodule",
+            "module m(input a, input b, output y); and (y, a, b); assign y = a; endmodule",
+            "module m(input a, output [1:0] y); assign y[0] = a; assign y[0] = ~a; endmodule",
+            "module m(input a, input b, output [1:0] y); and (y[1], a, b); assign y[1] = a; endmodule",
+            "module m(input a, output [1:0] y); assign y[0] = a; assign y = 2'b00; endmodule",
+            "module m(input a, input b); assign a = b; endmodule",
+        ];
+
+        for source in errors {
+            assert!(import_error(source).contains("MultipleDrivers"), "{}", source);
+        }
+    }
+
+    #[test]
+    fn nesting() {
+        let chain = format!("module m(input a, output y); assign y = a{}; endmodule", " & a".repeat(NESTING_LIMIT));
+        assert_eq!(simulate(&import(&chain).unwrap(), &[("a", 1)], "y"), Some(1));
+
+        let message = format!("line: 2, message: \"Nested more than {} levels deep\"", NESTING_LIMIT);
+        let deep = [
+            format!("{}a{}", "(".repeat(100_000), ")".repeat(100_000)),
+            format!("{}a", "~".repeat(100_000)),
+            format!("a{}", " | a".repeat(100_000)),
+            format!("{}a", "a ? a : ".repeat(100_000)),
+        ];
+
+        for expr in deep {
+            assert!(import_error(&format!("module m(input a, output y);\nassign y = {};\nendmodule", expr)).contains(&message));
+        }
+    }
+
+    #[test]
+    fn recursion() {
+        let error = import_error("module a(input i, output o); b u (i, o); endmodule
+            module b(input i, output o); a u (i, o); endmodule
+            module top(input i, output o); a u (i, o); endmodule");
+        assert!(error.contains("Recursive([\"a\", \"b\"])"));
+
+        assert!(import_error("module m(input i, output o); m u (i, o); endmodule").contains("Recursive([\"m\"])"));
+    }
+
+    #[test]
+    fn exports() {
+        let verilog = export(&design(), "top").unwrap();
+
+        assert!(verilog.contains("module xor_gate (
+    input wire a,
+    input wire b,
+    output wire y
+);
+    assign y = a & ~b | ~a & b;
+endmodule"));
+        assert!(verilog.contains("    reg state;
+    always @(posedge clk) state <= d;
+    assign q = state;
+    assign q_ = ~state;
+"));
+        assert!(verilog.contains("    xor_gate u2 (.a(a), .b(b), .y(n2_0));"));
+        assert!(verilog.ends_with("    wrapper u3 (.a(a), .b(b), .y(n3_0));
+    d_flip_flop u4 (.d(n3_0), .clk(clock), .q(n4_0), .q_(n4_1));
+    assign q = n4_0;
+endmodule
+"));
+
+        // Modules come before the modules instantiating them
+        let position = |module: &str| verilog.find(&format!("module {} ", module)).unwrap();
+        assert!(position("xor_gate") < position("wrapper") && position("wrapper") < position("top"));
+
+        // Clocks only make sense at the top level
+        let mut project = design();
+        let mut clocked = self::project();
+        place(&mut clocked, 4, Project::CLOCK, "clock");
+
+        if let ComponentDriver::Subcomponent { body, .. } = &mut project.components.get_mut(&21).unwrap().driver {
+            body.extend(clocked.body);
+        }
+
+        assert!(export(&project, "top").err().unwrap().to_string().contains("Unsupported(5)"));
+    }
+
+    #[test]
+    fn round_trip() {
+        let verilog = export(&Project::empty(), "top").unwrap();
+        let project = import(&verilog).unwrap();
+
+        let mut labels = project.body.values()
+            .filter_map(|placement| placement.label.as_deref())
+            .collect::<Vec<_>>();
+        labels.sort_unstable();
+        assert_eq!(labels, ["Input_", "Output_", "u2"]);
+        Circuit::compile(&project).unwrap();
+
+        // Wired up, the default project's gate feeds its input through to its output
+        let mut project = Project::empty();
+        project.connect(Connection::output(0, 0), Connection::input(2, 0)).unwrap();
+        project.connect(Connection::output(0, 0), Connection::input(2, 1)).unwrap();
+        project.connect(Connection::output(2, 0), Connection::input(1, 0)).unwrap();
+
+        let project = import(&export(&project, "top").unwrap()).unwrap();
+        for value in 0..2 {
+            assert_eq!(simulate(&project, &[("Input_", value)], "Output_"), Some(value));
+        }
+    }
+}
@@ -0,0 +1,383 @@
+use crate::{
+    circuit::CircuitError,
+    error::global::Inner,
+    interop,
+    interop::Body,
+    interop::Design,
+    interop::ExportError,
+    interop::Module,
+    interop::Naming,
+    interop::Operators,
+    interop::Signal,
+    project::Component,
+    project::ComponentDriver,
+    project::FlipFlopKind,
+    project::LatchKind,
+    project::MemoryKind,
+    project::Port,
+    project::Project,
+    Result
+};
+use std::fmt::Write;
+
+const NAMING: Naming = Naming {
+    keywords: &[
+        "abs", "access", "after", "alias", "all", "and", "architecture", "array", "assert", "attribute", "begin", "block",
+        "body", "buffer", "bus", "case", "component", "configuration", "constant", "disconnect", "downto", "else", "elsif",
+        "end", "entity", "exit", "file", "for", "function", "generate", "generic", "group", "guarded", "if", "impure", "in",
+        "inertial", "inout", "is", "label", "library", "linkage", "literal", "loop", "map", "mod", "nand", "new", "next", "nor",
+        "not", "null", "of", "on", "open", "or", "others", "out", "package", "port", "postponed", "procedure", "process",
+        "pure", "range", "record", "register", "reject", "rem", "report", "return", "rol", "ror", "select", "severity",
+        "signal", "shared", "sla", "sll", "sra", "srl", "subtype", "then", "to", "transport", "type", "unaffected", "units",
+        "until", "use", "variable", "wait", "when", "while", "with", "xnor", "xor",
+    ],
+    strict: true,
+};
+
+// VHDL gives `and`, `or` and `xor` the same precedence, so mixing them always takes parentheses
+const OPERATORS: Operators = Operators {
+    not: "not ",
+    and: "and",
+    or: "or",
+    xor: "xor",
+    constants: ["'0'", "'1'"],
+    precedence: false,
+};
+
+/// Writes a project as VHDL, with an entity and architecture for every component and `top` for the project itself.
+///
+/// Subcomponents become architectures instantiating the entities of their contents, and the project's inputs, outputs and clocks
+/// become ports of `top` named after their labels. Truth tables become `with ... select` statements.
+/// Components the project doesn't use are written out too, unless they have no VHDL equivalent.
+pub fn export(project: &Project, top: &str) -> Result<String> {
+    let design = Design::new(project, top, &NAMING, true)?;
+    let mut units = vec![];
+
+    for module in design.modules.iter() {
+        let mut vhdl = String::new();
+
+        match write_unit(&mut vhdl, module) {
+            Ok(()) => units.push(vhdl),
+            // Unused components are only left out for having no equivalent, not for being broken
+            Err(err) if !module.used && matches!(err.inner(), Inner::ExportError(ExportError::Unsupported(_))) => continue,
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(units.join("\n"))
+}
+
+fn write_unit(out: &mut String, module: &Module) -> Result<()> {
+    writeln!(out, "library ieee;\nuse ieee.std_logic_1164.all;\n").unwrap();
+    writeln!(out, "entity {} is", module.name).unwrap();
+
+    let ports = module.inputs.iter()
+        .map(|signal| format!("        {} : in {}", signal.name, kind(signal.width)))
+        .chain(module.outputs.iter().map(|signal| format!("        {} : out {}", signal.name, kind(signal.width))))
+        .collect::<Vec<_>>();
+
+    if !ports.is_empty() {
+        writeln!(out, "    port (\n{}\n    );", ports.join(";\n")).unwrap();
+    }
+
+    writeln!(out, "end entity {};\n", module.name).unwrap();
+
+    // Declarations go before `begin` and statements after, so they are written separately
+    let mut declarations = String::new();
+    let mut statements = String::new();
+
+    let architecture = match &module.body {
+        Body::Netlist { wires, instances, assigns } => {
+            for wire in wires {
+                writeln!(declarations, "    signal {} : {};", wire.name, kind(wire.width)).unwrap();
+            }
+
+            for instance in instances {
+                let connections = instance.connections.iter()
+                    .enumerate()
+                    .map(|(index, (port, net))| {
+                        let actual = match net {
+                            Some(net) => net.clone(),
+                            // Inputs can't be left open, so unconnected ones float as they do in simulation
+                            None if index < instance.inputs => fill('Z', port.width),
+                            None => "open".to_owned(),
+                        };
+
+                        format!("{} => {}", port.name, actual)
+                    })
+                    .collect::<Vec<_>>();
+
+                match connections.is_empty() {
+                    true => writeln!(statements, "    {} : entity work.{};", instance.name, instance.module),
+                    false => writeln!(statements, "    {} : entity work.{} port map ({});", instance.name, instance.module, connections.join(", ")),
+                }.unwrap();
+            }
+
+            for (port, net) in assigns {
+                writeln!(statements, "    {} <= {};", port, net).unwrap();
+            }
+
+            "structural"
+        },
+        Body::Leaf(component) => {
+            leaf(&mut declarations, &mut statements, module, component)?;
+            "behavioural"
+        },
+    };
+
+    writeln!(out, "architecture {} of {} is", architecture, module.name).unwrap();
+    write!(out, "{}", declarations).unwrap();
+    writeln!(out, "begin").unwrap();
+    write!(out, "{}", statements).unwrap();
+    writeln!(out, "end architecture {};", architecture).unwrap();
+
+    Ok(())
+}
+
+fn kind(width: u8) -> String {
+    match width {
+        1 => "std_logic".to_owned(),
+        width => format!("std_logic_vector({} downto 0)", width - 1),
+    }
+}
+
+// A constant of the given width, bit 0 last
+fn literal(value: u64, width: u32) -> String {
+    let bits = (0..width)
+        .rev()
+        .map(|bit| match bit < 64 && value >> bit & 1 == 1 {
+            true => '1',
+            false => '0',
+        })
+        .collect::<String>();
+
+    match width {
+        1 => format!("'{}'", bits),
+        _ => format!("\"{}\"", bits),
+    }
+}
+
+// Every bit of a signal set to the same value
+fn fill(bit: char, width: u8) -> String {
+    match width {
+        1 => format!("'{}'", bit),
+        _ => format!("(others => '{}')", bit),
+    }
+}
+
+fn bit(signal: &Signal, index: u8) -> String {
+    match signal.width {
+        1 => signal.name.clone(),
+        _ => format!("{}({})", signal.name, index),
+    }
+}
+
+// The slice of a vector `total` bits wide holding each port, packed the way simulation packs them with the first port in the lowest bits
+fn slices(vector: &str, signals: &[Signal], total: u32) -> Vec<(String, String)> {
+    let mut offset = 0;
+
+    signals.iter()
+        .map(|signal| {
+            let slice = match (total, signal.width) {
+                (1, _) => vector.to_owned(),
+                (_, 1) => format!("{}({})", vector, offset),
+                (_, width) => format!("{}({} downto {})", vector, offset + width as u32 - 1, offset),
+            };
+
+            offset += signal.width as u32;
+            (signal.name.clone(), slice)
+        })
+        .collect()
+}
+
+// Writes the architecture of a component that isn't made of other components
+fn leaf(declarations: &mut String, statements: &mut String, module: &Module, component: &Component) -> Result<()> {
+    let bits = |ports: &[Port]| ports.iter().map(|port| port.width as u32).sum::<u32>();
+    let (inputs, outputs) = (bits(&component.inputs), bits(&component.outputs));
+
+    let mut taken = module.taken(component)?;
+    let mut internal = |name: &str| interop::unique(name, &mut taken);
+
+    match &component.driver {
+        ComponentDriver::TruthTable { truth, .. } => {
+            let (key, row) = (internal("key"), internal("row"));
+            let mut keys = truth.keys().collect::<Vec<_>>();
+            keys.sort_unstable();
+
+            writeln!(declarations, "    signal {} : {};", key, kind(inputs.max(1) as u8)).unwrap();
+            writeln!(declarations, "    signal {} : {};", row, kind(outputs.max(1) as u8)).unwrap();
+
+            for (port, slice) in slices(&key, &module.inputs, inputs) {
+                writeln!(statements, "    {} <= {};", slice, port).unwrap();
+            }
+
+            match inputs {
+                // With no inputs, the table only has the one row
+                0 => writeln!(statements, "    {} <= {};", row, literal(truth.get(&0).copied().unwrap_or(0), outputs.max(1))).unwrap(),
+                _ => {
+                    writeln!(statements, "    with {} select {} <=", key, row).unwrap();
+
+                    for key in keys.into_iter().filter(|key| inputs >= 64 || **key >> inputs == 0) {
+                        writeln!(statements, "        {} when {},", literal(truth[key], outputs.max(1)), literal(*key, inputs)).unwrap();
+                    }
+
+                    // Missing rows output 0, as they do in simulation
+                    writeln!(statements, "        {} when others;", literal(0, outputs.max(1))).unwrap();
+                },
+            }
+
+            for (port, slice) in slices(&row, &module.outputs, outputs) {
+                writeln!(statements, "    {} <= {};", port, slice).unwrap();
+            }
+        },
+        ComponentDriver::Expression { equations } => {
+            let names = module.bits(component, bit);
+
+            for (name, output) in Port::bit_names(&component.outputs).iter().zip(module.outputs.iter().flat_map(|signal| (0..signal.width).map(move |index| bit(signal, index)))) {
+                let expression = match equations.iter().find(|equation| equation.output == *name) {
+                    Some(equation) => {
+                        if let Some(variable) = equation.expr.variables().into_iter().find(|variable| !names.contains_key(variable)) {
+                            return Err(CircuitError::PortMismatch { component: component.id, port: variable }.into());
+                        }
+
+                        interop::expression(&equation.expr, &OPERATORS, &|name| names[name].clone())
+                    },
+                    // Bits without an equation are X
+                    None => "'X'".to_owned(),
+                };
+
+                writeln!(statements, "    {} <= {};", output, expression).unwrap();
+            }
+        },
+        ComponentDriver::Bdd { table } => {
+            if !table.is_valid(inputs as usize) {
+                return Err(CircuitError::InvalidBdd(component.id).into());
+            }
+
+            let input = module.inputs.iter()
+                .flat_map(|signal| (0..signal.width).map(move |index| bit(signal, index)))
+                .collect::<Vec<_>>();
+            let mut nodes = vec![OPERATORS.constants[0].to_owned(), OPERATORS.constants[1].to_owned()];
+
+            for (variable, low, high) in table.nodes.iter() {
+                let node = internal(&format!("b{}", nodes.len()));
+
+                writeln!(declarations, "    signal {} : std_logic;", node).unwrap();
+                writeln!(statements, "    {} <= {} when {} = '1' else {};", node, nodes[*high], input[*variable as usize], nodes[*low]).unwrap();
+                nodes.push(node);
+            }
+
+            let outputs = module.outputs.iter()
+                .flat_map(|signal| (0..signal.width).map(move |index| bit(signal, index)));
+
+            for (output, root) in outputs.zip(table.outputs.iter()) {
+                writeln!(statements, "    {} <= {};", output, nodes[*root]).unwrap();
+            }
+        },
+        ComponentDriver::FlipFlop { kind: flip_flop } => {
+            let state = internal("state");
+            let data = |index: usize| module.inputs[index].name.as_str();
+            let clock = &module.inputs[module.inputs.len() - 1].name;
+
+            let next = match flip_flop {
+                FlipFlopKind::D => data(0).to_owned(),
+                FlipFlopKind::JK => format!("({j} and not {q}) or (not {k} and {q})", j = data(0), k = data(1), q = state),
+                FlipFlopKind::T => format!("{} xor {}", data(0), state),
+            };
+
+            writeln!(declarations, "    signal {} : {};", state, kind(module.outputs[0].width)).unwrap();
+            writeln!(statements, "    process ({}) begin\n        if rising_edge({}) then\n            {} <= {};\n        end if;\n    end process;", clock, clock, state, next).unwrap();
+            module.stored(&state, &OPERATORS, |output, value| writeln!(statements, "    {} <= {};", output, value).unwrap());
+        },
+        ComponentDriver::Latch { kind: latch } => {
+            let state = internal("state");
+            let data = |index: usize| module.inputs[index].name.as_str();
+
+            writeln!(declarations, "    signal {} : {};", state, kind(module.outputs[0].width)).unwrap();
+
+            match latch {
+                LatchKind::D => writeln!(statements, "    process ({d}, {en}) begin\n        if {en} = '1' then\n            {q} <= {d};\n        end if;\n    end process;", d = data(0), en = data(1), q = state),
+                LatchKind::SR => writeln!(statements, "    {q} <= {s} or ({q} and not {r});", q = state, s = data(0), r = data(1)),
+            }.unwrap();
+
+            module.stored(&state, &OPERATORS, |output, value| writeln!(statements, "    {} <= {};", output, value).unwrap());
+        },
+        ComponentDriver::Memory { kind: MemoryKind::Rom, address, data, contents, .. } => {
+            let word = internal("word");
+
+            writeln!(declarations, "    signal {} : {};", word, kind(*data)).unwrap();
+            writeln!(statements, "    with {} select {} <=", module.inputs[0].name, word).unwrap();
+
+            for (index, value) in contents.iter().enumerate() {
+                writeln!(statements, "        {} when {},", literal(*value, *data as u32), literal(index as u64, *address as u32)).unwrap();
+            }
+
+            writeln!(statements, "        {} when others;", fill('0', *data)).unwrap();
+
+            // The output floats while the read enable is low
+            writeln!(statements, "    {} <= {} when {} = '1' else {};", module.outputs[0].name, word, module.inputs[1].name, fill('Z', *data)).unwrap();
+        },
+        _ => return Err(ExportError::Unsupported(component.id).into()),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bdd::BddTable,
+        interop::verilog::tests::design
+    };
+
+    #[test]
+    fn exports() {
+        let vhdl = export(&design(), "top").unwrap();
+
+        assert!(vhdl.contains("architecture behavioural of xor_gate is
+    signal key : std_logic_vector(1 downto 0);
+    signal row : std_logic;
+begin
+    key(0) <= a;
+    key(1) <= b;
+    with key select row <=
+        '0' when \"00\",
+        '1' when \"01\",
+        '1' when \"10\",
+        '0' when \"11\",
+        '0' when others;
+    y <= row;
+end architecture behavioural;"));
+        assert!(vhdl.contains("    process (clk) begin
+        if rising_edge(clk) then
+            state <= d;
+        end if;
+    end process;
+    q <= state;
+    q_2 <= not state;
+"));
+        assert!(vhdl.contains("    u2 : entity work.xor_gate port map (a => a, b => b, y => n2_0);"));
+        assert!(vhdl.contains("    u4 : entity work.d_flip_flop port map (d => n3_0, clk => clock, q => n4_0, q_2 => n4_1);"));
+
+        // Unused components are written out too, unless VHDL has no equivalent for them
+        assert!(vhdl.contains("entity jk_flip_flop is"));
+        assert!(!vhdl.contains("entity ram is"));
+    }
+
+    #[test]
+    fn broken_components() {
+        // An unused component that could be written out, were it not for its diagram referring to a node that doesn't exist
+        let mut project = design();
+        project.components.insert(30, Component {
+            id: 30,
+            name: "broken".into(),
+            inputs: vec!["a".into()],
+            outputs: vec!["y".into()],
+            driver: ComponentDriver::Bdd { table: BddTable { nodes: vec![(0, 0, 5)], outputs: vec![2] } },
+            delay: 1,
+        });
+
+        assert!(export(&project, "top").err().unwrap().to_string().contains("InvalidBdd(30)"));
+    }
+}
@@ -0,0 +1,593 @@
+use crate::{
+    expression::Equation,
+    expression::Expr,
+    interop,
+    interop::Bit,
+    interop::Bits,
+    interop::ImportError,
+    interop::Schematic,
+    project::Component,
+    project::ComponentDriver,
+    project::ComponentId,
+    project::Connection,
+    project::FlipFlopKind,
+    project::Port,
+    project::Project,
+    Result
+};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{
+    HashMap,
+    HashSet
+};
+
+#[derive(Deserialize)]
+struct YDesign {
+    #[serde(default)]
+    modules: HashMap<String, YModule>,
+}
+
+#[derive(Deserialize)]
+struct YModule {
+    #[serde(default)]
+    attributes: HashMap<String, Value>,
+    #[serde(default)]
+    ports: HashMap<String, YPort>,
+    #[serde(default)]
+    cells: HashMap<String, YCell>,
+    #[serde(default)]
+    netnames: HashMap<String, YNet>,
+}
+
+impl YModule {
+    // The ports going one way. JSON objects don't keep their order, but Yosys numbers the bits of ports in the order they are declared.
+    fn ports(&self, direction: &str) -> Vec<(&String, &YPort)> {
+        let mut ports = self.ports.iter()
+            .filter(|(_, port)| port.direction == direction)
+            .collect::<Vec<_>>();
+
+        ports.sort_by_key(|(name, port)| (port.bits.iter().filter_map(YBit::net).min(), *name));
+        ports
+    }
+}
+
+#[derive(Deserialize)]
+struct YPort {
+    direction: String,
+    bits: Vec<YBit>,
+}
+
+// A net, numbered from 2, or a constant bit: "0", "1", "x" or "z"
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize)]
+#[serde(untagged)]
+enum YBit {
+    Net(u64),
+    Constant(String),
+}
+
+impl YBit {
+    fn net(&self) -> Option<u64> {
+        match self {
+            Self::Net(net) => Some(*net),
+            Self::Constant(_) => None,
+        }
+    }
+
+    fn bit(&self) -> Bit {
+        match self {
+            Self::Net(net) => Bit::Net(*net),
+            Self::Constant(constant) if constant == "0" || constant == "1" => Bit::Constant(constant == "1"),
+            Self::Constant(_) => Bit::Unknown,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct YCell {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    hide_name: u8,
+    #[serde(default)]
+    parameters: HashMap<String, Value>,
+    #[serde(default)]
+    connections: HashMap<String, Vec<YBit>>,
+}
+
+#[derive(Deserialize)]
+struct YNet {
+    #[serde(default)]
+    hide_name: u8,
+    bits: Vec<YBit>,
+}
+
+// Parameters and attributes are written as numbers by some versions of Yosys and as strings of binary digits by others
+fn number(value: &Value) -> u64 {
+    match value {
+        Value::Number(number) => number.as_u64().unwrap_or_default(),
+        Value::String(bits) => u64::from_str_radix(bits, 2).unwrap_or_default(),
+        _ => 0,
+    }
+}
+
+/// Reads the netlist `yosys write_json` writes into a project, each module a component, with the module marked `top`,
+/// or failing that the last one no other module instantiates, as the top level.
+///
+/// `$not`, `$and`, `$or`, `$xor`, `$mux` and `$dff` cells, along with their single-bit `$_NOT_`-style forms, become
+/// the built-in gates and flip-flops where they fit and expression components otherwise. Buses that are sliced or joined
+/// along the way get an expression component picking out their bits. Everything is laid out in columns running from
+/// the inputs to the outputs.
+pub fn import(source: &str) -> Result<Project> {
+    let design: YDesign = serde_json::from_str(source)
+        .map_err(|err| ImportError::Syntax { line: err.line(), message: err.to_string() })?;
+
+    let mut modules = design.modules.iter().collect::<Vec<_>>();
+    modules.sort_by_key(|(name, _)| *name);
+
+    let mut project = Project::empty();
+    project.body.clear();
+    project.connections.clear();
+    project.wires.clear();
+
+    // Modules may be instantiated before they are defined, so every one gets its component up front
+    let mut components = HashMap::new();
+
+    for (name, module) in modules.iter() {
+        if let Some((port, _)) = module.ports.iter().find(|(_, port)| !matches!(port.direction.as_str(), "input" | "output")) {
+            return Err(ImportError::Unsupported(format!("{} port '{}' of '{}'", module.ports[port].direction, port, name)).into());
+        }
+
+        let ports = |direction: &str| module.ports(direction)
+            .into_iter()
+            .map(|(port, bits)| match bits.bits.len() {
+                1..=64 => Ok(Port::new(port.as_str(), bits.bits.len() as u8)),
+                _ => Err(ImportError::WidthMismatch { module: (*name).clone(), signal: port.clone() }.into()),
+            })
+            .collect::<Result<Vec<_>>>();
+
+        let id = interop::next_component(&project);
+        project.components.insert(id, Component {
+            id,
+            name: (*name).clone(),
+            inputs: ports("input")?,
+            outputs: ports("output")?,
+            driver: Schematic::default().into_driver(),
+            delay: 1,
+        });
+
+        components.insert((*name).clone(), (id, *module));
+    }
+
+    let instantiated = modules.iter()
+        .flat_map(|(_, module)| module.cells.values())
+        .map(|cell| cell.kind.as_str())
+        .collect::<HashSet<_>>();
+    let top = modules.iter()
+        .find(|(_, module)| module.attributes.get("top").is_some_and(|top| number(top) != 0))
+        .or(modules.iter().rev().find(|(name, _)| !instantiated.contains(name.as_str())))
+        .or(modules.last())
+        .map(|(name, _)| (*name).clone());
+
+    let mut cells = HashMap::new();
+
+    for (name, module) in modules.iter() {
+        let schematic = Importer {
+            project: &mut project,
+            components: &components,
+            cells: &mut cells,
+            name,
+            module,
+            schematic: Schematic::default(),
+            bits: Bits::default(),
+        }.build()?;
+
+        if top.as_ref() == Some(*name) {
+            project.body = schematic.body.clone();
+            project.connections = schematic.connections.clone();
+            project.wires = schematic.wires.clone();
+        }
+
+        if let Some(component) = project.components.get_mut(&components[*name].0) {
+            component.driver = schematic.into_driver();
+        }
+    }
+
+    interop::acyclic(&project)?;
+    Ok(project)
+}
+
+// Builds the body of one module
+struct Importer<'a> {
+    project: &'a mut Project,
+    components: &'a HashMap<String, (ComponentId, &'a YModule)>,
+    // Components standing in for cells, by operation, port widths and signedness, shared by every cell they fit
+    cells: &'a mut HashMap<(String, Vec<usize>, [bool; 2]), ComponentId>,
+    name: &'a str,
+    module: &'a YModule,
+    schematic: Schematic,
+    bits: Bits,
+}
+
+impl Importer<'_> {
+    fn build(mut self) -> Result<Schematic> {
+        for (name, port) in self.module.ports("input") {
+            let component = interop::port_component(self.project, true, port.bits.len() as u8);
+            let instance = self.schematic.place(component, Some(name.clone()));
+            self.drive(&port.bits, Connection::output(instance, 0))?;
+        }
+
+        for (name, port) in self.module.ports("output") {
+            let component = interop::port_component(self.project, false, port.bits.len() as u8);
+            let instance = self.schematic.place(component, Some(name.clone()));
+            self.read(&port.bits, Connection::input(instance, 0));
+        }
+
+        let mut cells = self.module.cells.iter().collect::<Vec<_>>();
+        cells.sort_by_key(|(name, _)| *name);
+
+        for (name, cell) in cells {
+            self.cell(name, cell)?;
+        }
+
+        self.bits.connect(self.project, &mut self.schematic);
+
+        self.schematic.lay_out();
+        Ok(self.schematic)
+    }
+
+    fn drive(&mut self, bits: &[YBit], output: Connection) -> Result<()> {
+        let bits = bits.iter().map(YBit::bit).collect::<Vec<_>>();
+
+        self.bits.drive(&bits, output).map_err(|net| ImportError::MultipleDrivers {
+            module: self.name.to_owned(),
+            signal: self.net_name(&[YBit::Net(net)]).unwrap_or(net.to_string()),
+        }.into())
+    }
+
+    fn read(&mut self, bits: &[YBit], input: Connection) {
+        let name = format!("{}.{}", self.name, self.net_name(bits).unwrap_or("bits".to_owned()));
+        self.bits.read(bits.iter().map(YBit::bit).collect(), input, name);
+    }
+
+    fn read_port(&mut self, cell: &YCell, port: &str, input: Connection) {
+        self.read(cell.connections.get(port).map(Vec::as_slice).unwrap_or_default(), input);
+    }
+
+    // The name of the net with exactly these bits, if it has one
+    fn net_name(&self, bits: &[YBit]) -> Option<String> {
+        let mut names = self.module.netnames.iter()
+            .filter(|(_, net)| net.hide_name == 0 && net.bits == bits)
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+
+        names.into_iter().next()
+    }
+
+    // Labels placements after the cells they stand for, unless Yosys made the name up
+    fn label(name: &str, cell: &YCell) -> Option<String> {
+        (cell.hide_name == 0 && !name.starts_with('$')).then(|| name.to_owned())
+    }
+
+    fn cell(&mut self, name: &str, cell: &YCell) -> Result<()> {
+        let kind = cell.kind.as_str();
+
+        if self.components.contains_key(kind) {
+            return self.instance(name, cell);
+        }
+
+        let (operation, inputs) = match kind {
+            "$not" | "$_NOT_" => ("not", &["A"][..]),
+            "$and" | "$_AND_" => ("and", &["A", "B"][..]),
+            "$or" | "$_OR_" => ("or", &["A", "B"][..]),
+            "$xor" | "$_XOR_" => ("xor", &["A", "B"][..]),
+            "$mux" | "$_MUX_" => ("mux", &["A", "B", "S"][..]),
+            "$dff" | "$_DFF_P_" | "$_DFF_N_" => return self.flip_flop(name, cell),
+            _ => return Err(ImportError::Unsupported(format!("cell '{}' of type '{}'", name, kind)).into()),
+        };
+
+        let widths = inputs.iter()
+            .chain(["Y"].iter())
+            .map(|port| match cell.connections.get(*port).map(Vec::len) {
+                Some(width @ 1..=64) => Ok(width),
+                _ => Err(ImportError::WidthMismatch { module: self.name.to_owned(), signal: format!("{}.{}", name, port) }.into()),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // Whether `A` and `B` are each sign-extended rather than zero-extended
+        let signed = ["A_SIGNED", "B_SIGNED"].map(|parameter| cell.parameters.get(parameter).is_some_and(|signed| number(signed) != 0));
+
+        let component = match (operation, widths.as_slice()) {
+            ("not", [1, 1]) => Project::NOT,
+            ("and", [1, 1, 1]) => Project::AND,
+            ("or", [1, 1, 1]) => Project::OR,
+            _ => self.operation(operation, widths, signed),
+        };
+
+        let instance = self.schematic.place(component, Self::label(name, cell));
+
+        for (terminal, port) in inputs.iter().enumerate() {
+            self.read_port(cell, port, Connection::input(instance, terminal as u64));
+        }
+
+        self.drive(cell.connections.get("Y").map(Vec::as_slice).unwrap_or_default(), Connection::output(instance, 0))
+    }
+
+    // An expression component carrying out a cell's operation on each bit, extending narrower operands to the output's width
+    fn operation(&mut self, operation: &str, widths: Vec<usize>, signed: [bool; 2]) -> ComponentId {
+        let key = (operation.to_owned(), widths.clone(), signed);
+
+        if let Some(id) = self.cells.get(&key) {
+            return *id;
+        }
+
+        let (inputs, output) = widths.split_at(widths.len() - 1);
+        let input_ports = ["a", "b", "s"].iter()
+            .zip(inputs.iter())
+            .map(|(name, width)| Port::new(*name, *width as u8))
+            .collect::<Vec<_>>();
+        let output_ports = vec![Port::new("y", output[0] as u8)];
+
+        let bits = |port: &Port| Port::bit_names(std::slice::from_ref(port));
+        let operand = |port: usize, bit: usize| {
+            let names = bits(&input_ports[port]);
+
+            match names.get(bit) {
+                Some(name) => Expr::Variable(name.clone()),
+                None if signed.get(port) == Some(&true) => Expr::Variable(names[names.len() - 1].clone()),
+                None => Expr::Constant(false),
+            }
+        };
+
+        let equations = bits(&output_ports[0])
+            .into_iter()
+            .enumerate()
+            .map(|(bit, output)| {
+                let (a, b) = (Box::new(operand(0, bit)), || Box::new(operand(1, bit)));
+
+                let expr = match operation {
+                    "not" => Expr::Not(a),
+                    "and" => Expr::And(a, b()),
+                    "or" => Expr::Or(a, b()),
+                    "xor" => Expr::Xor(a, b()),
+                    // The select picks the second operand when it is high
+                    _ => {
+                        let select = Box::new(operand(2, 0));
+
+                        Expr::Or(
+                            Box::new(Expr::And(select.clone(), b())),
+                            Box::new(Expr::And(Box::new(Expr::Not(select)), a)),
+                        )
+                    },
+                };
+
+                Equation { output, expr }
+            })
+            .collect();
+
+        let id = interop::next_component(self.project);
+        self.project.components.insert(id, Component {
+            id,
+            name: operation.to_owned(),
+            inputs: input_ports,
+            outputs: output_ports,
+            driver: ComponentDriver::Expression { equations },
+            delay: 1,
+        });
+
+        self.cells.insert(key, id);
+        id
+    }
+
+    fn flip_flop(&mut self, name: &str, cell: &YCell) -> Result<()> {
+        // The word-level cell and the single-bit ones name their clocks differently
+        let (clock, rising) = match cell.kind.as_str() {
+            "$dff" => ("CLK", cell.parameters.get("CLK_POLARITY").is_none_or(|polarity| number(polarity) != 0)),
+            kind => ("C", kind != "$_DFF_N_"),
+        };
+
+        let component = match cell.connections.get("D").map(Vec::len) {
+            Some(1) => Project::D_FLIP_FLOP,
+            Some(width @ 2..=64) => self.register(width),
+            _ => return Err(ImportError::WidthMismatch { module: self.name.to_owned(), signal: format!("{}.D", name) }.into()),
+        };
+
+        let instance = self.schematic.place(component, Self::label(name, cell));
+        self.read_port(cell, "D", Connection::input(instance, 0));
+
+        // A falling edge is a rising edge of the inverted clock
+        match rising {
+            true => self.read_port(cell, clock, Connection::input(instance, 1)),
+            false => {
+                let not = self.schematic.place(Project::NOT, None);
+                self.read_port(cell, clock, Connection::input(not, 0));
+                self.schematic.connect(Connection::output(not, 0), Connection::input(instance, 1));
+            },
+        }
+
+        self.drive(cell.connections.get("Q").map(Vec::as_slice).unwrap_or_default(), Connection::output(instance, 0))
+    }
+
+    // A D flip-flop storing a whole word
+    fn register(&mut self, width: usize) -> ComponentId {
+        let key = ("dff".to_owned(), vec![width], [false; 2]);
+
+        if let Some(id) = self.cells.get(&key) {
+            return *id;
+        }
+
+        let id = interop::next_component(self.project);
+        self.project.components.insert(id, Component {
+            id,
+            name: "d flip-flop".to_owned(),
+            inputs: vec![Port::new("d", width as u8), Port::new("clk", 1)],
+            outputs: vec![Port::new("q", width as u8), Port::new("q!", width as u8)],
+            driver: ComponentDriver::FlipFlop { kind: FlipFlopKind::D },
+            delay: 1,
+        });
+
+        self.cells.insert(key, id);
+        id
+    }
+
+    fn instance(&mut self, name: &str, cell: &YCell) -> Result<()> {
+        let (component, module) = self.components[&cell.kind];
+        let instance = self.schematic.place(component, Self::label(name, cell));
+
+        let inputs = module.ports("input");
+        let outputs = module.ports("output");
+
+        let mut connections = cell.connections.iter().collect::<Vec<_>>();
+        connections.sort_by_key(|(port, _)| *port);
+
+        for (port, bits) in connections {
+            if let Some(terminal) = inputs.iter().position(|(input, _)| *input == port) {
+                self.read(bits, Connection::input(instance, terminal as u64));
+            } else if let Some(terminal) = outputs.iter().position(|(output, _)| *output == port) {
+                self.drive(bits, Connection::output(instance, terminal as u64))?;
+            } else {
+                return Err(ImportError::UnknownSignal { module: cell.kind.clone(), signal: port.clone() }.into());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::Circuit;
+
+    // Sets the inputs of a circuit by label, then reads the output with the given label
+    fn step(circuit: &mut Circuit, inputs: &[(&str, u64)], output: &str) -> Option<u64> {
+        for (label, value) in inputs {
+            circuit.set_input(circuit.find(label).unwrap(), *value).unwrap();
+        }
+
+        circuit.evaluate().unwrap();
+        circuit.output(circuit.find(output).unwrap()).unwrap().to_u64()
+    }
+
+    fn import_error(source: &str) -> String {
+        import(source).err().unwrap().to_string()
+    }
+
+    // `z_spare` sorts last and nothing instantiates it, but `top` is marked as the top level
+    const NETLIST: &str = r#"{
+        "modules": {
+            "half": {
+                "ports": {
+                    "a": { "direction": "input", "bits": [ 2 ] },
+                    "b": { "direction": "input", "bits": [ 3 ] },
+                    "y": { "direction": "output", "bits": [ 4 ] }
+                },
+                "cells": {
+                    "$and$half.v:3$1": {
+                        "hide_name": 1,
+                        "type": "$_AND_",
+                        "connections": { "A": [ 2 ], "B": [ 3 ], "Y": [ 4 ] }
+                    }
+                }
+            },
+            "top": {
+                "attributes": { "top": "00000000000000000000000000000001" },
+                "ports": {
+                    "a": { "direction": "input", "bits": [ 2, 3 ] },
+                    "b": { "direction": "input", "bits": [ 4 ] },
+                    "s": { "direction": "input", "bits": [ 5 ] },
+                    "clk": { "direction": "input", "bits": [ 6 ] },
+                    "y": { "direction": "output", "bits": [ 7, 8 ] },
+                    "q": { "direction": "output", "bits": [ 9 ] },
+                    "r": { "direction": "output", "bits": [ 16, "1" ] },
+                    "p": { "direction": "output", "bits": [ 17, 18 ] }
+                },
+                "cells": {
+                    "$mux$top.v:10$2": {
+                        "hide_name": 1,
+                        "type": "$mux",
+                        "parameters": { "WIDTH": 2 },
+                        "connections": { "A": [ 2, 3 ], "B": [ "1", "0" ], "S": [ 5 ], "Y": [ 12, 13 ] }
+                    },
+                    "$and$top.v:11$3": {
+                        "hide_name": 1,
+                        "type": "$and",
+                        "parameters": { "A_SIGNED": 0, "B_SIGNED": 0, "A_WIDTH": 1, "B_WIDTH": 1, "Y_WIDTH": 1 },
+                        "connections": { "A": [ 12 ], "B": [ 4 ], "Y": [ 14 ] }
+                    },
+                    "$and$top.v:12$4": {
+                        "hide_name": 1,
+                        "type": "$and",
+                        "parameters": { "A_SIGNED": "1", "B_SIGNED": "0", "A_WIDTH": 2, "B_WIDTH": 1, "Y_WIDTH": 2 },
+                        "connections": { "A": [ 2, 3 ], "B": [ 4 ], "Y": [ 17, 18 ] }
+                    },
+                    "$not$top.v:13$5": {
+                        "hide_name": 1,
+                        "type": "$not",
+                        "connections": { "A": [ 15 ], "Y": [ 16 ] }
+                    },
+                    "u_half": {
+                        "type": "half",
+                        "connections": { "a": [ 3 ], "b": [ 4 ], "y": [ 15 ] }
+                    },
+                    "register": {
+                        "type": "$dff",
+                        "parameters": { "CLK_POLARITY": 1, "WIDTH": 2 },
+                        "connections": { "CLK": [ 6 ], "D": [ 12, 13 ], "Q": [ 7, 8 ] }
+                    },
+                    "falling": {
+                        "type": "$_DFF_N_",
+                        "connections": { "C": [ 6 ], "D": [ 14 ], "Q": [ 9 ] }
+                    }
+                },
+                "netnames": {
+                    "selected": { "hide_name": 0, "bits": [ 12, 13 ] }
+                }
+            },
+            "z_spare": {
+                "ports": {
+                    "i": { "direction": "input", "bits": [ 2 ] },
+                    "o": { "direction": "output", "bits": [ 2 ] }
+                }
+            }
+        }
+    }"#;
+
+    #[test]
+    fn netlist() {
+        let project = import(NETLIST).unwrap();
+        let mut circuit = Circuit::compile(&project).unwrap();
+
+        assert!(project.body.values().any(|placement| placement.label.as_deref() == Some("u_half")));
+        step(&mut circuit, &[("clk", 0)], "q");
+        let mut previous = None;
+
+        for (a, b, s) in (0..16).map(|row| (row & 3, row >> 2 & 1, row >> 3)) {
+            let selected = if s == 1 { 1 } else { a };
+            let inputs = [("a", a), ("b", b), ("s", s)];
+
+            // `r` is the inverted AND of the high bit of `a` with `b`, and its constant high bit
+            assert_eq!(step(&mut circuit, &inputs, "r"), Some(2 | !(a >> 1 & b) & 1));
+            // Only `a` is signed, so the one bit of `b` isn't copied into the high bit
+            assert_eq!(step(&mut circuit, &inputs, "p"), Some(a & b));
+
+            // The register takes the selected value on the rising edge, the other flip-flop its low bit ANDed with `b` on the falling edge
+            assert_eq!(step(&mut circuit, &[("clk", 1)], "y"), Some(selected));
+            if previous.is_some() {
+                assert_eq!(step(&mut circuit, &[], "q"), previous);
+            }
+
+            previous = step(&mut circuit, &[("clk", 0)], "q");
+            assert_eq!(previous, Some(selected & b & 1));
+        }
+    }
+
+    #[test]
+    fn errors() {
+        assert!(import_error(r#"{ "modules": { "m": { "ports": { "io": { "direction": "inout", "bits": [ 2 ] } } } } }"#).contains("inout port 'io' of 'm'"));
+        assert!(import_error(r#"{ "modules": { "m": { "cells": { "c": { "type": "$add", "connections": {} } } } } }"#).contains("cell 'c' of type '$add'"));
+        assert!(import_error(r#"{ "modules": { "m": { "cells": { "u": { "type": "m", "connections": {} } } } } }"#).contains("Recursive([\"m\"])"));
+        assert!(import_error("{ \"modules\": \n [").contains("line: 2"));
+    }
+}
@@ -0,0 +1,189 @@
+use crate::{
+    minimise,
+    minimise::Expression,
+    minimise::Form,
+    project::Component,
+    project::ComponentDriver,
+    project::ComponentId,
+    project::Port,
+    Result
+};
+use serde::Serialize;
+
+/// Karnaugh maps are only drawn for components with up to this many input bits, which makes an 8×8 map.
+pub const INPUT_LIMIT: usize = 6;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize)]
+pub enum Cell {
+    Zero,
+    One,
+    DontCare,
+}
+
+/// One output bit of a truth table laid out as a Karnaugh map.
+///
+/// The first half of the inputs run down the rows and the rest across the columns, each in Gray-code order
+/// so that neighbouring cells, including those across the edges, differ in exactly one input.
+#[derive(Debug, Clone, Serialize)]
+pub struct KarnaughMap {
+    pub output: String,
+    pub row_inputs: Vec<String>,
+    pub column_inputs: Vec<String>,
+    /// The values of the row and column inputs at each row and column, the first input in the most significant bit
+    pub rows: Vec<u64>,
+    pub columns: Vec<u64>,
+    /// Indexed by row, then column
+    pub cells: Vec<Vec<Cell>>,
+    /// The prime implicants of a minimal expression, each a group of ones for a sum of products or of zeros for a product of sums
+    pub groups: Vec<Group>,
+    pub expression: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Group {
+    pub term: String,
+    /// The `(row, column)` of every cell in the group
+    pub cells: Vec<(usize, usize)>,
+}
+
+impl Component {
+    /// Builds the Karnaugh map of one output bit of a truth-table component, grouped the way [`Component::minimise`] would.
+    pub fn karnaugh_map(&self, output: usize, form: Form) -> Result<KarnaughMap> {
+        let ComponentDriver::TruthTable { truth, dont_care } = &self.driver else {
+            return Err(KarnaughError::NotATruthTable(self.id).into());
+        };
+
+        let inputs = Port::bit_names(&self.inputs);
+
+        if inputs.len() > INPUT_LIMIT {
+            return Err(KarnaughError::TooManyInputs(inputs.len()).into());
+        }
+
+        let name = Port::bit_names(&self.outputs)
+            .get(output)
+            .cloned()
+            .ok_or(KarnaughError::UnknownOutput(output))?;
+
+        let (row_bits, column_bits) = (inputs.len() / 2, inputs.len() - inputs.len() / 2);
+        let gray = |bits: usize| (0..1u64 << bits)
+            .map(|index| index ^ index >> 1)
+            .collect::<Vec<_>>();
+        let (rows, columns) = (gray(row_bits), gray(column_bits));
+
+        // The row of the truth table at a cell. Input bit 0 is the first input, which is the most significant bit of the row's code.
+        let key = |row: usize, column: usize| {
+            let code = rows[row] << column_bits | columns[column];
+
+            (0..inputs.len())
+                .map(|bit| (code >> (inputs.len() - 1 - bit) & 1) << bit)
+                .sum::<u64>()
+        };
+
+        let cells = (0..rows.len())
+            .map(|row| (0..columns.len())
+                .map(|column| match key(row, column) {
+                    key if dont_care.contains(&key) => Cell::DontCare,
+                    key => match truth.get(&key).copied().unwrap_or(0) >> output & 1 {
+                        1 => Cell::One,
+                        _ => Cell::Zero,
+                    },
+                })
+                .collect())
+            .collect();
+
+        let expression = minimise::minimise(truth, dont_care, inputs.len() as u32, output as u8, form, inputs.clone());
+
+        // A sum term covers the rows where it is false
+        let covers = |term: &minimise::Term, key: u64| match form {
+            Form::SumOfProducts => (key ^ term.polarity) & term.mask == 0,
+            Form::ProductOfSums => (key ^ !term.polarity) & term.mask == 0,
+        };
+
+        let groups = expression.terms.iter()
+            .map(|term| Group {
+                term: Expression { form, inputs: inputs.clone(), terms: vec![*term] }.to_string(),
+                cells: (0..rows.len())
+                    .flat_map(|row| (0..columns.len()).map(move |column| (row, column)))
+                    .filter(|(row, column)| covers(term, key(*row, *column)))
+                    .collect(),
+            })
+            .collect();
+
+        Ok(KarnaughMap {
+            output: name,
+            row_inputs: inputs[..row_bits].to_vec(),
+            column_inputs: inputs[row_bits..].to_vec(),
+            rows,
+            columns,
+            cells,
+            groups,
+            expression: expression.to_string(),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum KarnaughError {
+    NotATruthTable(ComponentId),
+    TooManyInputs(usize),
+    UnknownOutput(usize),
+}
+
+impl std::error::Error for KarnaughError {}
+impl std::fmt::Display for KarnaughError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{
+        HashMap,
+        HashSet
+    };
+
+    fn component(inputs: Vec<Port>, truth: HashMap<u64, u64>, dont_care: HashSet<u64>) -> Component {
+        Component {
+            id: 20,
+            name: "table".to_owned(),
+            inputs,
+            outputs: vec![Port::new("q", 1)],
+            driver: ComponentDriver::TruthTable { truth, dont_care },
+            delay: 1,
+        }
+    }
+
+    #[test]
+    fn gray_code() {
+        use Cell::*;
+
+        // a & !c, with a don't-care where every input is 1
+        let inputs = ["a", "b", "c", "d"].map(|name| Port::new(name, 1)).to_vec();
+        let truth = (0..16).map(|key| (key, (key & 0b101 == 0b001) as u64)).collect();
+        let map = component(inputs, truth, HashSet::from([0b1111])).karnaugh_map(0, Form::SumOfProducts).unwrap();
+
+        assert_eq!((map.row_inputs, map.column_inputs), (vec!["a".to_owned(), "b".to_owned()], vec!["c".to_owned(), "d".to_owned()]));
+        assert_eq!((map.rows, map.columns), (vec![0b00, 0b01, 0b11, 0b10], vec![0b00, 0b01, 0b11, 0b10]));
+        assert_eq!(map.cells, vec![
+            vec![Zero, Zero, Zero, Zero],
+            vec![Zero, Zero, Zero, Zero],
+            vec![One, One, DontCare, Zero],
+            vec![One, One, Zero, Zero],
+        ]);
+
+        assert_eq!(map.expression, "a & !c");
+        assert_eq!(map.groups.len(), 1);
+        assert_eq!(map.groups[0].cells, vec![(2, 0), (2, 1), (3, 0), (3, 1)]);
+    }
+
+    #[test]
+    fn errors() {
+        let table = component(vec![Port::new("a", INPUT_LIMIT as u8 + 1)], HashMap::new(), HashSet::new());
+        assert!(table.karnaugh_map(0, Form::SumOfProducts).err().unwrap().to_string().contains("TooManyInputs(7)"));
+
+        let table = component(vec![Port::new("a", 2)], HashMap::new(), HashSet::new());
+        assert!(table.karnaugh_map(1, Form::SumOfProducts).err().unwrap().to_string().contains("UnknownOutput(1)"));
+    }
+}
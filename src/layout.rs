@@ -0,0 +1,53 @@
+use crate::project::Coord;
+
+/// Arranges the nodes of an acyclic graph into columns, each node one column to the right of its furthest predecessor.
+///
+/// `predecessors` lists the nodes feeding each node, which must all come before it.
+/// Within a column, nodes are ordered by the average row of their predecessors to keep wires from crossing,
+/// and columns are centred on each other. Positions are returned in grid units, `spacing` apart.
+pub fn layered(predecessors: &[Vec<usize>], spacing: Coord) -> Vec<Coord> {
+    let mut depth = vec![0; predecessors.len()];
+
+    for (node, inputs) in predecessors.iter().enumerate() {
+        depth[node] = inputs.iter()
+            .map(|input| depth[*input] + 1)
+            .max()
+            .unwrap_or(0);
+    }
+
+    let columns = depth.iter().max().map(|max| max + 1).unwrap_or(0);
+    let mut layers = vec![vec![]; columns];
+
+    for (node, depth) in depth.iter().enumerate() {
+        layers[*depth].push(node);
+    }
+
+    let mut row = vec![0.0; predecessors.len()];
+
+    for layer in layers.iter_mut() {
+        let barycentre = |node: usize| match predecessors[node].len() {
+            0 => node as f64,
+            count => predecessors[node].iter().map(|input| row[*input]).sum::<f64>() / count as f64,
+        };
+
+        layer.sort_by(|a, b| barycentre(*a).total_cmp(&barycentre(*b)));
+
+        for (index, node) in layer.iter().enumerate() {
+            row[*node] = index as f64;
+        }
+    }
+
+    let tallest = layers.iter().map(Vec::len).max().unwrap_or(0);
+
+    let mut positions = vec![Coord(0.0, 0.0); predecessors.len()];
+
+    for (column, layer) in layers.iter().enumerate() {
+        let offset = (tallest - layer.len()) as f64 / 2.0;
+
+        for node in layer.iter() {
+            positions[*node] = Coord(column as f64 * spacing.0, (row[*node] + offset) * spacing.1);
+        }
+    }
+
+    positions
+}
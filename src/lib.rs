@@ -1,9 +1,31 @@
 pub mod error;
 pub mod components;
 pub mod project;
+pub mod circuit;
+pub mod analysis;
+pub mod logic;
+pub mod script;
+pub mod memory;
+pub mod trace;
+pub mod stimulus;
+pub mod testbench;
+pub mod truth;
+pub mod minimise;
+pub mod expression;
+pub mod layout;
+pub mod synthesis;
+pub mod kmap;
+pub mod bdd;
+pub mod equivalence;
+pub mod interop;
 
 use std::rc::Rc;
 pub use error::*;
+// Named explicitly, since the leptos prelude exports a `Result` and an `Error` of its own
+pub use error::{
+    Error,
+    Result
+};
 use leptos::{
     prelude::*,
     web_sys::*,
@@ -11,17 +33,28 @@ use leptos::{
     svg::Svg
 };
 use crate::{
+    circuit::Circuit,
     components::*,
+    equivalence::Equivalence,
+    minimise::Form,
+    project::ComponentId,
     project::Coord,
     project::InstanceId,
     project::Project,
-    project::Terminal
+    project::Terminal,
+    stimulus::Stimulus,
+    synthesis::GateLibrary
 };
 
+// What bindings that can fail return, so that errors reach JavaScript as exceptions rather than aborting the module
+type JsResult<T> = std::result::Result<T, JsError>;
+
 #[wasm_bindgen(js_name=LogicXContext)]
 pub struct LogicX {
     project: ArcRwSignal<Project>,
     state: ArcRwSignal<State>,
+    // The circuit being simulated in play mode
+    circuit: ArcRwSignal<Option<Circuit>>,
 }
 
 #[wasm_bindgen(js_class=LogicXContext)]
@@ -31,6 +64,7 @@ impl LogicX {
         Self {
             project: ArcRwSignal::new(Project::empty()),
             state: ArcRwSignal::new(State::new()),
+            circuit: ArcRwSignal::new(None),
         }
     }
 
@@ -44,15 +78,18 @@ impl LogicX {
 
         let project = self.project.clone();
         let state = self.state.clone();
+        let circuit = self.circuit.clone();
 
         mount_to(root.unchecked_into(), move || view!(<ContextProvider cx=state.clone()>
             <ContextProvider cx=project.clone()>
+            <ContextProvider cx=circuit.clone()>
 
                 <Show when=move || state.try_read().map(|state| state.edit).unwrap_or_default()
                     fallback=move || view!(<PlayMode />)>
                     <EditMode />
                 </Show>
             </ContextProvider>
+            </ContextProvider>
         </ContextProvider>)).forget();
     }
 
@@ -73,6 +110,158 @@ impl LogicX {
         }
     }
 
+    #[wasm_bindgen(js_name=memorySources)]
+    pub fn memory_sources(&self) -> Vec<String> {
+        self.project.read_untracked().memory_sources()
+    }
+
+    #[wasm_bindgen(js_name=loadMemory)]
+    pub fn load_memory(&self, path: String, data: Vec<u8>) -> JsResult<()> {
+        self.project.write().load_memory(&path, &data)?;
+        Ok(())
+    }
+
+    /// The waveforms recorded since play mode was last entered, as a VCD file.
+    #[wasm_bindgen(js_name=getTrace)]
+    pub fn get_trace(&self) -> Option<String> {
+        self.circuit.with_untracked(|circuit| circuit.as_ref()
+            .and_then(|circuit| circuit.trace())
+            .map(|trace| trace.to_vcd()))
+    }
+
+    /// Replays a VCD or CSV file of input values against the circuit being simulated in play mode.
+    #[wasm_bindgen(js_name=applyStimulus)]
+    pub fn apply_stimulus(&self, path: String, data: String) -> JsResult<()> {
+        let result = Stimulus::parse(&path, &data)
+            .and_then(|stimulus| self.circuit.try_update(|circuit| match circuit {
+                Some(circuit) => stimulus.apply(circuit),
+                None => Ok(()),
+            }).unwrap_or(Ok(())));
+
+        Ok(result?)
+    }
+
+    /// Runs the test benches embedded in the project and describes the results, one line per failing output.
+    #[wasm_bindgen(js_name=runTests)]
+    pub fn run_tests(&self) -> JsResult<String> {
+        let reports = self.project.read_untracked().test()?;
+
+        Ok(reports.iter()
+            .map(|report| report.to_string())
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+
+    /// Minimises every output of a truth-table component, one `output = expression` line each.
+    #[wasm_bindgen(js_name=minimise)]
+    pub fn minimise(&self, component: ComponentId, product_of_sums: bool) -> JsResult<String> {
+        let form = match product_of_sums {
+            true => Form::ProductOfSums,
+            false => Form::SumOfProducts,
+        };
+
+        let expressions = self.project.with_untracked(|project| project.components.get(&component)
+            .map(|component| component.minimise(form))
+            .unwrap_or(Ok(vec![])))?;
+
+        Ok(expressions.iter()
+            .map(|(output, expression)| format!("{} = {}", output, expression))
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+
+    /// Adds gates built from the default gate library that compute `expression`, driving a new output labelled `output`.
+    #[wasm_bindgen(js_name=synthesise)]
+    pub fn synthesise(&self, expression: String, output: String) -> JsResult<()> {
+        self.project.write().synthesise(&expression, &output, &GateLibrary::default())?;
+        Ok(())
+    }
+
+    /// Stores a truth-table component as BDDs instead of row by row.
+    #[wasm_bindgen(js_name=convertToBdd)]
+    pub fn convert_to_bdd(&self, component: ComponentId) -> JsResult<()> {
+        self.project.write().convert_to_bdd(component)?;
+        Ok(())
+    }
+
+    /// Checks whether two components compute the same outputs, describing an input on which they differ if they don't.
+    #[wasm_bindgen(js_name=checkEquivalence)]
+    pub fn check_equivalence(&self, left: ComponentId, right: ComponentId) -> JsResult<Option<String>> {
+        match self.project.with_untracked(|project| project.equivalent(left, right))? {
+            Equivalence::Equivalent => Ok(None),
+            Equivalence::Counterexample(counterexample) => Ok(Some(counterexample.to_string())),
+        }
+    }
+
+    /// The project as Verilog, its top level named `top`.
+    #[wasm_bindgen(js_name=exportVerilog)]
+    pub fn export_verilog(&self, top: String) -> JsResult<String> {
+        Ok(self.project.with_untracked(|project| project.export_verilog(&top))?)
+    }
+
+    /// Every component as VHDL, the top level as the entity `top`.
+    #[wasm_bindgen(js_name=exportVhdl)]
+    pub fn export_vhdl(&self, top: String) -> JsResult<String> {
+        Ok(self.project.with_untracked(|project| project.export_vhdl(&top))?)
+    }
+
+    /// Replaces the project with one read from gate-level Verilog.
+    #[wasm_bindgen(js_name=importVerilog)]
+    pub fn import_verilog(&self, source: String) -> JsResult<()> {
+        self.project.set(Project::import_verilog(&source)?);
+        Ok(())
+    }
+
+    /// The project as BLIF, its top level the model `top`.
+    #[wasm_bindgen(js_name=exportBlif)]
+    pub fn export_blif(&self, top: String) -> JsResult<String> {
+        Ok(self.project.with_untracked(|project| project.export_blif(&top))?)
+    }
+
+    /// Replaces the project with one read from BLIF.
+    #[wasm_bindgen(js_name=importBlif)]
+    pub fn import_blif(&self, source: String) -> JsResult<()> {
+        self.project.set(Project::import_blif(&source)?);
+        Ok(())
+    }
+
+    /// Replaces the project with one read from a Yosys JSON netlist.
+    #[wasm_bindgen(js_name=importYosys)]
+    pub fn import_yosys(&self, source: String) -> JsResult<()> {
+        self.project.set(Project::import_yosys(&source)?);
+        Ok(())
+    }
+
+    /// Replaces the project with one read from a Logisim `.circ` file, returning the elements it had no equivalent for as JSON.
+    #[wasm_bindgen(js_name=importLogisim)]
+    pub fn import_logisim(&self, source: String) -> JsResult<String> {
+        let (project, untranslated) = Project::import_logisim(&source)?;
+        self.project.set(project);
+
+        Ok(serde_json::to_string(&untranslated).expect("Failed to serialise untranslated elements"))
+    }
+
+    /// The Karnaugh map of one output bit of a truth-table component, as JSON.
+    #[wasm_bindgen(js_name=karnaughMap)]
+    pub fn karnaugh_map(&self, component: ComponentId, output: usize, product_of_sums: bool) -> JsResult<Option<String>> {
+        let form = match product_of_sums {
+            true => Form::ProductOfSums,
+            false => Form::SumOfProducts,
+        };
+
+        let map = self.project.with_untracked(|project| project.components.get(&component)
+            .map(|component| component.karnaugh_map(output, form)))
+            .transpose()?;
+
+        Ok(map.map(|map| serde_json::to_string(&map).expect("Failed to serialise Karnaugh map")))
+    }
+
+    /// Draws the Karnaugh map of a truth-table component over the editor, or hides it.
+    #[wasm_bindgen(js_name=showKarnaughMap)]
+    pub fn show_karnaugh_map(&self, component: Option<ComponentId>, output: usize) {
+        self.state.update(|state| state.karnaugh = component.map(|component| (component, output)));
+    }
+
     #[wasm_bindgen(js_name=clear)]
     pub fn clear(&mut self) {
         self.project.set(Project::empty());
@@ -113,7 +302,10 @@ pub struct State {
 
     pub(crate) viewport: NodeRef<Svg>,
 
-    pub(crate) edit: bool
+    pub(crate) edit: bool,
+
+    // The truth-table component and output bit whose Karnaugh map is drawn over the editor
+    pub(crate) karnaugh: Option<(ComponentId, usize)>,
 }
 
 #[wasm_bindgen(js_class=LogicXState)]
@@ -126,7 +318,8 @@ impl State {
             snap: true,
             scroll: (0.0, 0.0).into(),
             start_connect_wire: None,
-            edit: true
+            edit: true,
+            karnaugh: None,
         }
     }
 
@@ -0,0 +1,327 @@
+use std::{
+    fmt::Display,
+    fmt::Formatter,
+    ops::BitAnd,
+    ops::BitOr,
+    ops::BitXor,
+    ops::Not,
+    str::FromStr
+};
+
+/// The state of a single bit.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Logic {
+    Zero,
+    One,
+    /// Unknown: could be either 0 or 1, for example because it was never initialised or two values conflict.
+    X,
+    /// High impedance: nothing is driving the bit.
+    Z,
+}
+
+impl Logic {
+    pub fn is_known(self) -> bool {
+        matches!(self, Self::Zero | Self::One)
+    }
+}
+
+impl From<bool> for Logic {
+    fn from(value: bool) -> Self {
+        match value {
+            true => Self::One,
+            false => Self::Zero,
+        }
+    }
+}
+
+impl Display for Logic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Zero => write!(f, "0"),
+            Self::One => write!(f, "1"),
+            Self::X => write!(f, "x"),
+            Self::Z => write!(f, "z"),
+        }
+    }
+}
+
+/// A bus of up to 64 four-valued bits.
+///
+/// Each bit is stored across two planes: `0` is `(0, 0)`, `1` is `(1, 0)`, `X` is `(0, 1)` and `Z` is `(1, 1)`,
+/// written as `(bits, unknown)`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Value {
+    width: u8,
+    bits: u64,
+    unknown: u64,
+}
+
+impl Value {
+    pub fn new(width: u8, bits: u64) -> Self {
+        Self::from_planes(width, bits, 0)
+    }
+
+    pub fn x(width: u8) -> Self {
+        Self::from_planes(width, 0, u64::MAX)
+    }
+
+    pub fn z(width: u8) -> Self {
+        Self::from_planes(width, u64::MAX, u64::MAX)
+    }
+
+    pub(crate) fn from_planes(width: u8, bits: u64, unknown: u64) -> Self {
+        let width = width.min(64);
+
+        Self {
+            width,
+            bits: bits & mask(width),
+            unknown: unknown & mask(width),
+        }
+    }
+
+    pub fn width(&self) -> u8 {
+        self.width
+    }
+
+    pub(crate) fn bits(&self) -> u64 {
+        self.bits
+    }
+
+    // A mask of every bit that is either X or Z
+    pub(crate) fn unknown(&self) -> u64 {
+        self.unknown
+    }
+
+    pub fn get(&self, bit: u8) -> Logic {
+        match ((self.bits >> bit) & 1, (self.unknown >> bit) & 1) {
+            (0, 0) => Logic::Zero,
+            (_, 0) => Logic::One,
+            (0, _) => Logic::X,
+            _ => Logic::Z,
+        }
+    }
+
+    pub fn set(&mut self, bit: u8, logic: Logic) {
+        let (value, unknown) = match logic {
+            Logic::Zero => (0, 0),
+            Logic::One => (1, 0),
+            Logic::X => (0, 1),
+            Logic::Z => (1, 1),
+        };
+
+        self.bits = self.bits & !(1 << bit) | value << bit;
+        self.unknown = self.unknown & !(1 << bit) | unknown << bit;
+    }
+
+    pub fn is_known(&self) -> bool {
+        self.unknown == 0
+    }
+
+    /// The value as an integer, if none of its bits are X or Z.
+    pub fn to_u64(&self) -> Option<u64> {
+        self.is_known().then_some(self.bits)
+    }
+
+    /// Extracts `width` bits starting at `offset`.
+    pub fn slice(&self, offset: u8, width: u8) -> Self {
+        Self::from_planes(width, self.bits.checked_shr(offset as u32).unwrap_or(0), self.unknown.checked_shr(offset as u32).unwrap_or(0))
+    }
+
+    /// Places `high` above the bits of this value.
+    pub fn concat(&self, high: Self) -> Self {
+        let shift = |value: u64| value.checked_shl(self.width as u32).unwrap_or(0);
+
+        Self::from_planes(self.width.saturating_add(high.width), self.bits | shift(high.bits), self.unknown | shift(high.unknown))
+    }
+
+    /// Reads high-impedance bits as unknown, which is how they appear at the input of a gate.
+    pub fn resolve(&self) -> Self {
+        Self::from_planes(self.width, self.bits & !self.unknown, self.unknown)
+    }
+
+    /// Keeps the bits on which both values agree and makes the rest unknown.
+    pub fn merge(&self, other: Self) -> Self {
+        let (a, b) = (self.resolve(), other.resolve());
+        let differ = a.bits ^ b.bits | a.unknown | b.unknown;
+
+        Self::from_planes(self.width, a.bits & !differ, differ)
+    }
+
+    // Masks of the bits that are known to be 0 and known to be 1
+    fn known(&self) -> (u64, u64) {
+        (!self.bits & !self.unknown & mask(self.width), self.bits & !self.unknown)
+    }
+}
+
+// The bitwise operators follow the usual rules for unknowns: a known 0 forces an AND low and a known 1 forces an OR high,
+// while anything else involving X or Z is X.
+impl BitAnd for Value {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        let ((a0, a1), (b0, b1)) = (self.known(), rhs.known());
+        let (zero, one) = (a0 | b0, a1 & b1);
+
+        Self::from_planes(self.width.max(rhs.width), one, !(zero | one))
+    }
+}
+
+impl BitOr for Value {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        let ((a0, a1), (b0, b1)) = (self.known(), rhs.known());
+        let (zero, one) = (a0 & b0, a1 | b1);
+
+        Self::from_planes(self.width.max(rhs.width), one, !(zero | one))
+    }
+}
+
+impl BitXor for Value {
+    type Output = Self;
+
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        let unknown = self.unknown | rhs.unknown;
+
+        Self::from_planes(self.width.max(rhs.width), (self.bits ^ rhs.bits) & !unknown, unknown)
+    }
+}
+
+impl Not for Value {
+    type Output = Self;
+
+    fn not(self) -> Self::Output {
+        Self::from_planes(self.width, !self.bits & !self.unknown, self.unknown)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Self::new(1, value as u64)
+    }
+}
+
+impl From<u64> for Value {
+    fn from(value: u64) -> Self {
+        Self::new(64, value)
+    }
+}
+
+impl From<Logic> for Value {
+    fn from(logic: Logic) -> Self {
+        let mut value = Self::new(1, 0);
+        value.set(0, logic);
+        value
+    }
+}
+
+// Written most significant bit first, the way it would appear in a waveform viewer
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for bit in (0..self.width).rev() {
+            write!(f, "{}", self.get(bit))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Value {
+    type Err = ParseValueError;
+
+    /// Parses a string of `0`, `1`, `x` and `z` digits, most significant first. Underscores are ignored.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s.chars()
+            .filter(|c| *c != '_')
+            .collect::<Vec<_>>();
+
+        if digits.is_empty() || digits.len() > 64 {
+            return Err(ParseValueError(s.to_owned()));
+        }
+
+        let mut value = Self::new(digits.len() as u8, 0);
+
+        for (bit, digit) in digits.iter().rev().enumerate() {
+            value.set(bit as u8, match digit {
+                '0' => Logic::Zero,
+                '1' => Logic::One,
+                'x' | 'X' => Logic::X,
+                'z' | 'Z' => Logic::Z,
+                _ => return Err(ParseValueError(s.to_owned())),
+            });
+        }
+
+        Ok(value)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ParseValueError(pub String);
+
+impl std::error::Error for ParseValueError {}
+impl std::fmt::Display for ParseValueError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+pub(crate) fn mask(width: u8) -> u64 {
+    match width {
+        64.. => u64::MAX,
+        width => (1 << width) - 1,
+    }
+}
+
+// Reads a whole number written in decimal, or in hex or binary with a `0x` or `0b` prefix. Underscores are ignored.
+pub(crate) fn number(text: &str) -> Option<u64> {
+    let digits = text.replace('_', "");
+
+    match digits.get(..2) {
+        Some("0x" | "0X") => u64::from_str_radix(&digits[2..], 16).ok(),
+        Some("0b" | "0B") => u64::from_str_radix(&digits[2..], 2).ok(),
+        _ => digits.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(digits: &str) -> Value {
+        digits.parse().unwrap()
+    }
+
+    #[test]
+    fn parsing() {
+        assert_eq!(value("1x0z").to_string(), "1x0z");
+        assert_eq!(value("1x0z").get(0), Logic::Z);
+        assert_eq!(value("1x0z").get(2), Logic::X);
+        assert_eq!(value("1010_0101").to_u64(), Some(0xa5));
+        assert_eq!(value("10x").to_u64(), None);
+
+        assert!("".parse::<Value>().is_err());
+        assert!("102".parse::<Value>().is_err());
+        assert!("1".repeat(65).parse::<Value>().is_err());
+    }
+
+    #[test]
+    fn operators() {
+        // Each bit of the left operand against 0, 1, x and z in turn
+        let (lhs, rhs) = (value("0000_1111_xxxx_zzzz"), value("01xz_01xz_01xz_01xz"));
+
+        assert_eq!((lhs & rhs).to_string(), "0000_01xx_0xxx_0xxx".replace('_', ""));
+        assert_eq!((lhs | rhs).to_string(), "01xx_1111_x1xx_x1xx".replace('_', ""));
+        assert_eq!((lhs ^ rhs).to_string(), "01xx_10xx_xxxx_xxxx".replace('_', ""));
+        assert_eq!((!rhs).to_string(), "10xx_10xx_10xx_10xx".replace('_', ""));
+    }
+
+    #[test]
+    fn bits() {
+        assert_eq!(value("1x0z").slice(1, 2).to_string(), "x0");
+        assert_eq!(value("1x0z").slice(3, 4).to_string(), "0001");
+        assert_eq!(Value::new(2, 1).concat(Value::new(3, 5)).to_string(), "10101");
+        assert_eq!(value("z1").resolve().to_string(), "x1");
+        assert_eq!(value("0011").merge(value("0101")).to_string(), "0xx1");
+        assert_eq!(Value::new(4, 0xff).to_u64(), Some(0xf));
+    }
+}
@@ -0,0 +1,230 @@
+use crate::{
+    logic::mask,
+    logic::number,
+    Result
+};
+
+/// The widest address a memory may have, which puts an upper bound on how many words it holds.
+pub const ADDRESS_LIMIT: u8 = 16;
+
+/// The formats a memory image can be loaded from.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum MemoryFormat {
+    /// Intel HEX records, addressed in bytes.
+    IntelHex,
+    /// The words back to back, each taking as many whole bytes as it needs, least significant byte first.
+    Binary,
+    /// One word per cell, separated by commas or line breaks. Words are decimal unless prefixed with `0x` or `0b`.
+    Csv,
+}
+
+impl MemoryFormat {
+    /// Picks a format by the extension of a file, falling back to raw binary.
+    pub fn from_path(path: &str) -> Self {
+        let extension = path.rsplit_once('.')
+            .map(|(_, extension)| extension.to_ascii_lowercase())
+            .unwrap_or_default();
+
+        match extension.as_str() {
+            "hex" | "ihex" | "ihx" => Self::IntelHex,
+            "csv" => Self::Csv,
+            _ => Self::Binary,
+        }
+    }
+}
+
+/// Parses a memory image into words `data` bits wide, for a memory with an `address`-bit address.
+///
+/// Words the image doesn't mention are 0.
+pub fn load(format: MemoryFormat, bytes: &[u8], address: u8, data: u8) -> Result<Vec<u64>> {
+    let capacity = 1usize << address.min(ADDRESS_LIMIT);
+
+    let words = match format {
+        MemoryFormat::IntelHex => pack(&hex(bytes)?, data, capacity)?,
+        MemoryFormat::Binary => pack(&bytes.iter().copied().enumerate().collect::<Vec<_>>(), data, capacity)?,
+        MemoryFormat::Csv => csv(bytes, data)?,
+    };
+
+    match words.len() > capacity {
+        true => Err(MemoryError::TooLarge { words: words.len(), capacity }.into()),
+        false => Ok(words),
+    }
+}
+
+// Gathers addressed bytes into words, least significant byte first. Fails on a byte past the last of `capacity` words,
+// before making room for it, since the extended address records of a HEX file can point anywhere in 4GiB.
+fn pack(bytes: &[(usize, u8)], data: u8, capacity: usize) -> Result<Vec<u64>> {
+    let size = (data as usize).div_ceil(8).max(1);
+    let mut words = vec![];
+
+    for (address, byte) in bytes.iter().copied() {
+        let (word, offset) = (address / size, address % size);
+
+        if word >= capacity {
+            return Err(MemoryError::TooLarge { words: word + 1, capacity }.into());
+        }
+
+        if words.len() <= word {
+            words.resize(word + 1, 0);
+        }
+
+        words[word] |= (byte as u64).checked_shl(offset as u32 * 8).unwrap_or(0);
+    }
+
+    Ok(words.iter()
+        .map(|word| word & mask(data))
+        .collect())
+}
+
+fn hex(bytes: &[u8]) -> Result<Vec<(usize, u8)>> {
+    let text = std::str::from_utf8(bytes)
+        .map_err(|_| MemoryError::InvalidRecord(1))?;
+
+    let mut result = vec![];
+    // Added to every record's address by the extended segment and extended linear address records
+    let mut base = 0;
+
+    for (line, record) in text.lines().enumerate().map(|(index, record)| (index + 1, record.trim())) {
+        if record.is_empty() {
+            continue;
+        }
+
+        let digits = record.strip_prefix(':')
+            .filter(|digits| digits.len() >= 10 && digits.len() % 2 == 0)
+            .ok_or(MemoryError::InvalidRecord(line))?;
+
+        let record = (0..digits.len())
+            .step_by(2)
+            .map(|index| u8::from_str_radix(&digits[index..index + 2], 16))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|_| MemoryError::InvalidRecord(line))?;
+
+        if record.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) != 0 {
+            return Err(MemoryError::Checksum(line).into());
+        }
+
+        let (length, offset, kind) = (record[0] as usize, u16::from_be_bytes([record[1], record[2]]) as usize, record[3]);
+
+        let Some(payload) = record.get(4..4 + length).filter(|_| record.len() == length + 5) else {
+            return Err(MemoryError::InvalidRecord(line).into());
+        };
+
+        match (kind, payload) {
+            (0x00, payload) => result.extend(payload.iter()
+                .enumerate()
+                .map(|(index, byte)| (base + offset + index, *byte))),
+            (0x01, _) => break,
+            (0x02, [high, low]) => base = (u16::from_be_bytes([*high, *low]) as usize) << 4,
+            (0x04, [high, low]) => base = (u16::from_be_bytes([*high, *low]) as usize) << 16,
+            // Start addresses mean nothing to a memory
+            (0x03 | 0x05, _) => {},
+            _ => return Err(MemoryError::InvalidRecord(line).into()),
+        }
+    }
+
+    Ok(result)
+}
+
+fn csv(bytes: &[u8], data: u8) -> Result<Vec<u64>> {
+    let text = std::str::from_utf8(bytes)
+        .map_err(|_| MemoryError::InvalidValue { line: 1, value: String::new() })?;
+
+    let mut words = vec![];
+
+    for (line, row) in text.lines().enumerate().map(|(index, row)| (index + 1, row)) {
+        for cell in row.split(',').map(str::trim).filter(|cell| !cell.is_empty()) {
+            match number(cell) {
+                Some(word) if word & !mask(data) == 0 => words.push(word),
+                _ => return Err(MemoryError::InvalidValue { line, value: cell.to_owned() }.into()),
+            }
+        }
+    }
+
+    Ok(words)
+}
+
+#[derive(Debug, Clone)]
+pub enum MemoryError {
+    /// The line of an Intel HEX file that isn't a well-formed record
+    InvalidRecord(usize),
+    /// The line of an Intel HEX file whose checksum doesn't add up
+    Checksum(usize),
+    /// A CSV cell that isn't a number, or doesn't fit in a word
+    InvalidValue { line: usize, value: String },
+    TooLarge { words: usize, capacity: usize },
+}
+
+impl std::error::Error for MemoryError {}
+impl std::fmt::Display for MemoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::{
+        ComponentDriver,
+        MemoryKind,
+        Project
+    };
+
+    fn error(format: MemoryFormat, bytes: &[u8], address: u8, data: u8) -> String {
+        load(format, bytes, address, data).err().unwrap().to_string()
+    }
+
+    #[test]
+    fn formats() {
+        assert_eq!(MemoryFormat::from_path("program.HEX"), MemoryFormat::IntelHex);
+        assert_eq!(MemoryFormat::from_path("table.csv"), MemoryFormat::Csv);
+        assert_eq!(MemoryFormat::from_path("image"), MemoryFormat::Binary);
+    }
+
+    #[test]
+    fn intel_hex() {
+        // Two bytes at 0x0001, a start address, then the end of file
+        let image = b":020001000102FA\n:0400000300000000F9\n:00000001FF\n";
+        assert_eq!(load(MemoryFormat::IntelHex, image, 4, 8).unwrap(), vec![0, 1, 2]);
+        assert_eq!(load(MemoryFormat::IntelHex, image, 4, 16).unwrap(), vec![0x100, 0x2]);
+
+        assert!(error(MemoryFormat::IntelHex, b":020001000102FB\n", 4, 8).contains("Checksum(1)"));
+        assert!(error(MemoryFormat::IntelHex, b"\n020001000102FA\n", 4, 8).contains("InvalidRecord(2)"));
+        assert!(error(MemoryFormat::IntelHex, b":030001000102F9\n", 4, 8).contains("InvalidRecord(1)"));
+
+        // An extended linear address record moves the byte after it to 0x10000000
+        let image = b":020000041000EA\n:0100000001FE\n:00000001FF\n";
+        assert!(error(MemoryFormat::IntelHex, image, 8, 8).contains("TooLarge { words: 268435457, capacity: 256 }"));
+    }
+
+    #[test]
+    fn binary() {
+        assert_eq!(load(MemoryFormat::Binary, &[1, 2, 3, 4], 2, 8).unwrap(), vec![1, 2, 3, 4]);
+        assert_eq!(load(MemoryFormat::Binary, &[1, 2, 3, 4], 2, 16).unwrap(), vec![0x201, 0x403]);
+        assert_eq!(load(MemoryFormat::Binary, &[0xff, 0xff], 2, 12).unwrap(), vec![0xfff]);
+        assert!(error(MemoryFormat::Binary, &[0; 5], 2, 8).contains("TooLarge { words: 5, capacity: 4 }"));
+    }
+
+    #[test]
+    fn csv() {
+        assert_eq!(load(MemoryFormat::Csv, b"1, 0x2,0b11\n\n255,", 4, 8).unwrap(), vec![1, 2, 3, 255]);
+        assert!(error(MemoryFormat::Csv, b"1\n256", 4, 8).contains("InvalidValue { line: 2, value: \"256\" }"));
+        assert!(error(MemoryFormat::Csv, b"1, two", 4, 8).contains("InvalidValue { line: 1, value: \"two\" }"));
+        assert!(error(MemoryFormat::Csv, b"1,2,3", 1, 8).contains("TooLarge { words: 3, capacity: 2 }"));
+    }
+
+    #[test]
+    fn too_wide() {
+        let mut project = Project::empty();
+        project.components.get_mut(&Project::ROM).unwrap().driver = ComponentDriver::Memory {
+            kind: MemoryKind::Rom,
+            address: 8,
+            data: 72,
+            source: Some("rom.bin".into()),
+            contents: vec![],
+        };
+
+        let error = project.load_memory("rom.bin", &[0; 16]).err().unwrap().to_string();
+        assert!(error.contains("TooWide(11)"));
+    }
+}
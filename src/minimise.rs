@@ -0,0 +1,449 @@
+use crate::{
+    expression::Expr,
+    logic::mask,
+    project::Component,
+    project::ComponentDriver,
+    project::ComponentId,
+    project::Port,
+    Result
+};
+use std::{
+    collections::HashMap,
+    collections::HashSet,
+    fmt::Display,
+    fmt::Formatter
+};
+
+/// Tables with up to this many input bits are minimised exactly with Quine–McCluskey.
+/// Larger ones are minimised with a faster heuristic in the style of Espresso, which may not find the smallest expression.
+pub const EXACT_LIMIT: u32 = 8;
+
+/// Tables with more input bits than this are not minimised at all, as every row has to be enumerated.
+pub const WIDTH_LIMIT: u32 = 20;
+
+// How many partial covers the exact search may try before settling for the best found so far
+const SEARCH_LIMIT: usize = 10_000;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Form {
+    /// An OR of ANDs, built from the rows where the output is 1
+    SumOfProducts,
+    /// An AND of ORs, built from the rows where the output is 0
+    ProductOfSums,
+}
+
+/// A product or sum of literals. Input bit `n` appears in the term if bit `n` of `mask` is set,
+/// negated unless bit `n` of `polarity` is set too.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Term {
+    pub mask: u64,
+    pub polarity: u64,
+}
+
+/// A two-level expression over the input bits of a truth table.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Expression {
+    pub form: Form,
+    /// The name of each input bit, least significant first
+    pub inputs: Vec<String>,
+    pub terms: Vec<Term>,
+}
+
+impl Expression {
+    pub fn evaluate(&self, input: u64) -> bool {
+        let literals = |term: &Term| !(input ^ term.polarity) & term.mask;
+
+        match self.form {
+            Form::SumOfProducts => self.terms.iter().any(|term| literals(term) == term.mask),
+            Form::ProductOfSums => self.terms.iter().all(|term| literals(term) != 0),
+        }
+    }
+
+    pub fn literals(&self) -> u32 {
+        self.terms.iter()
+            .map(|term| term.mask.count_ones())
+            .sum()
+    }
+
+    /// The same function as an expression tree, with terms and literals in the order they are displayed.
+    pub fn to_expr(&self) -> Expr {
+        let (and, or) = (Expr::And as fn(_, _) -> _, Expr::Or as fn(_, _) -> _);
+        let (inner, outer) = match self.form {
+            Form::SumOfProducts => (and, or),
+            Form::ProductOfSums => (or, and),
+        };
+        let identity = self.form == Form::SumOfProducts;
+
+        self.terms.iter()
+            .map(|term| (0..self.inputs.len())
+                .filter(|bit| term.mask >> bit & 1 == 1)
+                .map(|bit| match term.polarity >> bit & 1 {
+                    1 => Expr::Variable(self.inputs[bit].clone()),
+                    _ => Expr::Not(Box::new(Expr::Variable(self.inputs[bit].clone()))),
+                })
+                .reduce(|lhs, rhs| inner(Box::new(lhs), Box::new(rhs)))
+                .unwrap_or(Expr::Constant(identity)))
+            .reduce(|lhs, rhs| outer(Box::new(lhs), Box::new(rhs)))
+            .unwrap_or(Expr::Constant(!identity))
+    }
+}
+
+// Written with the same operators the expression parser accepts: `!`, `&` and `|`
+impl Display for Expression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (inner, outer, empty, constant) = match self.form {
+            Form::SumOfProducts => (" & ", " | ", "0", "1"),
+            Form::ProductOfSums => (" | ", " & ", "1", "0"),
+        };
+
+        if self.terms.is_empty() {
+            return write!(f, "{}", empty);
+        }
+
+        let terms = self.terms.iter()
+            .map(|term| {
+                let literals = (0..self.inputs.len())
+                    .filter(|bit| term.mask >> bit & 1 == 1)
+                    .map(|bit| match term.polarity >> bit & 1 {
+                        1 => self.inputs[bit].clone(),
+                        _ => format!("!{}", self.inputs[bit]),
+                    })
+                    .collect::<Vec<_>>();
+
+                match literals.len() {
+                    0 => constant.to_owned(),
+                    1 => literals[0].clone(),
+                    _ if self.terms.len() == 1 || self.form == Form::SumOfProducts => literals.join(inner),
+                    _ => format!("({})", literals.join(inner)),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        write!(f, "{}", terms.join(outer))
+    }
+}
+
+impl Component {
+    /// Minimises every output bit of a truth-table component, naming each bit after its port.
+    ///
+    /// Rows missing from the table are taken to output 0, as they do in simulation,
+    /// while the outputs of don't-care rows are chosen however gives the smallest expression.
+    pub fn minimise(&self, form: Form) -> Result<Vec<(String, Expression)>> {
+        let ComponentDriver::TruthTable { truth, dont_care } = &self.driver else {
+            return Err(MinimiseError::NotATruthTable(self.id).into());
+        };
+
+        let inputs = Port::bit_names(&self.inputs);
+        let width = inputs.len() as u32;
+
+        if width > WIDTH_LIMIT {
+            return Err(MinimiseError::TooManyInputs(width).into());
+        }
+
+        Ok(Port::bit_names(&self.outputs)
+            .into_iter()
+            .enumerate()
+            .map(|(bit, output)| (output, minimise(truth, dont_care, width, bit as u8, form, inputs.clone())))
+            .collect())
+    }
+}
+
+/// Minimises one output bit of a truth table with `width` input bits.
+pub fn minimise(truth: &HashMap<u64, u64>, dont_care: &HashSet<u64>, width: u32, output: u8, form: Form, inputs: Vec<String>) -> Expression {
+    let wanted = match form {
+        Form::SumOfProducts => 1,
+        Form::ProductOfSums => 0,
+    };
+
+    // The rows the expression has to cover, and the rows it may cover
+    let mut on = vec![];
+    let mut allowed = vec![false; 1 << width];
+
+    for row in 0..1u64 << width {
+        if dont_care.contains(&row) {
+            allowed[row as usize] = true;
+        } else if truth.get(&row).copied().unwrap_or(0) >> output & 1 == wanted {
+            allowed[row as usize] = true;
+            on.push(row);
+        }
+    }
+
+    let cubes = match width <= EXACT_LIMIT {
+        true => exact(&on, &allowed, width),
+        false => heuristic(&on, &allowed, width),
+    };
+
+    let mut terms = cubes.into_iter()
+        .map(|cube| Term {
+            mask: cube.fixed,
+            polarity: match form {
+                Form::SumOfProducts => cube.value,
+                // A sum term is false exactly on the rows of the cube
+                Form::ProductOfSums => !cube.value & cube.fixed,
+            },
+        })
+        .collect::<Vec<_>>();
+
+    terms.sort_by_key(|term| (term.mask.count_ones(), term.mask, term.polarity));
+
+    Expression { form, inputs, terms }
+}
+
+// A set of rows which agree on the `fixed` bits, taking their values from `value`
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+struct Cube {
+    value: u64,
+    fixed: u64,
+}
+
+impl Cube {
+    fn contains(&self, row: u64) -> bool {
+        row & self.fixed == self.value
+    }
+
+    // Every row in the cube, counting down through the subsets of its free bits
+    fn rows(&self, width: u32) -> impl Iterator<Item=u64> {
+        let (value, free) = (self.value, mask(width as u8) & !self.fixed);
+
+        std::iter::successors(Some(free), move |subset| (*subset != 0).then(|| (subset - 1) & free))
+            .map(move |subset| value | subset)
+    }
+
+    // Whether every row in the cube is allowed
+    fn inside(&self, allowed: &[bool], width: u32) -> bool {
+        self.rows(width).all(|row| allowed[row as usize])
+    }
+}
+
+// Quine–McCluskey: find every prime implicant, then the smallest set of them covering every row
+fn exact(on: &[u64], allowed: &[bool], width: u32) -> Vec<Cube> {
+    let full = mask(width as u8);
+
+    let mut current = allowed.iter()
+        .enumerate()
+        .filter(|(_, allowed)| **allowed)
+        .map(|(row, _)| Cube { value: row as u64, fixed: full })
+        .collect::<HashSet<_>>();
+    let mut primes = vec![];
+
+    while !current.is_empty() {
+        let mut next = HashSet::new();
+        let mut merged = HashSet::new();
+
+        for cube in current.iter() {
+            for bit in (0..width).map(|bit| 1 << bit).filter(|bit| cube.fixed & bit != 0) {
+                let neighbour = Cube { value: cube.value ^ bit, fixed: cube.fixed };
+
+                if current.contains(&neighbour) {
+                    next.insert(Cube { value: cube.value & !bit, fixed: cube.fixed & !bit });
+                    merged.insert(*cube);
+                    merged.insert(neighbour);
+                }
+            }
+        }
+
+        primes.extend(current.difference(&merged).copied());
+        current = next;
+    }
+
+    // Larger cubes first, so that the search tries the cheapest covers early
+    primes.sort_by_key(|cube| (cube.fixed.count_ones(), cube.fixed, cube.value));
+
+    // The primes covering each row
+    let covers = on.iter()
+        .map(|row| (*row, (0..primes.len()).filter(|prime| primes[*prime].contains(*row)).collect()))
+        .collect::<HashMap<_, Vec<_>>>();
+
+    let mut best = greedy(on, &primes);
+    let mut budget = SEARCH_LIMIT;
+    search(on, &primes, &covers, &mut vec![], &mut best, &mut budget);
+
+    best
+}
+
+// Picks the cube that covers the most uncovered rows until every row is covered
+fn greedy(on: &[u64], cubes: &[Cube]) -> Vec<Cube> {
+    let mut uncovered = on.to_vec();
+    let mut chosen = vec![];
+
+    while !uncovered.is_empty() {
+        let Some(cube) = cubes.iter().max_by_key(|cube| uncovered.iter().filter(|row| cube.contains(**row)).count()) else {
+            break;
+        };
+
+        uncovered.retain(|row| !cube.contains(*row));
+        chosen.push(*cube);
+    }
+
+    chosen
+}
+
+// Branch and bound over covers, always branching on the uncovered row with the fewest cubes that could cover it
+fn search(uncovered: &[u64], cubes: &[Cube], covers: &HashMap<u64, Vec<usize>>, chosen: &mut Vec<Cube>, best: &mut Vec<Cube>, budget: &mut usize) {
+    let cost = |cover: &[Cube]| (cover.len(), cover.iter().map(|cube| cube.fixed.count_ones()).sum::<u32>());
+
+    if *budget == 0 {
+        return;
+    }
+    *budget -= 1;
+
+    let Some(row) = uncovered.iter().min_by_key(|row| covers[row].len()) else {
+        if cost(chosen) < cost(best) {
+            *best = chosen.clone();
+        }
+
+        return;
+    };
+
+    if chosen.len() + 1 > best.len() {
+        return;
+    }
+
+    for cube in covers[row].iter().map(|cube| &cubes[*cube]) {
+        let remaining = uncovered.iter()
+            .copied()
+            .filter(|row| !cube.contains(*row))
+            .collect::<Vec<_>>();
+
+        chosen.push(*cube);
+        search(&remaining, cubes, covers, chosen, best, budget);
+        chosen.pop();
+    }
+}
+
+// Grows each row into as large a cube as it can without covering a forbidden row, then drops cubes that aren't needed
+fn heuristic(on: &[u64], allowed: &[bool], width: u32) -> Vec<Cube> {
+    let full = mask(width as u8);
+    let mut cubes: Vec<Cube> = vec![];
+    // How many of the cubes contain each row
+    let mut covers = vec![0u32; allowed.len()];
+
+    for row in on.iter().copied() {
+        if covers[row as usize] > 0 {
+            continue;
+        }
+
+        let mut cube = Cube { value: row, fixed: full };
+
+        for bit in (0..width).map(|bit| 1 << bit) {
+            let larger = Cube { value: cube.value & !bit, fixed: cube.fixed & !bit };
+
+            if larger.inside(allowed, width) {
+                cube = larger;
+            }
+        }
+
+        for row in cube.rows(width) {
+            covers[row as usize] += 1;
+        }
+
+        cubes.push(cube);
+    }
+
+    let mut required = vec![false; allowed.len()];
+    for row in on.iter() {
+        required[*row as usize] = true;
+    }
+
+    // Smallest cubes are the likeliest to be redundant
+    cubes.sort_by_key(|cube| std::cmp::Reverse(cube.fixed.count_ones()));
+
+    cubes.retain(|cube| {
+        let redundant = cube.rows(width).all(|row| !required[row as usize] || covers[row as usize] > 1);
+
+        if redundant {
+            for row in cube.rows(width) {
+                covers[row as usize] -= 1;
+            }
+        }
+
+        !redundant
+    });
+
+    cubes
+}
+
+#[derive(Debug, Clone)]
+pub enum MinimiseError {
+    NotATruthTable(ComponentId),
+    TooManyInputs(u32),
+}
+
+impl std::error::Error for MinimiseError {}
+impl std::fmt::Display for MinimiseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(width: u32) -> Vec<String> {
+        (0..width).map(|bit| ((b'a' + bit as u8) as char).to_string()).collect()
+    }
+
+    // Minimises `f` over every row, checking the result agrees with it wherever it cares
+    fn check(width: u32, f: impl Fn(u64) -> bool, dont_care: impl Fn(u64) -> bool, form: Form) -> Expression {
+        let truth = (0..1u64 << width).map(|row| (row, f(row) as u64)).collect();
+        let dont_care = (0..1u64 << width).filter(|row| dont_care(*row)).collect();
+        let expression = minimise(&truth, &dont_care, width, 0, form, names(width));
+
+        for row in (0..1u64 << width).filter(|row| !dont_care.contains(row)) {
+            assert_eq!(expression.evaluate(row), f(row), "row {:b} of {}", row, expression);
+        }
+
+        expression
+    }
+
+    #[test]
+    fn exact() {
+        let majority = |row: u64| row.count_ones() >= 2;
+        assert_eq!(check(3, majority, |_| false, Form::SumOfProducts).to_string(), "a & b | a & c | b & c");
+        assert_eq!(check(3, majority, |_| false, Form::ProductOfSums).to_string(), "(a | b) & (a | c) & (b | c)");
+
+        // Row 3 may be covered, so `a & !b` grows to `a`
+        assert_eq!(check(2, |row| row == 1, |_| false, Form::SumOfProducts).to_string(), "a & !b");
+        assert_eq!(check(2, |row| row == 1, |row| row == 3, Form::SumOfProducts).to_string(), "a");
+
+        assert_eq!(check(2, |_| false, |_| false, Form::SumOfProducts).to_string(), "0");
+        assert_eq!(check(2, |_| true, |_| false, Form::SumOfProducts).to_string(), "1");
+    }
+
+    #[test]
+    fn heuristic() {
+        let width = EXACT_LIMIT + 2;
+
+        let a_and_b = check(width, |row| row & 3 == 3, |_| false, Form::SumOfProducts);
+        assert_eq!(a_and_b.to_string(), "a & b");
+
+        // The rows where `a` is set and `b` isn't don't matter
+        let a = check(width, |row| row & 3 == 3, |row| row & 3 == 1, Form::SumOfProducts);
+        assert_eq!(a.to_string(), "a");
+
+        // Odd parity of the low three bits has no adjacent rows to merge, so every zero keeps all three literals
+        let parity = check(width, |row| (row & 0b111).count_ones() % 2 == 1, |row| row >> 3 != 0, Form::ProductOfSums);
+        assert_eq!((parity.terms.len(), parity.literals()), (4, 12));
+
+        // A scattered function, which leaves some early cubes redundant once later ones grow
+        let scattered = |row: u64| row.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 61 < 3;
+        check(width, scattered, |row| row % 7 == 0, Form::SumOfProducts);
+        check(width, scattered, |row| row % 7 == 0, Form::ProductOfSums);
+    }
+
+    #[test]
+    fn too_many_inputs() {
+        let component = Component {
+            id: 20,
+            name: "wide".to_owned(),
+            inputs: vec![Port::new("in", WIDTH_LIMIT as u8 + 1)],
+            outputs: vec![Port::new("out", 1)],
+            driver: ComponentDriver::TruthTable { truth: HashMap::new(), dont_care: HashSet::new() },
+            delay: 1,
+        };
+
+        let error = component.minimise(Form::SumOfProducts).err().unwrap().to_string();
+        assert!(error.contains("TooManyInputs(21)"));
+    }
+}
@@ -1,3 +1,20 @@
+use crate::{
+    bdd::BddTable,
+    circuit::CircuitError,
+    equivalence,
+    equivalence::Equivalence,
+    equivalence::EquivalenceError,
+    expression::Equation,
+    expression::Expr,
+    interop,
+    memory,
+    memory::MemoryFormat,
+    synthesis,
+    synthesis::GateLibrary,
+    testbench::TestBench,
+    testbench::TestReport,
+    truth::TruthTable
+};
 use leptos::prelude::*;
 use regex::Regex;
 use serde::{
@@ -11,6 +28,7 @@ use serde::{
 use std::{
     cell::LazyCell,
     collections::HashMap,
+    collections::HashSet,
     fmt::Display,
     fmt::Formatter,
     hash::Hash,
@@ -36,6 +54,9 @@ pub struct Project {
     // TODO: convert (InstanceId, u64) into a string-serialisable type
 
     pub(crate) wires: Vec<Wire>,
+
+    #[serde(default)]
+    pub(crate) tests: Vec<TestBench>,
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
@@ -137,6 +158,21 @@ impl Hash for Connection {
 }
 
 impl Project {
+    // The ids `empty` gives the built-in components
+    pub const NOT: ComponentId = 0;
+    pub const AND: ComponentId = 1;
+    pub const OR: ComponentId = 2;
+    pub const INPUT: ComponentId = 3;
+    pub const OUTPUT: ComponentId = 4;
+    pub const CLOCK: ComponentId = 5;
+    pub const D_FLIP_FLOP: ComponentId = 6;
+    pub const JK_FLIP_FLOP: ComponentId = 7;
+    pub const T_FLIP_FLOP: ComponentId = 8;
+    pub const D_LATCH: ComponentId = 9;
+    pub const SR_LATCH: ComponentId = 10;
+    pub const ROM: ComponentId = 11;
+    pub const RAM: ComponentId = 12;
+
     pub fn empty() -> Self {
         Self {
             components: vec![
@@ -151,6 +187,8 @@ impl Project {
                         outputs: vec!["q!".into()],
 
                         driver: ComponentDriver::truth([(0b0, 0b1), (0b1, 0b0)]),
+
+                        delay: 1,
                     },
                 ),
                 (
@@ -169,6 +207,8 @@ impl Project {
                             (0b10, 0b0),
                             (0b11, 0b1),
                         ]),
+
+                        delay: 1,
                     },
                 ),
                 (
@@ -187,6 +227,8 @@ impl Project {
                             (0b10, 0b1),
                             (0b11, 0b1),
                         ]),
+
+                        delay: 1,
                     },
                 ),
                 (
@@ -200,6 +242,8 @@ impl Project {
                         outputs: vec!["q".into()],
 
                         driver: ComponentDriver::Input,
+
+                        delay: 1,
                     },
                 ),
                 (
@@ -213,6 +257,128 @@ impl Project {
                         outputs: vec![],
 
                         driver: ComponentDriver::Output,
+
+                        delay: 1,
+                    },
+                ),
+                (
+                    5,
+                    Component {
+                        id: 5,
+
+                        name: "clock".into(),
+
+                        inputs: vec![],
+                        outputs: vec!["clk".into()],
+
+                        driver: ComponentDriver::Clock { period: 10, duty: 0.5 },
+
+                        delay: 0,
+                    },
+                ),
+                (
+                    6,
+                    Component {
+                        id: 6,
+
+                        name: "d flip-flop".into(),
+
+                        inputs: vec!["d".into(), "clk".into()],
+                        outputs: vec!["q".into(), "q!".into()],
+
+                        driver: ComponentDriver::FlipFlop { kind: FlipFlopKind::D },
+
+                        delay: 1,
+                    },
+                ),
+                (
+                    7,
+                    Component {
+                        id: 7,
+
+                        name: "jk flip-flop".into(),
+
+                        inputs: vec!["j".into(), "k".into(), "clk".into()],
+                        outputs: vec!["q".into(), "q!".into()],
+
+                        driver: ComponentDriver::FlipFlop { kind: FlipFlopKind::JK },
+
+                        delay: 1,
+                    },
+                ),
+                (
+                    8,
+                    Component {
+                        id: 8,
+
+                        name: "t flip-flop".into(),
+
+                        inputs: vec!["t".into(), "clk".into()],
+                        outputs: vec!["q".into(), "q!".into()],
+
+                        driver: ComponentDriver::FlipFlop { kind: FlipFlopKind::T },
+
+                        delay: 1,
+                    },
+                ),
+                (
+                    9,
+                    Component {
+                        id: 9,
+
+                        name: "d latch".into(),
+
+                        inputs: vec!["d".into(), "en".into()],
+                        outputs: vec!["q".into(), "q!".into()],
+
+                        driver: ComponentDriver::Latch { kind: LatchKind::D },
+
+                        delay: 1,
+                    },
+                ),
+                (
+                    10,
+                    Component {
+                        id: 10,
+
+                        name: "sr latch".into(),
+
+                        inputs: vec!["s".into(), "r".into()],
+                        outputs: vec!["q".into(), "q!".into()],
+
+                        driver: ComponentDriver::Latch { kind: LatchKind::SR },
+
+                        delay: 1,
+                    },
+                ),
+                (
+                    11,
+                    Component {
+                        id: 11,
+
+                        name: "rom".into(),
+
+                        inputs: vec![Port::new("addr", 8), "re".into()],
+                        outputs: vec![Port::new("q", 8)],
+
+                        driver: ComponentDriver::Memory { kind: MemoryKind::Rom, address: 8, data: 8, source: None, contents: vec![] },
+
+                        delay: 1,
+                    },
+                ),
+                (
+                    12,
+                    Component {
+                        id: 12,
+
+                        name: "ram".into(),
+
+                        inputs: vec![Port::new("addr", 8), Port::new("d", 8), "we".into(), "re".into()],
+                        outputs: vec![Port::new("q", 8)],
+
+                        driver: ComponentDriver::Memory { kind: MemoryKind::Ram, address: 8, data: 8, source: None, contents: vec![] },
+
+                        delay: 1,
                     },
                 ),
             ]
@@ -229,6 +395,7 @@ impl Project {
                         label: Some("Input".to_string()),
                         pos: (0.0, 0.0).into(),
                         orientation: 0.0,
+                        delay: None,
                     },
                 ),
                 (
@@ -240,6 +407,7 @@ impl Project {
                         label: Some("Output".to_string()),
                         pos: (0.0, 1.0).into(),
                         orientation: 0.0,
+                        delay: None,
                     },
                 ),
                 (
@@ -251,6 +419,7 @@ impl Project {
                         label: Some("And".to_string()),
                         pos: (2.0, 0.0).into(),
                         orientation: 0.0,
+                        delay: None,
                     },
                 ),
             ]
@@ -258,7 +427,169 @@ impl Project {
                 .collect(),
             connections: HashMap::new(),
             wires: vec![],
+            tests: vec![],
+        }
+    }
+}
+
+impl Project {
+    /// The width of the port a connection refers to, if it exists.
+    pub fn width(&self, connection: Connection) -> Option<u8> {
+        self.body.get(&connection.instance)
+            .and_then(|placement| self.components.get(&placement.component))
+            .and_then(|component| component.port(connection.terminal))
+            .map(|port| port.width)
+    }
+
+    /// Feeds an output terminal into an input terminal and draws a wire between them.
+    pub fn connect(&mut self, output: Connection, input: Connection) -> crate::Result<()> {
+        let (Terminal::Output(_), Terminal::Input(_)) = (output.terminal, input.terminal) else {
+            return Err(CircuitError::InvalidConnection(input).into());
+        };
+
+        let from = self.width(output).ok_or(CircuitError::InvalidConnection(output))?;
+        let to = self.width(input).ok_or(CircuitError::InvalidConnection(input))?;
+
+        if from != to {
+            return Err(CircuitError::WidthMismatch { output, input }.into());
+        }
+
+        if self.connections.values().flatten().any(|existing| *existing == input) {
+            return Err(CircuitError::MultipleDrivers(input).into());
+        }
+
+        self.connections.entry(output)
+            .or_default()
+            .push(input);
+
+        self.wires.push(Wire {
+            from: output.instance,
+            from_terminal: output.terminal,
+            points: vec![],
+            to: input.instance,
+            to_terminal: input.terminal,
+        });
+
+        Ok(())
+    }
+
+    /// Runs every test bench embedded in the project.
+    pub fn test(&self) -> crate::Result<Vec<TestReport>> {
+        self.tests.iter()
+            .map(|bench| bench.run(self))
+            .collect()
+    }
+
+    /// Replaces a subcomponent with a truth table that behaves the same, which is much quicker to simulate.
+    /// See [`TruthTable::extract_component`] for what `limit` means.
+    pub fn tabulate(&mut self, component: ComponentId, limit: u8) -> crate::Result<()> {
+        let table = TruthTable::extract_component(self, component, limit)?;
+
+        if let Some(component) = self.components.get_mut(&component) {
+            component.driver = ComponentDriver::truth(table.truth);
+        }
+
+        Ok(())
+    }
+
+    /// Converts a truth-table component into BDDs, which usually take far less space for wide tables.
+    pub fn convert_to_bdd(&mut self, component: ComponentId) -> crate::Result<()> {
+        let component = self.components.get_mut(&component)
+            .ok_or(EquivalenceError::UnknownComponent(component))?;
+
+        let ComponentDriver::TruthTable { truth, .. } = &component.driver else {
+            return Err(EquivalenceError::NotATruthTable(component.id).into());
+        };
+
+        let bits = |ports: &Vec<Port>| ports.iter().map(|port| port.width as u32).sum::<u32>();
+        let table = BddTable::from_truth(truth, bits(&component.inputs), bits(&component.outputs) as usize);
+
+        component.driver = ComponentDriver::Bdd { table };
+
+        Ok(())
+    }
+
+    /// Lists the vault files that memory components take their contents from.
+    pub fn memory_sources(&self) -> Vec<String> {
+        let mut sources = self.components.values()
+            .filter_map(|component| match &component.driver {
+                ComponentDriver::Memory { source, .. } => source.clone(),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        sources.sort_unstable();
+        sources.dedup();
+        sources
+    }
+
+    /// Replaces the contents of every memory component whose source is `path` with the image in `bytes`.
+    /// The format is chosen by the file's extension.
+    pub fn load_memory(&mut self, path: &str, bytes: &[u8]) -> crate::Result<()> {
+        for component in self.components.values_mut() {
+            if let ComponentDriver::Memory { address, data, source: Some(source), contents, .. } = &mut component.driver {
+                if source == path {
+                    if *address > memory::ADDRESS_LIMIT || *data > 64 {
+                        return Err(CircuitError::TooWide(component.id).into());
+                    }
+
+                    *contents = memory::load(MemoryFormat::from_path(path), bytes, *address, *data)?;
+                }
+            }
         }
+
+        Ok(())
+    }
+
+    /// Proves that two combinational components with the same ports behave the same. See [`equivalence::check`].
+    pub fn equivalent(&self, left: ComponentId, right: ComponentId) -> crate::Result<Equivalence> {
+        equivalence::check(self, left, right)
+    }
+
+    /// Builds a circuit computing a Boolean expression such as `a & !b | c ^ d` out of the gates in `library`,
+    /// driving a new `Output` placement labelled `output`, which is returned.
+    ///
+    /// Each variable is read from the top-level `Input` placement labelled with its name, which is added if there isn't one.
+    /// The new placements are laid out in columns below everything already in the project.
+    pub fn synthesise(&mut self, expression: &str, output: &str, library: &GateLibrary) -> crate::Result<InstanceId> {
+        let expr = expression.parse::<Expr>()?;
+        synthesis::synthesise(self, &expr, output, library)
+    }
+
+    /// Writes the project as Verilog for other tools, with its top level as the module `top`. See [`interop::verilog::export`].
+    pub fn export_verilog(&self, top: &str) -> crate::Result<String> {
+        interop::verilog::export(self, top)
+    }
+
+    /// Writes every component as a VHDL entity, with the top level as the entity `top`. See [`interop::vhdl::export`].
+    pub fn export_vhdl(&self, top: &str) -> crate::Result<String> {
+        interop::vhdl::export(self, top)
+    }
+
+    /// Reads a project from gate-level Verilog. See [`interop::verilog::import`].
+    pub fn import_verilog(source: &str) -> crate::Result<Self> {
+        interop::verilog::import(source)
+    }
+
+    /// Writes the project as BLIF for logic synthesis tools, with its top level as the model `top`. See [`interop::blif::export`].
+    pub fn export_blif(&self, top: &str) -> crate::Result<String> {
+        interop::blif::export(self, top)
+    }
+
+    /// Reads a project from BLIF, such as a netlist optimised by a synthesis tool. See [`interop::blif::import`].
+    pub fn import_blif(source: &str) -> crate::Result<Self> {
+        interop::blif::import(source)
+    }
+
+    /// Reads a project from the JSON netlist Yosys writes. See [`interop::yosys::import`].
+    pub fn import_yosys(source: &str) -> crate::Result<Self> {
+        interop::yosys::import(source)
+    }
+
+    /// Reads a project from a Logisim or Logisim-evolution `.circ` file, along with the elements it had no equivalent for.
+    /// See [`interop::logisim::import`].
+    pub fn import_logisim(source: &str) -> crate::Result<(Self, Vec<interop::logisim::Untranslated>)> {
+        interop::logisim::import(source)
     }
 }
 
@@ -271,10 +602,115 @@ pub struct Component {
 
     pub(crate) name: String,
 
-    pub(crate) inputs: Vec<String>,
-    pub(crate) outputs: Vec<String>,
+    pub(crate) inputs: Vec<Port>,
+    pub(crate) outputs: Vec<Port>,
 
     pub(crate) driver: ComponentDriver,
+
+    // Number of time units between an input changing and the outputs reflecting it
+    #[serde(default = "Component::default_delay")]
+    pub(crate) delay: u64,
+}
+
+impl Component {
+    pub(crate) fn default_delay() -> u64 {
+        1
+    }
+
+    pub fn port(&self, terminal: Terminal) -> Option<&Port> {
+        match terminal {
+            Terminal::Input(terminal) => self.inputs.get(terminal as usize),
+            Terminal::Output(terminal) => self.outputs.get(terminal as usize),
+        }
+    }
+}
+
+/// A named terminal carrying a bus of `width` bits. A single-bit port is serialised as its bare name, wider ports as `name[width]`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Port {
+    pub(crate) name: String,
+    pub(crate) width: u8,
+}
+
+impl Port {
+    pub fn new(name: impl Into<String>, width: u8) -> Self {
+        Self {
+            name: name.into(),
+            width,
+        }
+    }
+
+    /// Names every bit of a list of ports, first port first, suffixing the bits of buses with their index as in `a[3]`.
+    pub fn bit_names(ports: &[Port]) -> Vec<String> {
+        ports.iter()
+            .flat_map(|port| (0..port.width).map(move |bit| match port.width {
+                1 => port.name.clone(),
+                _ => format!("{}[{}]", port.name, bit),
+            }))
+            .collect()
+    }
+}
+
+impl From<&str> for Port {
+    fn from(name: &str) -> Self {
+        Self::new(name, 1)
+    }
+}
+
+impl Display for Port {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.width {
+            1 => write!(f, "{}", self.name),
+            width => write!(f, "{}[{}]", self.name, width),
+        }
+    }
+}
+
+impl Serialize for Port {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Port {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>
+    {
+        deserializer.deserialize_str(PortVisitor)
+    }
+}
+
+struct PortVisitor;
+const PORT_REGEX: LazyCell<Regex> = LazyCell::new(|| Regex::new(r"^(?<name>.*?)(\[(?<width>\d+)\])?$").expect("Failed to parse RegExp"));
+
+impl Visitor<'_> for PortVisitor {
+    type Value = Port;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "Expecting a port of format /^name(\\[width\\])?$/")
+    }
+
+    fn visit_str<E>(self, v: &str) -> std::result::Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        let port = PORT_REGEX.captures(v)
+            .ok_or(serde::de::Error::custom("Port does not match the expected format"))?;
+
+        let width = match port.name("width") {
+            Some(width) => width.as_str().parse()
+                .ok()
+                .filter(|width| (1..=64).contains(width))
+                .ok_or(serde::de::Error::invalid_value(Unexpected::Str(width.as_str()), &"a width between 1 and 64"))?,
+            None => 1,
+        };
+
+        Ok(Port::new(&port["name"], width))
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -286,28 +722,96 @@ pub struct Placement {
 
     pub(crate) pos: Coord,
     pub(crate) orientation: f64,
+
+    // Overrides the component's delay for this instance only
+    #[serde(default)]
+    pub(crate) delay: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize)]
 pub enum ComponentDriver {
     TruthTable {
         truth: HashMap<u64, u64>,
+        // Rows whose outputs don't matter. They simulate as written in `truth`, but minimisation may change their outputs.
+        #[serde(default)]
+        dont_care: HashSet<u64>,
     },
+    // A circuit of its own. Its ports are matched by name to the labels of the input and output placements inside it.
     Subcomponent {
-        connections: HashMap<(ComponentId, String), (ComponentId, String)>,
+        body: HashMap<InstanceId, Placement>,
+        connections: HashMap<Connection, Vec<Connection>>,
+        #[serde(default)]
+        wires: Vec<Wire>,
     },
     Script {
         script: Script,
     },
+    // Defines output bits as Boolean expressions over the input bits, naming the bits of buses as in `a[3]`.
+    // Output bits without an equation are X.
+    Expression {
+        equations: Vec<Equation>,
+    },
+    // A truth table stored as binary decision diagrams, which stays small for wide decoders and comparators
+    // and isn't limited to 64 input or output bits
+    Bdd {
+        table: BddTable,
+    },
+
+    // Outputs a square wave which is high for `duty` of every `period` time units
+    Clock {
+        period: u64,
+        duty: f64,
+    },
+    // Edge-triggered on a rising clock. Takes its data inputs followed by the clock, and outputs `q` and its inverse.
+    FlipFlop {
+        kind: FlipFlopKind,
+    },
+    // Level-sensitive. Takes its data inputs followed by the enable, if any, and outputs `q` and its inverse.
+    Latch {
+        kind: LatchKind,
+    },
+    // Takes the address, then for RAM the data to write and the write enable, then the read enable, and outputs the addressed word.
+    // The output floats while the read enable is low. Writes happen for as long as the write enable is high.
+    Memory {
+        kind: MemoryKind,
+        address: u8,
+        data: u8,
+        // The vault file the contents were loaded from, so they can be reloaded when it changes
+        #[serde(default)]
+        source: Option<String>,
+        // The initial contents. Missing words are 0 in a ROM and X in a RAM.
+        #[serde(default)]
+        contents: Vec<u64>,
+    },
 
     Input,
     Output,
 }
 
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum FlipFlopKind {
+    D,
+    JK,
+    T,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum LatchKind {
+    D,
+    SR,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum MemoryKind {
+    Rom,
+    Ram,
+}
+
 impl ComponentDriver {
     pub fn truth(truth: impl IntoIterator<Item=(u64, u64)>) -> Self {
         Self::TruthTable {
             truth: truth.into_iter().collect(),
+            dont_care: HashSet::new(),
         }
     }
 }
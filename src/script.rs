@@ -0,0 +1,718 @@
+//! A small language for describing the behaviour of a component.
+//!
+//! A script runs every time one of the component's inputs changes. It can read the inputs by the names of the
+//! component's input ports, and assigns the outputs by the names of its output ports. Values are unsigned 64-bit
+//! integers, truncated to the width of the port they are written to.
+//!
+//! ```text
+//! // Variables declared with `state` keep their value between runs
+//! state count = 0;
+//! state last = 0;
+//!
+//! if reset {
+//!     count = 0;
+//! } else if clk && !last {
+//!     count = count + 1;
+//! }
+//!
+//! last = clk;
+//! q = count;
+//! ```
+//!
+//! Besides `state`, local variables are declared with `let`. Control flow is `if`/`else` and `while`.
+//! Expressions support the arithmetic (`+ - * / %`), bitwise (`& | ^ ~ << >>`), comparison (`== != < <= > >=`)
+//! and logical (`&& || !`) operators, the conditional operator `c ? a : b`, and bit selection `x[n]`.
+//! Numbers can be written in decimal, or in hex or binary with a `0x` or `0b` prefix.
+//!
+//! Scripts are sandboxed: they can't reach anything but their own variables, and a run that takes more than
+//! [`STEP_LIMIT`] steps is abandoned, setting every output to X. Statements and expressions nested more than
+//! [`NESTING_LIMIT`] levels deep are rejected when the script is compiled.
+
+use crate::{
+    logic::mask,
+    project::Port,
+    Result
+};
+use std::collections::HashMap;
+
+/// The number of statements and operators a single run may evaluate.
+pub const STEP_LIMIT: usize = 100_000;
+
+/// How deeply statements and expressions may nest, since they are evaluated recursively.
+pub const NESTING_LIMIT: usize = 64;
+
+#[derive(Debug, Clone)]
+pub struct ScriptError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl std::error::Error for ScriptError {}
+impl std::fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+/// A parsed script with every variable resolved to a slot.
+///
+/// Slots are laid out as the inputs, then the outputs, then the state variables, then the locals.
+#[derive(Debug, Clone)]
+pub struct Program {
+    pub(crate) inputs: Vec<u8>,
+    pub(crate) outputs: Vec<u8>,
+    state: Vec<Expr>,
+    slots: usize,
+    body: Vec<Statement>,
+}
+
+impl Program {
+    pub fn compile(source: &str, inputs: &[Port], outputs: &[Port]) -> Result<Self> {
+        let tokens = lex(source)?;
+
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+            names: HashMap::new(),
+            kinds: vec![],
+            state: vec![],
+            depth: 0,
+        };
+
+        for port in inputs {
+            parser.declare(&port.name, Kind::Input, (1, 1))?;
+        }
+        for port in outputs {
+            parser.declare(&port.name, Kind::Output, (1, 1))?;
+        }
+
+        let body = parser.program()?;
+
+        // State is declared among the locals, so move it up behind the outputs
+        let ports = inputs.len() + outputs.len();
+        let mut order = (0..parser.kinds.len()).collect::<Vec<_>>();
+        order[ports..].sort_by_key(|slot| !matches!(parser.kinds[*slot], Kind::State));
+
+        let mut relocate = vec![0; order.len()];
+        for (to, from) in order.iter().enumerate() {
+            relocate[*from] = to;
+        }
+
+        Ok(Self {
+            inputs: inputs.iter().map(|port| port.width).collect(),
+            outputs: outputs.iter().map(|port| port.width).collect(),
+            state: parser.state.into_iter()
+                .map(|expr| expr.relocate(&relocate))
+                .collect(),
+            slots: parser.kinds.len(),
+            body: body.into_iter()
+                .map(|statement| statement.relocate(&relocate))
+                .collect(),
+        })
+    }
+
+    /// The values of the state variables before the first run
+    pub fn initial_state(&self) -> Vec<u64> {
+        let ports = self.inputs.len() + self.outputs.len();
+
+        let mut slots = vec![0; self.slots];
+        let mut steps = 0;
+
+        // Each initialiser can see the state declared before it
+        for (index, expr) in self.state.iter().enumerate() {
+            slots[ports + index] = expr.evaluate(&slots, &mut steps).unwrap_or(0);
+        }
+
+        slots[ports..ports + self.state.len()].to_vec()
+    }
+
+    /// Runs the script once. Returns the value of each output, or `None` for outputs the script didn't assign.
+    /// Fails if the script ran out of steps or divided by zero, in which case `state` is left untouched.
+    pub fn run(&self, inputs: &[u64], state: &mut [u64]) -> Option<Vec<Option<u64>>> {
+        let ports = self.inputs.len() + self.outputs.len();
+
+        let mut slots = vec![0; self.slots];
+        for (slot, (value, width)) in inputs.iter().zip(self.inputs.iter()).enumerate() {
+            slots[slot] = value & mask(*width);
+        }
+        slots[ports..ports + state.len()].copy_from_slice(state);
+
+        let mut assigned = vec![false; self.outputs.len()];
+        let mut steps = 0;
+
+        for statement in self.body.iter() {
+            statement.execute(&mut slots, &mut assigned, self.inputs.len(), &mut steps)?;
+        }
+
+        let end = ports + state.len();
+        state.copy_from_slice(&slots[ports..end]);
+
+        Some(self.outputs.iter()
+            .enumerate()
+            .map(|(index, width)| assigned[index].then(|| slots[self.inputs.len() + index] & mask(*width)))
+            .collect())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(u64),
+    Ident(String),
+    Symbol(&'static str),
+    End,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Number(number) => write!(f, "`{}`", number),
+            Self::Ident(ident) => write!(f, "`{}`", ident),
+            Self::Symbol(symbol) => write!(f, "`{}`", symbol),
+            Self::End => write!(f, "end of script"),
+        }
+    }
+}
+
+// Longest first, so that `<=` isn't read as `<` followed by `=`
+const SYMBOLS: [&str; 31] = [
+    "<<", ">>", "<=", ">=", "==", "!=", "&&", "||",
+    "+", "-", "*", "/", "%", "&", "|", "^", "~", "!", "<", ">", "=",
+    "(", ")", "{", "}", "[", "]", ";", "?", ":", ",",
+];
+
+fn lex(source: &str) -> Result<Vec<(Token, (usize, usize))>> {
+    let mut tokens = vec![];
+
+    for (line, text) in source.lines().enumerate() {
+        let text = text.split("//").next().unwrap_or_default();
+        let mut rest = text;
+
+        loop {
+            rest = rest.trim_start();
+            let position = (line + 1, text.len() - rest.len() + 1);
+
+            let Some(next) = rest.chars().next() else {
+                break;
+            };
+
+            if next.is_ascii_digit() {
+                let length = rest.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(rest.len());
+                let literal = rest[..length].replace('_', "");
+
+                let number = match literal.get(..2) {
+                    Some("0x") => u64::from_str_radix(&literal[2..], 16),
+                    Some("0b") => u64::from_str_radix(&literal[2..], 2),
+                    _ => literal.parse(),
+                }.map_err(|_| error(position, format!("invalid number `{}`", &rest[..length])))?;
+
+                tokens.push((Token::Number(number), position));
+                rest = &rest[length..];
+            } else if next.is_alphabetic() || next == '_' {
+                let length = rest.find(|c: char| !c.is_alphanumeric() && c != '_').unwrap_or(rest.len());
+
+                tokens.push((Token::Ident(rest[..length].to_owned()), position));
+                rest = &rest[length..];
+            } else {
+                let symbol = SYMBOLS.iter()
+                    .find(|symbol| rest.starts_with(**symbol))
+                    .ok_or(error(position, format!("unexpected character `{}`", next)))?;
+
+                tokens.push((Token::Symbol(symbol), position));
+                rest = &rest[symbol.len()..];
+            }
+        }
+    }
+
+    let end = (source.lines().count().max(1), source.lines().last().map(|line| line.len() + 1).unwrap_or(1));
+    tokens.push((Token::End, end));
+
+    Ok(tokens)
+}
+
+fn error(position: (usize, usize), message: String) -> ScriptError {
+    ScriptError {
+        line: position.0,
+        column: position.1,
+        message,
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Kind {
+    Input,
+    Output,
+    State,
+    Local,
+}
+
+#[derive(Debug, Clone)]
+enum Statement {
+    Assign(usize, Expr),
+    If(Expr, Vec<Statement>, Vec<Statement>),
+    While(Expr, Vec<Statement>),
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Number(u64),
+    Variable(usize),
+    Unary(&'static str, Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    Conditional(Box<Expr>, Box<Expr>, Box<Expr>),
+}
+
+// Binary operators from loosest to tightest binding
+const PRECEDENCE: [&[&str]; 10] = [
+    &["||"],
+    &["&&"],
+    &["|"],
+    &["^"],
+    &["&"],
+    &["==", "!="],
+    &["<", "<=", ">", ">="],
+    &["<<", ">>"],
+    &["+", "-"],
+    &["*", "/", "%"],
+];
+
+struct Parser {
+    tokens: Vec<(Token, (usize, usize))>,
+    position: usize,
+
+    names: HashMap<String, usize>,
+    kinds: Vec<Kind>,
+    state: Vec<Expr>,
+
+    // How many levels deep the statement or expression being parsed is
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position].0
+    }
+
+    fn here(&self) -> (usize, usize) {
+        self.tokens[self.position].1
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.peek().clone();
+        self.position = (self.position + 1).min(self.tokens.len() - 1);
+        token
+    }
+
+    fn accept(&mut self, symbol: &str) -> bool {
+        let found = match self.peek() {
+            Token::Symbol(s) => *s == symbol,
+            Token::Ident(s) => s == symbol,
+            _ => false,
+        };
+        if found {
+            self.next();
+        }
+        found
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<()> {
+        match self.accept(symbol) {
+            true => Ok(()),
+            false => Err(error(self.here(), format!("expected `{}` but found {}", symbol, self.peek())).into()),
+        }
+    }
+
+    fn ident(&mut self) -> Result<(String, (usize, usize))> {
+        let position = self.here();
+
+        match self.next() {
+            Token::Ident(ident) if !KEYWORDS.contains(&ident.as_str()) => Ok((ident, position)),
+            token => Err(error(position, format!("expected a name but found {}", token)).into()),
+        }
+    }
+
+    // Goes `levels` deeper, failing once past the nesting limit. Callers step back out with `self.depth -= levels`.
+    fn nest(&mut self, levels: usize) -> Result<()> {
+        self.depth += levels;
+
+        match self.depth > NESTING_LIMIT {
+            true => Err(error(self.here(), format!("nested more than {} levels deep", NESTING_LIMIT)).into()),
+            false => Ok(()),
+        }
+    }
+
+    fn declare(&mut self, name: &str, kind: Kind, position: (usize, usize)) -> Result<usize> {
+        if self.names.contains_key(name) {
+            return Err(error(position, format!("`{}` is already declared", name)).into());
+        }
+
+        self.kinds.push(kind);
+        self.names.insert(name.to_owned(), self.kinds.len() - 1);
+
+        Ok(self.kinds.len() - 1)
+    }
+
+    fn program(&mut self) -> Result<Vec<Statement>> {
+        let mut body = vec![];
+
+        while *self.peek() != Token::End {
+            if self.accept("state") {
+                let (name, position) = self.ident()?;
+                self.expect("=")?;
+                let init = self.expression()?;
+                self.expect(";")?;
+
+                self.declare(&name, Kind::State, position)?;
+                self.state.push(init);
+            } else {
+                body.push(self.statement()?);
+            }
+        }
+
+        Ok(body)
+    }
+
+    fn block(&mut self) -> Result<Vec<Statement>> {
+        self.expect("{")?;
+
+        let mut body = vec![];
+        while !self.accept("}") {
+            if *self.peek() == Token::End {
+                return Err(error(self.here(), "expected `}` but found end of script".to_owned()).into());
+            }
+
+            body.push(self.statement()?);
+        }
+
+        Ok(body)
+    }
+
+    fn statement(&mut self) -> Result<Statement> {
+        self.nest(1)?;
+        let statement = self.unnested_statement();
+        self.depth -= 1;
+
+        statement
+    }
+
+    fn unnested_statement(&mut self) -> Result<Statement> {
+        if self.accept("if") {
+            let condition = self.expression()?;
+            let then = self.block()?;
+
+            let otherwise = match self.accept("else") {
+                true if matches!(self.peek(), Token::Ident(ident) if ident == "if") => vec![self.statement()?],
+                true => self.block()?,
+                false => vec![],
+            };
+
+            return Ok(Statement::If(condition, then, otherwise));
+        }
+
+        if self.accept("while") {
+            let condition = self.expression()?;
+            return Ok(Statement::While(condition, self.block()?));
+        }
+
+        if self.accept("state") {
+            return Err(error(self.tokens[self.position - 1].1, "state can only be declared at the top level".to_owned()).into());
+        }
+
+        let local = self.accept("let");
+        let (name, position) = self.ident()?;
+        self.expect("=")?;
+        let value = self.expression()?;
+        self.expect(";")?;
+
+        let slot = match local {
+            true => self.declare(&name, Kind::Local, position)?,
+            false => match self.names.get(&name) {
+                Some(slot) if self.kinds[*slot] == Kind::Input => return Err(error(position, format!("cannot assign to input `{}`", name)).into()),
+                Some(slot) => *slot,
+                None => return Err(error(position, format!("unknown variable `{}`", name)).into()),
+            }
+        };
+
+        Ok(Statement::Assign(slot, value))
+    }
+
+    fn expression(&mut self) -> Result<Expr> {
+        self.nest(1)?;
+        let expr = self.conditional();
+        self.depth -= 1;
+
+        expr
+    }
+
+    fn conditional(&mut self) -> Result<Expr> {
+        let condition = self.binary(0)?;
+
+        if !self.accept("?") {
+            return Ok(condition);
+        }
+
+        let then = self.expression()?;
+        self.expect(":")?;
+        let otherwise = self.expression()?;
+
+        Ok(Expr::Conditional(Box::new(condition), Box::new(then), Box::new(otherwise)))
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expr> {
+        if level == PRECEDENCE.len() {
+            return self.unary();
+        }
+
+        let mut lhs = self.binary(level + 1)?;
+        let mut levels = 0;
+
+        // Each operator in a chain like `a + b + c` nests the expression one level deeper
+        while let Some(op) = PRECEDENCE[level].iter().find(|op| matches!(self.peek(), Token::Symbol(s) if s == *op)) {
+            self.next();
+            self.nest(1)?;
+            levels += 1;
+
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.binary(level + 1)?));
+        }
+
+        self.depth -= levels;
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        for op in ["!", "~", "-"] {
+            if self.accept(op) {
+                self.nest(1)?;
+                let operand = self.unary()?;
+                self.depth -= 1;
+
+                return Ok(Expr::Unary(op, Box::new(operand)));
+            }
+        }
+
+        let mut expr = self.primary()?;
+        let mut levels = 0;
+
+        while self.accept("[") {
+            self.nest(1)?;
+            levels += 1;
+
+            let bit = self.expression()?;
+            self.expect("]")?;
+
+            expr = Expr::Binary("[]", Box::new(expr), Box::new(bit));
+        }
+
+        self.depth -= levels;
+        Ok(expr)
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        let position = self.here();
+
+        match self.next() {
+            Token::Number(number) => Ok(Expr::Number(number)),
+            Token::Ident(ident) if ident == "true" => Ok(Expr::Number(1)),
+            Token::Ident(ident) if ident == "false" => Ok(Expr::Number(0)),
+            Token::Ident(ident) if !KEYWORDS.contains(&ident.as_str()) => self.names.get(&ident)
+                .map(|slot| Expr::Variable(*slot))
+                .ok_or(error(position, format!("unknown variable `{}`", ident)).into()),
+            Token::Symbol("(") => {
+                let expr = self.expression()?;
+                self.expect(")")?;
+                Ok(expr)
+            },
+            token => Err(error(position, format!("expected an expression but found {}", token)).into()),
+        }
+    }
+}
+
+const KEYWORDS: [&str; 7] = ["if", "else", "while", "let", "state", "true", "false"];
+
+impl Statement {
+    fn relocate(self, slots: &[usize]) -> Self {
+        let block = |body: Vec<Statement>| body.into_iter()
+            .map(|statement| statement.relocate(slots))
+            .collect();
+
+        match self {
+            Self::Assign(slot, value) => Self::Assign(slots[slot], value.relocate(slots)),
+            Self::If(condition, then, otherwise) => Self::If(condition.relocate(slots), block(then), block(otherwise)),
+            Self::While(condition, body) => Self::While(condition.relocate(slots), block(body)),
+        }
+    }
+
+    fn execute(&self, slots: &mut [u64], assigned: &mut [bool], outputs: usize, steps: &mut usize) -> Option<()> {
+        *steps += 1;
+        if *steps > STEP_LIMIT {
+            return None;
+        }
+
+        match self {
+            Self::Assign(slot, value) => {
+                slots[*slot] = value.evaluate(slots, steps)?;
+
+                if let Some(assigned) = slot.checked_sub(outputs).and_then(|output| assigned.get_mut(output)) {
+                    *assigned = true;
+                }
+            },
+            Self::If(condition, then, otherwise) => {
+                let body = match condition.evaluate(slots, steps)? {
+                    0 => otherwise,
+                    _ => then,
+                };
+
+                for statement in body {
+                    statement.execute(slots, assigned, outputs, steps)?;
+                }
+            },
+            Self::While(condition, body) => while condition.evaluate(slots, steps)? != 0 {
+                for statement in body {
+                    statement.execute(slots, assigned, outputs, steps)?;
+                }
+            },
+        }
+
+        Some(())
+    }
+}
+
+impl Expr {
+    fn relocate(self, slots: &[usize]) -> Self {
+        match self {
+            Self::Number(number) => Self::Number(number),
+            Self::Variable(slot) => Self::Variable(slots[slot]),
+            Self::Unary(op, operand) => Self::Unary(op, Box::new(operand.relocate(slots))),
+            Self::Binary(op, lhs, rhs) => Self::Binary(op, Box::new(lhs.relocate(slots)), Box::new(rhs.relocate(slots))),
+            Self::Conditional(condition, then, otherwise) => Self::Conditional(
+                Box::new(condition.relocate(slots)),
+                Box::new(then.relocate(slots)),
+                Box::new(otherwise.relocate(slots))),
+        }
+    }
+
+    fn evaluate(&self, slots: &[u64], steps: &mut usize) -> Option<u64> {
+        *steps += 1;
+        if *steps > STEP_LIMIT {
+            return None;
+        }
+
+        Some(match self {
+            Self::Number(number) => *number,
+            Self::Variable(slot) => slots[*slot],
+            Self::Unary(op, operand) => {
+                let operand = operand.evaluate(slots, steps)?;
+
+                match *op {
+                    "!" => (operand == 0) as u64,
+                    "~" => !operand,
+                    _ => operand.wrapping_neg(),
+                }
+            },
+            Self::Conditional(condition, then, otherwise) => match condition.evaluate(slots, steps)? {
+                0 => otherwise.evaluate(slots, steps)?,
+                _ => then.evaluate(slots, steps)?,
+            },
+            Self::Binary("&&", lhs, rhs) => (lhs.evaluate(slots, steps)? != 0 && rhs.evaluate(slots, steps)? != 0) as u64,
+            Self::Binary("||", lhs, rhs) => (lhs.evaluate(slots, steps)? != 0 || rhs.evaluate(slots, steps)? != 0) as u64,
+            Self::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.evaluate(slots, steps)?, rhs.evaluate(slots, steps)?);
+                let shift = u32::try_from(rhs).unwrap_or(u32::MAX);
+
+                match *op {
+                    "+" => lhs.wrapping_add(rhs),
+                    "-" => lhs.wrapping_sub(rhs),
+                    "*" => lhs.wrapping_mul(rhs),
+                    "/" => lhs.checked_div(rhs)?,
+                    "%" => lhs.checked_rem(rhs)?,
+                    "&" => lhs & rhs,
+                    "|" => lhs | rhs,
+                    "^" => lhs ^ rhs,
+                    "<<" => lhs.checked_shl(shift).unwrap_or(0),
+                    ">>" => lhs.checked_shr(shift).unwrap_or(0),
+                    "[]" => lhs.checked_shr(shift).unwrap_or(0) & 1,
+                    "==" => (lhs == rhs) as u64,
+                    "!=" => (lhs != rhs) as u64,
+                    "<" => (lhs < rhs) as u64,
+                    "<=" => (lhs <= rhs) as u64,
+                    ">" => (lhs > rhs) as u64,
+                    _ => (lhs >= rhs) as u64,
+                }
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(source: &str, inputs: &[u64]) -> Option<Vec<Option<u64>>> {
+        let ports = (0..inputs.len()).map(|index| Port::new(format!("i{}", index), 8)).collect::<Vec<_>>();
+        let program = Program::compile(source, &ports, &[Port::new("q", 8)]).unwrap();
+
+        program.run(inputs, &mut program.initial_state())
+    }
+
+    fn compile_error(source: &str) -> String {
+        Program::compile(source, &[], &[Port::new("q", 8)]).err().unwrap().to_string()
+    }
+
+    #[test]
+    fn counter() {
+        let source = "state count = 0; state last = 0;
+            if reset { count = 0; } else if clk && !last { count = count + 1; }
+            last = clk;
+            q = count;";
+        let program = Program::compile(source, &[Port::new("clk", 1), Port::new("reset", 1)], &[Port::new("q", 4)]).unwrap();
+        let mut state = program.initial_state();
+
+        for _ in 0..18 {
+            program.run(&[1, 0], &mut state).unwrap();
+            program.run(&[0, 0], &mut state).unwrap();
+        }
+        assert_eq!(program.run(&[0, 0], &mut state), Some(vec![Some(2)]));
+        assert_eq!(program.run(&[0, 1], &mut state), Some(vec![Some(0)]));
+    }
+
+    #[test]
+    fn operators() {
+        assert_eq!(run("q = 1 + 2 * 3;", &[]), Some(vec![Some(7)]));
+        assert_eq!(run("q = (1 + 2) * 3;", &[]), Some(vec![Some(9)]));
+        assert_eq!(run("q = 10 - 2 - 3;", &[]), Some(vec![Some(5)]));
+        assert_eq!(run("q = 0b1010[1] ? 0x10 : 0;", &[]), Some(vec![Some(16)]));
+        assert_eq!(run("q = i0 << 4 | i1;", &[3, 5]), Some(vec![Some(0x35)]));
+        assert_eq!(run("q = 0 - 1;", &[]), Some(vec![Some(0xff)]));
+        assert_eq!(run("let i = 0; q = 0; while i < 5 { q = q + i; i = i + 1; }", &[]), Some(vec![Some(10)]));
+        assert_eq!(run("if 0 { q = 1; }", &[]), Some(vec![None]));
+    }
+
+    #[test]
+    fn failed_runs() {
+        assert_eq!(run("q = 1 / 0;", &[]), None);
+        assert_eq!(run("while 1 { }", &[]), None);
+    }
+
+    #[test]
+    fn compile_errors() {
+        assert!(compile_error("q = x;").contains("unknown variable `x`"));
+        assert!(compile_error("let q = 1;").contains("`q` is already declared"));
+        assert!(compile_error("if 1 { state s = 0; }").contains("state can only be declared at the top level"));
+        assert!(compile_error("q = (1;").contains("expected `)`"));
+    }
+
+    #[test]
+    fn nesting() {
+        let chain = vec!["i0[0]"; NESTING_LIMIT / 2 + 1].join(" ^ ");
+        assert_eq!(run(&format!("q = {};", chain), &[1]), Some(vec![Some(1)]));
+
+        let error = format!("nested more than {} levels deep", NESTING_LIMIT);
+        assert!(compile_error(&format!("q = {}1{};", "(".repeat(10_000), ")".repeat(10_000))).contains(&error));
+        assert!(compile_error(&format!("q = 1{};", " + 1".repeat(100_000))).contains(&error));
+        assert!(compile_error(&format!("q = {}1;", "!".repeat(100_000))).contains(&error));
+        assert!(compile_error(&format!("q = 1{};", "[0]".repeat(100_000))).contains(&error));
+        assert!(compile_error(&format!("q = {}1{};", "1 ? ".repeat(10_000), " : 0".repeat(10_000))).contains(&error));
+        assert!(compile_error(&format!("{}q = 1;{}", "if 1 { ".repeat(10_000), "}".repeat(10_000))).contains(&error));
+        assert!(compile_error(&format!("if 0 {{ }}{} else {{ q = 1; }}", " else if 0 { }".repeat(10_000))).contains(&error));
+    }
+}
@@ -0,0 +1,329 @@
+use crate::{
+    circuit::Circuit,
+    logic::number,
+    logic::Logic,
+    logic::Value,
+    project::InstanceId,
+    Result
+};
+use std::collections::HashMap;
+
+/// A list of timed values to drive the `Input` instances of a circuit with, matched to them by label.
+#[derive(Debug, Clone, Default)]
+pub struct Stimulus {
+    pub(crate) signals: Vec<String>,
+    // Sorted by time, which is relative to when the stimulus is applied
+    pub(crate) events: Vec<Drive>,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Drive {
+    pub time: u64,
+    pub signal: usize,
+    pub value: Value,
+}
+
+impl Stimulus {
+    /// Parses a stimulus file, picking the format by its extension. Anything but `.csv` is read as VCD.
+    pub fn parse(path: &str, text: &str) -> Result<Self> {
+        match path.to_ascii_lowercase().ends_with(".csv") {
+            true => Self::from_csv(text),
+            false => Self::from_vcd(text),
+        }
+    }
+
+    /// Reads a value change dump. Signals are named by their reference, ignoring the scopes they are declared in,
+    /// and times are converted from the dump's timescale into nanoseconds.
+    pub fn from_vcd(text: &str) -> Result<Self> {
+        let mut tokens = text.lines()
+            .enumerate()
+            .flat_map(|(line, text)| text.split_whitespace().map(move |token| (line + 1, token)));
+
+        let mut stimulus = Self::default();
+        // Each identifier code with the signal and width it stands for
+        let mut codes = HashMap::new();
+        let mut scale = (1, 1);
+        let mut time = 0;
+
+        while let Some((line, token)) = tokens.next() {
+            let mut block = || {
+                let mut block = vec![];
+
+                for (_, token) in tokens.by_ref() {
+                    if token == "$end" {
+                        return Some(block);
+                    }
+
+                    block.push(token);
+                }
+
+                None
+            };
+
+            match token {
+                "$var" => {
+                    let declaration = block();
+                    let Some([_, width, code, name, ..]) = declaration.as_deref() else {
+                        return Err(StimulusError::new(line, "Incomplete variable declaration").into());
+                    };
+
+                    let width = width.parse::<u8>()
+                        .ok()
+                        .filter(|width| (1..=64).contains(width))
+                        .ok_or(StimulusError::new(line, format!("Unsupported width {}", width)))?;
+
+                    let signal = match stimulus.signals.iter().position(|signal| signal == name) {
+                        Some(signal) => signal,
+                        None => {
+                            stimulus.signals.push(name.to_string());
+                            stimulus.signals.len() - 1
+                        }
+                    };
+
+                    codes.insert(code.to_string(), (signal, width));
+                },
+                "$timescale" => {
+                    let timescale = block()
+                        .ok_or(StimulusError::new(line, "Incomplete timescale"))?
+                        .concat();
+
+                    scale = timescale.find(|c: char| !c.is_ascii_digit())
+                        .and_then(|split| Some((timescale[..split].parse::<u64>().ok()?, &timescale[split..])))
+                        .and_then(|(magnitude, unit)| Some(match unit {
+                            "s" => (magnitude.checked_mul(1_000_000_000)?, 1),
+                            "ms" => (magnitude.checked_mul(1_000_000)?, 1),
+                            "us" => (magnitude.checked_mul(1_000)?, 1),
+                            "ns" => (magnitude, 1),
+                            "ps" => (magnitude, 1_000),
+                            "fs" => (magnitude, 1_000_000),
+                            _ => return None,
+                        }))
+                        .ok_or(StimulusError::new(line, format!("Invalid timescale {}", timescale)))?;
+                },
+                // Declarations that don't affect the values
+                "$comment" | "$date" | "$version" | "$scope" | "$upscope" | "$enddefinitions" => {
+                    block().ok_or(StimulusError::new(line, format!("Unterminated {}", token)))?;
+                },
+                // These only group value changes, which are handled the same either way
+                "$dumpvars" | "$dumpall" | "$dumpon" | "$dumpoff" | "$end" => {},
+                token if token.starts_with('#') => {
+                    let units = token[1..].parse::<u64>()
+                        .map_err(|_| StimulusError::new(line, format!("Invalid time {}", token)))?;
+
+                    time = units.saturating_mul(scale.0) / scale.1;
+                },
+                token if token.starts_with(['b', 'B']) => {
+                    let (_, code) = tokens.next()
+                        .ok_or(StimulusError::new(line, "Missing identifier code"))?;
+
+                    stimulus.change(&codes, line, time, &token[1..], code)?;
+                },
+                token if token.starts_with(['r', 'R']) => return Err(StimulusError::new(line, "Real values are not supported").into()),
+                token if token.starts_with(['0', '1', 'x', 'X', 'z', 'Z']) => stimulus.change(&codes, line, time, &token[..1], &token[1..])?,
+                token => return Err(StimulusError::new(line, format!("Unexpected {}", token)).into()),
+            }
+        }
+
+        stimulus.events.sort_by_key(|event| event.time);
+
+        Ok(stimulus)
+    }
+
+    /// Reads a table whose first column is the time and whose other columns are named by the header row.
+    /// Cells hold numbers as in memory images, or `x` or `z`. Empty cells leave the input as it was.
+    pub fn from_csv(text: &str) -> Result<Self> {
+        let mut rows = text.lines()
+            .enumerate()
+            .map(|(line, row)| (line + 1, row.split(',').map(str::trim).collect::<Vec<_>>()))
+            .filter(|(_, row)| row.iter().any(|cell| !cell.is_empty()));
+
+        let Some((_, header)) = rows.next() else {
+            return Ok(Self::default());
+        };
+
+        let mut stimulus = Self {
+            signals: header.iter().skip(1).map(|name| name.to_string()).collect(),
+            events: vec![],
+        };
+
+        for (line, row) in rows {
+            let time = number(row[0])
+                .ok_or(StimulusError::new(line, format!("Invalid time {}", row[0])))?;
+
+            if row.len() > header.len() {
+                return Err(StimulusError::new(line, "More cells than columns").into());
+            }
+
+            for (signal, cell) in row.iter().skip(1).enumerate().filter(|(_, cell)| !cell.is_empty()) {
+                let value = match *cell {
+                    "x" | "X" => Value::x(64),
+                    "z" | "Z" => Value::z(64),
+                    cell => number(cell)
+                        .map(Value::from)
+                        .ok_or(StimulusError::new(line, format!("Invalid value {}", cell)))?,
+                };
+
+                stimulus.events.push(Drive { time, signal, value });
+            }
+        }
+
+        stimulus.events.sort_by_key(|event| event.time);
+
+        Ok(stimulus)
+    }
+
+    // Records a VCD value change, widening it to the declared width the way VCD requires
+    fn change(&mut self, codes: &HashMap<String, (usize, u8)>, line: usize, time: u64, digits: &str, code: &str) -> Result<()> {
+        let (signal, width) = codes.get(code)
+            .copied()
+            .ok_or(StimulusError::new(line, format!("Undeclared identifier code {}", code)))?;
+
+        let value = digits.parse::<Value>()
+            .map_err(|_| StimulusError::new(line, format!("Invalid value {}", digits)))?;
+
+        // Leading zeroes and ones are implied zeroes, a leading X or Z repeats
+        let fill = match value.get(value.width() - 1) {
+            logic @ (Logic::X | Logic::Z) if value.width() < width => Value::from(logic),
+            _ => Value::new(1, 0),
+        };
+
+        let value = (value.width()..width).fold(value, |value, _| value.concat(fill));
+
+        self.events.push(Drive { time, signal, value: value.slice(0, width) });
+
+        Ok(())
+    }
+
+    pub fn signals(&self) -> &[String] {
+        &self.signals
+    }
+
+    /// When the last value is driven
+    pub fn end(&self) -> u64 {
+        self.events.last()
+            .map(|event| event.time)
+            .unwrap_or(0)
+    }
+
+    /// Drives the circuit's inputs, advancing it from its current time to each value in turn.
+    /// Signals that don't name a top-level `Input` instance are ignored, so a dump that also records outputs can be replayed.
+    pub fn apply(&self, circuit: &mut Circuit) -> Result<()> {
+        let inputs = circuit.inputs()
+            .filter_map(|(instance, label)| Some((label?.to_owned(), instance)))
+            .collect::<HashMap<String, InstanceId>>();
+
+        let start = circuit.time();
+
+        for event in self.events.iter() {
+            circuit.advance((start + event.time).saturating_sub(circuit.time()))?;
+
+            if let Some(instance) = inputs.get(&self.signals[event.signal]) {
+                circuit.set_input(*instance, event.value)?;
+            }
+        }
+
+        circuit.evaluate()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StimulusError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl StimulusError {
+    fn new(line: usize, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl std::error::Error for StimulusError {}
+impl std::fmt::Display for StimulusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.line, self.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        circuit::tests::connect,
+        circuit::tests::place,
+        circuit::tests::project,
+        project::Project
+    };
+
+    fn error(path: &str, text: &str) -> String {
+        Stimulus::parse(path, text).err().unwrap().to_string()
+    }
+
+    #[test]
+    fn vcd() {
+        let stimulus = Stimulus::parse("input.vcd", "$timescale 10ps $end
+            $scope module top $end $var wire 1 ! a $end $var wire 4 \" bus $end $upscope $end
+            $enddefinitions $end
+            #0 $dumpvars 0! bx \" $end
+            #300 1! b10 \"
+            #500 bz1 \"").unwrap();
+
+        assert_eq!(stimulus.signals(), ["a", "bus"]);
+        assert_eq!(stimulus.events, vec![
+            Drive { time: 0, signal: 0, value: Value::new(1, 0) },
+            Drive { time: 0, signal: 1, value: Value::x(4) },
+            Drive { time: 3, signal: 0, value: Value::new(1, 1) },
+            Drive { time: 3, signal: 1, value: Value::new(4, 2) },
+            Drive { time: 5, signal: 1, value: "zzz1".parse().unwrap() },
+        ]);
+        assert_eq!(stimulus.end(), 5);
+
+        assert!(error("input.vcd", "$timescale 100000000000s $end").contains(r#"line: 1, message: "Invalid timescale 100000000000s""#));
+        assert!(error("input.vcd", "$var wire 1 ! a $end\n#0 1?").contains(r#"line: 2, message: "Undeclared identifier code ?""#));
+        assert!(error("input.vcd", "$var wire 65 ! a $end").contains(r#"line: 1, message: "Unsupported width 65""#));
+        assert!(error("input.vcd", "$var wire 1 ! a $end\nr1.5 !").contains(r#"line: 2, message: "Real values are not supported""#));
+    }
+
+    #[test]
+    fn csv() {
+        let stimulus = Stimulus::parse("input.CSV", "time, a, b\n0, 1, 0x2\n\n10, , z\n5, x").unwrap();
+
+        assert_eq!(stimulus.signals(), ["a", "b"]);
+        assert_eq!(stimulus.events, vec![
+            Drive { time: 0, signal: 0, value: Value::from(1) },
+            Drive { time: 0, signal: 1, value: Value::from(2) },
+            Drive { time: 5, signal: 0, value: Value::x(64) },
+            Drive { time: 10, signal: 1, value: Value::z(64) },
+        ]);
+
+        assert!(error("input.csv", "time, a\n0, 1, 2").contains(r#"line: 2, message: "More cells than columns""#));
+        assert!(error("input.csv", "time, a\nlater, 1").contains(r#"line: 2, message: "Invalid time later""#));
+        assert!(error("input.csv", "time, a\n0, high").contains(r#"line: 2, message: "Invalid value high""#));
+    }
+
+    #[test]
+    fn apply() {
+        let mut project = project();
+        place(&mut project, 0, Project::INPUT, "a");
+        place(&mut project, 1, Project::NOT, "not");
+        place(&mut project, 2, Project::OUTPUT, "y");
+        connect(&mut project, (0, 0), (1, 0));
+        connect(&mut project, (1, 0), (2, 0));
+
+        let mut circuit = Circuit::compile(&project).unwrap();
+        circuit.record();
+
+        Stimulus::parse("input.csv", "time, a, y\n0, 0, 1\n4, 1\n9, 0").unwrap()
+            .apply(&mut circuit)
+            .unwrap();
+
+        let trace = circuit.trace().unwrap();
+        assert_eq!(circuit.time(), 10);
+        assert_eq!(trace.value("y", 4).and_then(|value| value.to_u64()), Some(1));
+        assert_eq!(trace.value("y", 5).and_then(|value| value.to_u64()), Some(0));
+        assert_eq!(trace.value("y", 10).and_then(|value| value.to_u64()), Some(1));
+    }
+}
@@ -0,0 +1,206 @@
+use crate::{
+    expression::Expr,
+    layout,
+    project::ComponentDriver,
+    project::ComponentId,
+    project::Connection,
+    project::Coord,
+    project::InstanceId,
+    project::Placement,
+    project::Project,
+    Result
+};
+use std::collections::HashMap;
+
+/// The components a circuit is synthesised from.
+#[derive(Debug, Clone)]
+pub struct GateLibrary {
+    pub not: ComponentId,
+    pub and: ComponentId,
+    pub or: ComponentId,
+    /// Without an XOR gate, `a ^ b` is built as `(a | b) & !(a & b)`.
+    pub xor: Option<ComponentId>,
+
+    pub input: ComponentId,
+    pub output: ComponentId,
+}
+
+/// The gates of [`Project::empty`].
+impl Default for GateLibrary {
+    fn default() -> Self {
+        Self {
+            not: Project::NOT,
+            and: Project::AND,
+            or: Project::OR,
+            xor: None,
+            input: Project::INPUT,
+            output: Project::OUTPUT,
+        }
+    }
+}
+
+impl GateLibrary {
+    // Checks that each gate has the right number of single-bit ports
+    fn validate(&self, project: &Project) -> Result<()> {
+        let gates = [(self.not, 1, 1), (self.and, 2, 1), (self.or, 2, 1), (self.input, 0, 1), (self.output, 1, 0)]
+            .into_iter()
+            .chain(self.xor.map(|xor| (xor, 2, 1)));
+
+        for (gate, inputs, outputs) in gates {
+            let valid = project.components.get(&gate).is_some_and(|component| {
+                let terminal = matches!(component.driver, ComponentDriver::Input | ComponentDriver::Output);
+
+                component.inputs.len() == inputs
+                    && component.outputs.len() == outputs
+                    && component.inputs.iter().chain(component.outputs.iter()).all(|port| port.width == 1)
+                    && terminal == (inputs == 0 || outputs == 0)
+            });
+
+            if !valid {
+                return Err(SynthesisError::InvalidGate(gate).into());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// A gate-level netlist in which every gate comes after the gates feeding it
+#[derive(Default)]
+struct Netlist {
+    gates: Vec<(Gate, Vec<usize>)>,
+    // Identical subexpressions share a gate
+    built: HashMap<Expr, usize>,
+}
+
+#[derive(Debug, Clone)]
+enum Gate {
+    Input(String),
+    Component(ComponentId),
+    Output,
+}
+
+impl Netlist {
+    fn gate(&mut self, gate: Gate, inputs: Vec<usize>) -> usize {
+        self.gates.push((gate, inputs));
+        self.gates.len() - 1
+    }
+
+    fn build(&mut self, expr: &Expr, library: &GateLibrary) -> usize {
+        if let Some(gate) = self.built.get(expr) {
+            return *gate;
+        }
+
+        let binary = |netlist: &mut Self, gate: ComponentId, lhs: &Expr, rhs: &Expr| {
+            let inputs = vec![netlist.build(lhs, library), netlist.build(rhs, library)];
+            netlist.gate(Gate::Component(gate), inputs)
+        };
+
+        let gate = match expr {
+            // Constants are folded away before building
+            Expr::Constant(_) => unreachable!(),
+            Expr::Variable(name) => self.gate(Gate::Input(name.clone()), vec![]),
+            Expr::Not(inner) => {
+                let inner = self.build(inner, library);
+                self.gate(Gate::Component(library.not), vec![inner])
+            },
+            Expr::And(lhs, rhs) => binary(self, library.and, lhs, rhs),
+            Expr::Or(lhs, rhs) => binary(self, library.or, lhs, rhs),
+            Expr::Xor(lhs, rhs) => match library.xor {
+                Some(xor) => binary(self, xor, lhs, rhs),
+                None => {
+                    let either = Expr::Or(lhs.clone(), rhs.clone());
+                    let both = Expr::And(lhs.clone(), rhs.clone());
+
+                    self.build(&Expr::And(Box::new(either), Box::new(Expr::Not(Box::new(both)))), library)
+                },
+            },
+        };
+
+        self.built.insert(expr.clone(), gate);
+        gate
+    }
+}
+
+/// Adds gates computing `expr` to the top level of `project`, returning the `Output` placement they drive.
+/// See [`Project::synthesise`].
+pub fn synthesise(project: &mut Project, expr: &Expr, output: &str, library: &GateLibrary) -> Result<InstanceId> {
+    library.validate(project)?;
+
+    let expr = expr.clone().simplify();
+
+    if let Expr::Constant(constant) = expr {
+        return Err(SynthesisError::Constant(constant).into());
+    }
+
+    let mut netlist = Netlist::default();
+    let root = netlist.build(&expr, library);
+    netlist.gate(Gate::Output, vec![root]);
+
+    let predecessors = netlist.gates.iter()
+        .map(|(_, inputs)| inputs.clone())
+        .collect::<Vec<_>>();
+    let positions = layout::layered(&predecessors, Coord(3.0, 3.0));
+
+    let top = project.body.values()
+        .map(|placement| placement.pos.1 + 3.0)
+        .fold(0.0, f64::max);
+    let mut next = project.body.keys().max().map(|max| max + 1).unwrap_or(0);
+
+    let mut instances = vec![];
+
+    for ((gate, _), pos) in netlist.gates.iter().zip(positions) {
+        let existing = match gate {
+            Gate::Input(name) => project.body.values()
+                .find(|placement| placement.component == library.input && placement.label.as_deref() == Some(name.as_str()))
+                .map(|placement| placement.instance),
+            _ => None,
+        };
+
+        if let Some(instance) = existing {
+            instances.push(instance);
+            continue;
+        }
+
+        let (component, label) = match gate {
+            Gate::Input(name) => (library.input, Some(name.clone())),
+            Gate::Component(component) => (*component, None),
+            Gate::Output => (library.output, Some(output.to_owned())),
+        };
+
+        project.body.insert(next, Placement {
+            component,
+            instance: next,
+            label,
+            pos: Coord(pos.0, pos.1 + top),
+            orientation: 0.0,
+            delay: None,
+        });
+
+        instances.push(next);
+        next += 1;
+    }
+
+    for (gate, (_, inputs)) in netlist.gates.iter().enumerate() {
+        for (terminal, input) in inputs.iter().enumerate() {
+            project.connect(Connection::output(instances[*input], 0), Connection::input(instances[gate], terminal as u64))?;
+        }
+    }
+
+    Ok(instances[instances.len() - 1])
+}
+
+#[derive(Debug, Clone)]
+pub enum SynthesisError {
+    /// The expression always has this value, and the gate library has nothing to produce a constant with
+    Constant(bool),
+    /// A component in the gate library which doesn't have the ports of the gate it stands for
+    InvalidGate(ComponentId),
+}
+
+impl std::error::Error for SynthesisError {}
+impl std::fmt::Display for SynthesisError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
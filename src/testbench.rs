@@ -0,0 +1,274 @@
+use crate::{
+    circuit::Circuit,
+    logic::Logic,
+    logic::ParseValueError,
+    logic::Value,
+    project::InstanceId,
+    project::Project,
+    Result
+};
+use serde::{
+    Deserialize,
+    Serialize
+};
+use std::{
+    fmt::Display,
+    fmt::Formatter
+};
+
+/// A list of test vectors to check a circuit against.
+///
+/// Inputs and outputs are named by the labels of the top-level `Input` and `Output` placements.
+/// Rows are applied one after the other to the same simulation, so sequential circuits keep their state between them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestBench {
+    pub(crate) name: String,
+
+    pub(crate) inputs: Vec<String>,
+    pub(crate) outputs: Vec<String>,
+
+    // An input to pulse for rows with clock steps
+    #[serde(default)]
+    pub(crate) clock: Option<String>,
+
+    pub(crate) rows: Vec<TestRow>,
+}
+
+/// Values are written most significant bit first, in `0`, `1`, `x` and `z`.
+/// Expected values may also use `-` for bits whose value doesn't matter, or be `-` on their own to ignore the output altogether.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestRow {
+    pub(crate) inputs: Vec<String>,
+    pub(crate) outputs: Vec<String>,
+
+    // How many times to pulse the clock after applying the inputs and before checking the outputs
+    #[serde(default)]
+    pub(crate) steps: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TestReport {
+    pub bench: String,
+    pub rows: Vec<RowReport>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RowReport {
+    pub row: usize,
+    pub mismatches: Vec<Mismatch>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Mismatch {
+    pub output: String,
+    pub expected: String,
+    pub actual: Value,
+}
+
+impl TestBench {
+    /// Reads a test bench stored in a file of its own.
+    pub fn from_json(json: &str) -> std::result::Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Simulates the project with every row in turn and compares the outputs against the expected values.
+    pub fn run(&self, project: &Project) -> Result<TestReport> {
+        let mut circuit = Circuit::compile(project)?;
+        circuit.evaluate()?;
+
+        let find = |label: &str, instances: Vec<(InstanceId, Option<&str>)>, missing: fn(String) -> TestBenchError| instances.into_iter()
+            .find(|(_, name)| *name == Some(label))
+            .map(|(instance, _)| instance)
+            .ok_or(missing(label.to_owned()));
+
+        let inputs = self.inputs.iter()
+            .map(|label| find(label, circuit.inputs().collect(), TestBenchError::UnknownInput))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let outputs = self.outputs.iter()
+            .map(|label| find(label, circuit.outputs().collect(), TestBenchError::UnknownOutput))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let clock = self.clock.as_deref()
+            .map(|label| find(label, circuit.inputs().collect(), TestBenchError::UnknownInput))
+            .transpose()?;
+
+        let mut report = TestReport {
+            bench: self.name.clone(),
+            rows: vec![],
+        };
+
+        for (index, row) in self.rows.iter().enumerate() {
+            if row.inputs.len() != inputs.len() || row.outputs.len() != outputs.len() {
+                return Err(TestBenchError::RowLength(index).into());
+            }
+
+            for (instance, value) in inputs.iter().zip(row.inputs.iter()) {
+                circuit.set_input(*instance, value.parse::<Value>()?)?;
+            }
+
+            circuit.evaluate()?;
+
+            if row.steps > 0 {
+                let clock = clock.ok_or(TestBenchError::NoClock(index))?;
+
+                for _ in 0..row.steps {
+                    for level in [true, false] {
+                        circuit.set_input(clock, level)?;
+                        circuit.evaluate()?;
+                    }
+                }
+            }
+
+            let mut mismatches = vec![];
+
+            for ((instance, label), expected) in outputs.iter().zip(self.outputs.iter()).zip(row.outputs.iter()) {
+                let actual = circuit.output(*instance)?;
+
+                if !matches(expected, actual)? {
+                    mismatches.push(Mismatch {
+                        output: label.clone(),
+                        expected: expected.clone(),
+                        actual,
+                    });
+                }
+            }
+
+            report.rows.push(RowReport { row: index, mismatches });
+        }
+
+        Ok(report)
+    }
+}
+
+// Compares an expected value bit for bit, right-aligned so that a shorter pattern checks only the low bits
+fn matches(expected: &str, actual: Value) -> std::result::Result<bool, ParseValueError> {
+    if expected == "-" {
+        return Ok(true);
+    }
+
+    let digits = expected.chars()
+        .filter(|c| *c != '_')
+        .collect::<Vec<_>>();
+
+    if digits.is_empty() || digits.len() > actual.width() as usize {
+        return Err(ParseValueError(expected.to_owned()));
+    }
+
+    digits.iter()
+        .rev()
+        .enumerate()
+        .try_fold(true, |matched, (bit, digit)| {
+            let logic = match digit {
+                '-' => return Ok(matched),
+                '0' => Logic::Zero,
+                '1' => Logic::One,
+                'x' | 'X' => Logic::X,
+                'z' | 'Z' => Logic::Z,
+                _ => return Err(ParseValueError(expected.to_owned())),
+            };
+
+            Ok(matched && actual.get(bit as u8) == logic)
+        })
+}
+
+impl TestReport {
+    pub fn passed(&self) -> bool {
+        self.rows.iter().all(|row| row.mismatches.is_empty())
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item=&RowReport> {
+        self.rows.iter().filter(|row| !row.mismatches.is_empty())
+    }
+}
+
+// One line per failing output, followed by a summary
+impl Display for TestReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for row in self.failures() {
+            for mismatch in row.mismatches.iter() {
+                writeln!(f, "{}: row {}: {}: expected {}, got {}", self.bench, row.row, mismatch.output, mismatch.expected, mismatch.actual)?;
+            }
+        }
+
+        let failed = self.failures().count();
+
+        write!(f, "{}: {} of {} rows passed", self.bench, self.rows.len() - failed, self.rows.len())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum TestBenchError {
+    UnknownInput(String),
+    UnknownOutput(String),
+    // The index of a row with the wrong number of values
+    RowLength(usize),
+    // The index of a row that steps the clock in a test bench without one
+    NoClock(usize),
+}
+
+impl std::error::Error for TestBenchError {}
+impl std::fmt::Display for TestBenchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::tests::{
+        connect,
+        place,
+        project
+    };
+
+    fn bench(rows: &[([&str; 2], &str)]) -> TestBench {
+        TestBench {
+            name: "and".to_owned(),
+            inputs: vec!["a".to_owned(), "b".to_owned()],
+            outputs: vec!["y".to_owned()],
+            clock: None,
+            rows: rows.iter()
+                .map(|(inputs, output)| TestRow {
+                    inputs: inputs.iter().map(|input| input.to_string()).collect(),
+                    outputs: vec![output.to_string()],
+                    steps: 0,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn report() {
+        let mut project = project();
+        place(&mut project, 0, Project::INPUT, "a");
+        place(&mut project, 1, Project::INPUT, "b");
+        place(&mut project, 2, Project::AND, "and");
+        place(&mut project, 3, Project::OUTPUT, "y");
+        connect(&mut project, (0, 0), (2, 0));
+        connect(&mut project, (1, 0), (2, 1));
+        connect(&mut project, (2, 0), (3, 0));
+
+        let report = bench(&[(["0", "1"], "0"), (["1", "1"], "-"), (["1", "0"], "1"), (["1", "x"], "x")])
+            .run(&project)
+            .unwrap();
+
+        assert!(!report.passed());
+        assert_eq!(report.failures().map(|row| row.row).collect::<Vec<_>>(), vec![2]);
+        assert_eq!(report.to_string(), "and: row 2: y: expected 1, got 0\nand: 3 of 4 rows passed");
+
+        assert!(bench(&[(["0", "1"], "00")]).run(&project).err().unwrap().to_string().contains("ParseValueError"));
+        assert!(bench(&[(["0", "1"], "0")]).run(&project).unwrap().passed());
+
+        let mut missing = bench(&[(["0", "1"], "0")]);
+        missing.inputs[1] = "c".to_owned();
+        assert!(missing.run(&project).err().unwrap().to_string().contains("UnknownInput(\"c\")"));
+
+        let mut short = bench(&[(["0", "1"], "0")]);
+        short.rows[0].inputs.pop();
+        assert!(short.run(&project).err().unwrap().to_string().contains("RowLength(0)"));
+
+        let mut stepped = bench(&[(["0", "1"], "0")]);
+        stepped.rows[0].steps = 1;
+        assert!(stepped.run(&project).err().unwrap().to_string().contains("NoClock(0)"));
+    }
+}
@@ -0,0 +1,204 @@
+use crate::{
+    circuit::NetId,
+    logic::Value
+};
+use std::{
+    collections::HashMap,
+    fmt::Write
+};
+
+/// A recording of every change to a set of named signals, made while a [`Circuit`](crate::circuit::Circuit) simulates.
+#[derive(Debug, Clone, Default)]
+pub struct Trace {
+    pub(crate) signals: Vec<Signal>,
+    pub(crate) changes: Vec<Change>,
+
+    // The signals each net is part of
+    watched: HashMap<NetId, Vec<usize>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Signal {
+    pub name: String,
+    pub width: u8,
+
+    // Packed first to last, the first net in the least significant bits. Unconnected terminals are `None` and read as Z.
+    pub(crate) nets: Vec<(Option<NetId>, u8)>,
+    last: Value,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Change {
+    pub time: u64,
+    pub signal: usize,
+    pub value: Value,
+}
+
+impl Trace {
+    pub fn signals(&self) -> &[Signal] {
+        &self.signals
+    }
+
+    pub fn changes(&self) -> &[Change] {
+        &self.changes
+    }
+
+    /// The value of a signal at `time`, or `None` if the signal doesn't exist.
+    pub fn value(&self, name: &str, time: u64) -> Option<Value> {
+        let signal = self.signals.iter().position(|signal| signal.name == name)?;
+
+        self.changes.iter()
+            .take_while(|change| change.time <= time)
+            .filter(|change| change.signal == signal)
+            .last()
+            .map(|change| change.value)
+    }
+
+    // Starts following a signal, recording the value it has now
+    pub(crate) fn watch(&mut self, name: String, nets: Vec<(Option<NetId>, u8)>, time: u64, values: &[Value]) {
+        let index = self.signals.len();
+
+        for net in nets.iter().filter_map(|(net, _)| *net) {
+            self.watched.entry(net).or_default().push(index);
+        }
+
+        let last = pack(&nets, values);
+
+        self.signals.push(Signal { width: last.width(), name, nets, last });
+        self.changes.push(Change { time, signal: index, value: last });
+    }
+
+    // Records the signals made up of `nets` which now have a different value
+    pub(crate) fn update(&mut self, time: u64, nets: &[NetId], values: &[Value]) {
+        let mut signals = nets.iter()
+            .filter_map(|net| self.watched.get(net))
+            .flatten()
+            .copied()
+            .collect::<Vec<_>>();
+        signals.sort_unstable();
+        signals.dedup();
+
+        for index in signals {
+            let signal = &mut self.signals[index];
+            let value = pack(&signal.nets, values);
+
+            if value != signal.last {
+                signal.last = value;
+                self.changes.push(Change { time, signal: index, value });
+            }
+        }
+    }
+
+    /// Writes the trace out as an IEEE 1364 value change dump, taking one time unit to be a nanosecond.
+    pub fn to_vcd(&self) -> String {
+        let mut vcd = String::new();
+
+        writeln!(vcd, "$version LogicX $end").unwrap();
+        writeln!(vcd, "$timescale 1ns $end").unwrap();
+        writeln!(vcd, "$scope module logicx $end").unwrap();
+
+        for (index, signal) in self.signals.iter().enumerate() {
+            let name = signal.name.split_whitespace().collect::<Vec<_>>().join("_");
+
+            match signal.width {
+                1 => writeln!(vcd, "$var wire 1 {} {} $end", identifier(index), name),
+                width => writeln!(vcd, "$var wire {} {} {} [{}:0] $end", width, identifier(index), name, width - 1),
+            }.unwrap();
+        }
+
+        writeln!(vcd, "$upscope $end").unwrap();
+        writeln!(vcd, "$enddefinitions $end").unwrap();
+
+        let mut time = None;
+
+        for change in self.changes.iter() {
+            if time != Some(change.time) {
+                writeln!(vcd, "#{}", change.time).unwrap();
+                time = Some(change.time);
+            }
+
+            match change.value.width() {
+                1 => writeln!(vcd, "{}{}", change.value, identifier(change.signal)),
+                _ => writeln!(vcd, "b{} {}", change.value, identifier(change.signal)),
+            }.unwrap();
+        }
+
+        vcd
+    }
+}
+
+fn pack(nets: &[(Option<NetId>, u8)], values: &[Value]) -> Value {
+    nets.iter()
+        .rev()
+        .fold(Value::new(0, 0), |packed, (net, width)| net
+            .map(|net| values[net])
+            .unwrap_or(Value::z(*width))
+            .concat(packed))
+}
+
+// VCD identifiers are strings of printable ASCII characters, so signals are numbered in base 94
+fn identifier(mut index: usize) -> String {
+    let mut identifier = String::new();
+
+    loop {
+        identifier.push((b'!' + (index % 94) as u8) as char);
+        index /= 94;
+
+        if index == 0 {
+            break identifier;
+        }
+
+        index -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        circuit::tests::connect,
+        circuit::tests::place,
+        circuit::tests::project,
+        circuit::Circuit,
+        project::Project
+    };
+
+    #[test]
+    fn vcd() {
+        let mut project = project();
+        place(&mut project, 0, Project::INPUT, "a");
+        place(&mut project, 1, Project::NOT, "not");
+        place(&mut project, 2, Project::OUTPUT, "y");
+        connect(&mut project, (0, 0), (1, 0));
+        connect(&mut project, (1, 0), (2, 0));
+
+        let mut circuit = Circuit::compile(&project).unwrap();
+        circuit.record();
+        circuit.advance(1).unwrap();
+
+        for value in [1, 0] {
+            circuit.set_input(0, value).unwrap();
+            circuit.advance(5).unwrap();
+        }
+
+        let trace = circuit.take_trace().unwrap();
+        assert_eq!(trace.value("y", 6).and_then(|value| value.to_u64()), Some(0));
+        assert_eq!(trace.value("y", 7).and_then(|value| value.to_u64()), Some(1));
+        assert_eq!(trace.value("b", 7), None);
+
+        assert_eq!(trace.to_vcd(), [
+            "$version LogicX $end",
+            "$timescale 1ns $end",
+            "$scope module logicx $end",
+            "$var wire 1 ! a $end",
+            "$var wire 1 \" y $end",
+            "$upscope $end",
+            "$enddefinitions $end",
+            "#0", "0!", "0\"",
+            "#1", "1\"", "1!",
+            "#2", "0\"",
+            "#6", "0!",
+            "#7", "1\"",
+            "",
+        ].join("\n"));
+    }
+}
@@ -0,0 +1,189 @@
+use crate::{
+    circuit::Circuit,
+    circuit::CircuitError,
+    logic::Value,
+    project::ComponentId,
+    project::InstanceId,
+    project::Port,
+    project::Project,
+    Result
+};
+use std::collections::HashMap;
+
+/// The default number of input bits a circuit may have for its truth table to be extracted.
+/// Each extra bit doubles the number of rows that have to be simulated.
+pub const INPUT_LIMIT: u8 = 16;
+
+/// The complete truth table of a combinational circuit.
+///
+/// Keys pack the inputs and values pack the outputs, the first port in the least significant bits,
+/// the same way [`ComponentDriver::TruthTable`](crate::project::ComponentDriver::TruthTable) reads them.
+#[derive(Debug, Clone)]
+pub struct TruthTable {
+    pub inputs: Vec<Port>,
+    pub outputs: Vec<Port>,
+    pub truth: HashMap<u64, u64>,
+}
+
+impl TruthTable {
+    /// Tabulates a project by enumerating every combination of its top-level `Input` instances, in instance order.
+    /// Ports are named after the labels of the instances.
+    ///
+    /// Fails with [`TruthError::TooManyInputs`] if the inputs are more than `limit` bits wide.
+    pub fn extract(project: &Project, limit: u8) -> Result<Self> {
+        let mut circuit = Circuit::compile(project)?;
+
+        let port = |(instance, label): (InstanceId, Option<&str>), width: u8| Port::new(label
+            .map(str::to_owned)
+            .unwrap_or_else(|| format!("instance{}", instance)), width);
+
+        let inputs = circuit.inputs()
+            .map(|(instance, label)| (instance, port((instance, label), circuit.input(instance).map(|value| value.width()).unwrap_or(0))))
+            .collect::<Vec<_>>();
+        let outputs = circuit.outputs()
+            .map(|(instance, label)| (instance, port((instance, label), circuit.output(instance).map(|value| value.width()).unwrap_or(0))))
+            .collect::<Vec<_>>();
+
+        tabulate(&mut circuit, inputs, outputs, limit)
+    }
+
+    /// Tabulates a subcomponent, matching its ports to the `Input` and `Output` placements inside it by label.
+    pub fn extract_component(project: &Project, component: ComponentId, limit: u8) -> Result<Self> {
+        let mut circuit = Circuit::compile_component(project, component)?;
+        let definition = &project.components[&component];
+
+        let find = |port: &Port, instances: Vec<(InstanceId, Option<&str>)>| instances.into_iter()
+            .find(|(_, label)| *label == Some(port.name.as_str()))
+            .map(|(instance, _)| (instance, port.clone()))
+            .ok_or(CircuitError::PortMismatch { component, port: port.name.clone() });
+
+        let inputs = definition.inputs.iter()
+            .map(|port| find(port, circuit.inputs().collect()))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let outputs = definition.outputs.iter()
+            .map(|port| find(port, circuit.outputs().collect()))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        tabulate(&mut circuit, inputs, outputs, limit)
+    }
+}
+
+fn tabulate(circuit: &mut Circuit, inputs: Vec<(InstanceId, Port)>, outputs: Vec<(InstanceId, Port)>, limit: u8) -> Result<TruthTable> {
+    let bits = |ports: &[(InstanceId, Port)]| ports.iter()
+        .map(|(_, port)| port.width as u32)
+        .sum::<u32>();
+
+    let (input_bits, output_bits) = (bits(&inputs), bits(&outputs));
+
+    if input_bits > limit.min(64) as u32 {
+        return Err(TruthError::TooManyInputs { bits: input_bits, limit }.into());
+    }
+
+    if output_bits > 64 {
+        return Err(TruthError::TooManyOutputs(output_bits).into());
+    }
+
+    if !circuit.is_combinational() {
+        return Err(TruthError::NotCombinational.into());
+    }
+
+    let mut truth = HashMap::new();
+
+    for key in (0..1u128 << input_bits).map(|key| key as u64) {
+        let mut offset = 0;
+
+        for (instance, port) in inputs.iter() {
+            circuit.set_input(*instance, Value::new(64, key).slice(offset, port.width))?;
+            offset += port.width;
+        }
+
+        circuit.evaluate()?;
+
+        let output = outputs.iter()
+            .rev()
+            .map(|(instance, _)| circuit.output(*instance))
+            .try_fold(Value::new(0, 0), |packed, value| value.map(|value| value.concat(packed)))?;
+
+        // An output that is X or Z can't be written into a truth table
+        let output = output.to_u64()
+            .ok_or(TruthError::Unknown { input: key })?;
+
+        truth.insert(key, output);
+    }
+
+    Ok(TruthTable {
+        inputs: inputs.into_iter().map(|(_, port)| port).collect(),
+        outputs: outputs.into_iter().map(|(_, port)| port).collect(),
+        truth,
+    })
+}
+
+#[derive(Debug, Clone)]
+pub enum TruthError {
+    TooManyInputs { bits: u32, limit: u8 },
+    TooManyOutputs(u32),
+    // The circuit has loops, clocks or storage, so its outputs don't follow from its inputs alone
+    NotCombinational,
+    // The packed input for which some output was X or Z
+    Unknown { input: u64 },
+}
+
+impl std::error::Error for TruthError {}
+impl std::fmt::Display for TruthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::tests::{
+        connect,
+        place,
+        project
+    };
+
+    #[test]
+    fn extract() {
+        let mut project = project();
+        place(&mut project, 0, Project::INPUT, "a");
+        place(&mut project, 1, Project::INPUT, "b");
+        place(&mut project, 2, Project::AND, "and");
+        place(&mut project, 3, Project::OUTPUT, "y");
+        connect(&mut project, (0, 0), (2, 0));
+        connect(&mut project, (1, 0), (2, 1));
+        connect(&mut project, (2, 0), (3, 0));
+
+        let table = TruthTable::extract(&project, INPUT_LIMIT).unwrap();
+        assert_eq!(table.inputs.iter().map(|port| port.name.as_str()).collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(table.truth, HashMap::from([(0, 0), (1, 0), (2, 0), (3, 1)]));
+
+        let error = TruthTable::extract(&project, 1).err().unwrap().to_string();
+        assert!(error.contains("TooManyInputs { bits: 2, limit: 1 }"));
+
+        place(&mut project, 4, Project::CLOCK, "clock");
+        let error = TruthTable::extract(&project, INPUT_LIMIT).err().unwrap().to_string();
+        assert!(error.contains("NotCombinational"));
+    }
+
+    #[test]
+    fn input_limit() {
+        let mut project = project();
+
+        for instance in 0..=INPUT_LIMIT as usize {
+            place(&mut project, instance, Project::INPUT, &format!("i{}", instance));
+        }
+
+        let error = TruthTable::extract(&project, INPUT_LIMIT).err().unwrap().to_string();
+        assert!(error.contains("TooManyInputs { bits: 17, limit: 16 }"));
+
+        // Keys hold no more than 64 bits, whatever the limit
+        for instance in INPUT_LIMIT as usize + 1..=64 {
+            place(&mut project, instance, Project::INPUT, &format!("i{}", instance));
+        }
+
+        let error = TruthTable::extract(&project, u8::MAX).err().unwrap().to_string();
+        assert!(error.contains("TooManyInputs { bits: 65, limit: 255 }"));
+    }
+}